@@ -0,0 +1,237 @@
+use crate::crawl::Page;
+use crate::dom;
+use crate::output::escape_html;
+use ego_tree::NodeId;
+use scraper::{ElementRef, Html, Node};
+
+/// Text suggesting a page has admonitions, so pages without any are not parsed.
+const MARKERS: &[&str] = &["admonition", "markdown-alert", "versionmodified"];
+
+/// The kinds of admonition generators use, each with the kind it is shown as.
+const KINDS: &[(&str, &str)] = &[
+    ("note", "note"),
+    ("info", "note"),
+    ("seealso", "note"),
+    ("abstract", "note"),
+    ("summary", "note"),
+    ("tldr", "note"),
+    ("todo", "note"),
+    ("question", "note"),
+    ("example", "note"),
+    ("quote", "note"),
+    ("tip", "tip"),
+    ("hint", "tip"),
+    ("success", "tip"),
+    ("important", "important"),
+    ("warning", "warning"),
+    ("attention", "warning"),
+    ("caution", "caution"),
+    ("danger", "danger"),
+    ("error", "danger"),
+    ("failure", "danger"),
+    ("bug", "danger"),
+];
+
+/// An admonition found in a page.
+struct Admonition {
+    id: NodeId,
+    kind: &'static str,
+    /// The element holding its title, if it has one.
+    title: Option<NodeId>,
+    title_text: String,
+}
+
+/// Rewrites the notes, tips and warnings of every page into one markup, so they look
+/// the same in the output whichever generator made them:
+///
+/// ```html
+/// <div class="admonition warning"><p class="admonition-title">Warning</p>...</div>
+/// ```
+///
+/// This is the markup of Sphinx and MkDocs; mdbook-admonish, Docusaurus and GitHub-style
+/// alerts are brought in line with it, their icons dropped from the titles, and so are
+/// Sphinx's notes of the version a feature was added, changed or deprecated in.
+pub fn normalize(pages: &mut [Page]) {
+    for page in pages {
+        if !MARKERS.iter().any(|marker| page.html.contains(marker)) {
+            continue;
+        }
+        let mut fragment = Html::parse_fragment(&page.html);
+        let admonitions = fragment
+            .root_element()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .filter_map(find)
+            .collect::<Vec<_>>();
+        if admonitions.is_empty() {
+            continue;
+        }
+        for admonition in admonitions {
+            if let Node::Element(element) = fragment.tree.get_mut(admonition.id).unwrap().value() {
+                dom::set_attr(
+                    &mut element.attrs,
+                    "class",
+                    &format!("admonition {}", admonition.kind),
+                );
+            }
+            let title = format!(
+                "<p class=\"admonition-title\">{}</p>",
+                escape_html(&admonition.title_text)
+            );
+            match admonition.title {
+                Some(id) if is_plain_title(&fragment, id) => {}
+                // A title inside the first paragraph, as Sphinx's version notes have, moves
+                // out of it.
+                Some(id) if !is_child(&fragment, id, admonition.id) => {
+                    fragment.tree.get_mut(id).unwrap().detach();
+                    dom::prepend(&mut fragment.tree, admonition.id, &title);
+                }
+                Some(id) => dom::replace(&mut fragment.tree, id, &title),
+                None => dom::prepend(&mut fragment.tree, admonition.id, &title),
+            }
+        }
+        page.html = fragment.root_element().inner_html();
+    }
+}
+
+/// The admonition `element` is, if it is one.
+fn find(element: ElementRef) -> Option<Admonition> {
+    let classes = element.value().classes().collect::<Vec<_>>();
+    let (kind, title_class) = if classes.contains(&"admonition") {
+        let kind = classes
+            .iter()
+            .map(|class| class.strip_prefix("admonish-").unwrap_or(class))
+            .find_map(kind);
+        (kind.unwrap_or("note"), "admonition-title")
+    } else if classes.contains(&"markdown-alert") {
+        let kind = classes
+            .iter()
+            .filter_map(|class| class.strip_prefix("markdown-alert-"))
+            .find_map(kind)?;
+        (kind, "markdown-alert-title")
+    } else if let Some(kind) = classes.iter().find_map(|class| {
+        VERSION_KINDS
+            .iter()
+            .find(|(name, _)| name == class)
+            .map(|(_, kind)| *kind)
+    }) {
+        return version_note(element, kind);
+    } else if classes.contains(&"theme-admonition") {
+        let kind = classes
+            .iter()
+            .filter_map(|class| class.strip_prefix("theme-admonition-"))
+            .find_map(kind)?;
+        // Docusaurus hashes its class names, as in `admonitionHeading_Gvgb`.
+        (kind, "admonitionHeading")
+    } else {
+        return None;
+    };
+
+    let title = element
+        .children()
+        .filter_map(ElementRef::wrap)
+        .find(|child| {
+            child
+                .value()
+                .classes()
+                .any(|class| class.starts_with(title_class))
+        });
+    let title_text = title
+        .map(|title| title.text().collect::<String>().trim().to_string())
+        .filter(|text| !text.is_empty())
+        .map(capitalize)
+        .unwrap_or_else(|| label(kind).to_string());
+    Some(Admonition {
+        id: element.id(),
+        kind,
+        title: title.map(|title| title.id()),
+        title_text,
+    })
+}
+
+/// The kinds of Sphinx's `versionadded`, `versionchanged`, `versionremoved` and
+/// `deprecated` directives, by their class.
+const VERSION_KINDS: &[(&str, &str)] = &[
+    ("versionadded", "note"),
+    ("versionchanged", "note"),
+    ("versionremoved", "caution"),
+    ("deprecated", "caution"),
+];
+
+/// The Sphinx version note `element` is, titled by the `<span class="versionmodified">`
+/// its first paragraph starts with, such as "Deprecated since version 3.1:".
+fn version_note(element: ElementRef, kind: &'static str) -> Option<Admonition> {
+    let title = element
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .find(|descendant| {
+            descendant.value().name() == "span"
+                && descendant
+                    .value()
+                    .classes()
+                    .any(|class| class == "versionmodified")
+        })?;
+    let title_text = title.text().collect::<String>();
+    let title_text = title_text.trim().trim_end_matches(':').trim_end();
+    Some(Admonition {
+        id: element.id(),
+        kind,
+        title: Some(title.id()),
+        title_text: if title_text.is_empty() {
+            label(kind).to_string()
+        } else {
+            title_text.to_string()
+        },
+    })
+}
+
+/// The kind an admonition class stands for.
+fn kind(class: &str) -> Option<&'static str> {
+    KINDS
+        .iter()
+        .find(|(name, _)| *name == class)
+        .map(|(_, kind)| *kind)
+}
+
+/// The title shown for an admonition without one.
+fn label(kind: &str) -> &'static str {
+    match kind {
+        "tip" => "Tip",
+        "important" => "Important",
+        "warning" => "Warning",
+        "caution" => "Caution",
+        "danger" => "Danger",
+        _ => "Note",
+    }
+}
+
+/// The title with a capital first letter, as Docusaurus shows its lowercase titles.
+fn capitalize(title: String) -> String {
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(first) if first.is_lowercase() => first.to_uppercase().chain(chars).collect(),
+        _ => title,
+    }
+}
+
+/// Whether the node `id` is a child of the node `parent`.
+fn is_child(fragment: &Html, id: NodeId, parent: NodeId) -> bool {
+    fragment
+        .tree
+        .get(id)
+        .and_then(|node| node.parent())
+        .is_some_and(|node| node.id() == parent)
+}
+
+/// Whether the title is already a `<p class="admonition-title">` holding only text.
+fn is_plain_title(fragment: &Html, id: NodeId) -> bool {
+    fragment
+        .tree
+        .get(id)
+        .and_then(ElementRef::wrap)
+        .is_some_and(|title| {
+            title.value().name() == "p"
+                && title.value().attr("class") == Some("admonition-title")
+                && title.children().all(|child| child.value().is_text())
+        })
+}
@@ -0,0 +1,66 @@
+use crate::crawl::Page;
+use crate::dom;
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::sync::LazyLock;
+
+/// A code comment that is only an MkDocs Material annotation marker, such as `# (1)!`.
+/// The `!` asks for the comment characters to go, leaving the number.
+static STRIPPED_MARKER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:#|//|--|;|%|/\*|<!--)\s*(\(\d+\))!\s*(?:\*/|-->)?\s*$").unwrap()
+});
+
+/// Annotations as MkDocs Material's script renders them, in pages saved after it ran.
+static RENDERED: LazyLock<Selector> = LazyLock::new(|| Selector::parse(".md-annotation").unwrap());
+
+static INDEX: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("[data-md-annotation-id]").unwrap());
+
+/// Turns the annotations of MkDocs Material, which its script shows as numbered bubbles
+/// with the notes in tooltips, into plain numbers that refer to the list of notes after
+/// the code block or paragraph. The comments only marking an annotation in code, such as
+/// `# (1)!`, are cut down to the number, as the bubbles show them.
+pub fn normalize(pages: &mut [Page]) {
+    for page in pages {
+        if !page.html.contains(")!") && !page.html.contains("md-annotation") {
+            continue;
+        }
+        let mut fragment = Html::parse_fragment(&page.html);
+        let markers = fragment
+            .root_element()
+            .descendants()
+            .filter(|node| {
+                node.ancestors()
+                    .filter_map(ElementRef::wrap)
+                    .any(|ancestor| ancestor.value().name() == "pre")
+            })
+            .filter_map(|node| {
+                let captures = STRIPPED_MARKER.captures(node.value().as_text()?)?;
+                Some((node.id(), captures[1].to_string()))
+            })
+            .collect::<Vec<_>>();
+        let rendered = fragment
+            .select(&RENDERED)
+            .filter_map(|annotation| {
+                let index = annotation
+                    .select(&INDEX)
+                    .next()?
+                    .value()
+                    .attr("data-md-annotation-id")?;
+                Some((annotation.id(), format!("({})", index)))
+            })
+            .collect::<Vec<_>>();
+        if markers.is_empty() && rendered.is_empty() {
+            continue;
+        }
+        for (id, number) in markers {
+            if let Node::Text(text) = fragment.tree.get_mut(id).unwrap().value() {
+                text.text = number.as_str().into();
+            }
+        }
+        for (id, number) in rendered {
+            dom::replace(&mut fragment.tree, id, &number);
+        }
+        page.html = fragment.root_element().inner_html();
+    }
+}
@@ -0,0 +1,422 @@
+use crate::crawl::{Crawler, Page};
+use crate::css;
+use crate::log::{info, warning};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use scraper::{Html, Selector};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use url::Url;
+
+/// How many levels of stylesheets importing other stylesheets are followed.
+const MAX_STYLESHEET_DEPTH: usize = 4;
+
+/// Which files referenced by the pages an output needs.
+pub enum Required {
+    Images,
+    /// Images, stylesheets, fonts and everything else the pages load.
+    Resources,
+}
+
+/// A file referenced by the scraped pages, downloaded so it can be embedded in the output.
+pub struct Asset {
+    pub media_type: String,
+    pub bytes: Vec<u8>,
+}
+
+impl Asset {
+    /// The file as a `data:` URI.
+    pub fn data_uri(&self) -> String {
+        format!(
+            "data:{};base64,{}",
+            self.media_type,
+            BASE64.encode(&self.bytes)
+        )
+    }
+}
+
+/// Downloaded assets, keyed by their absolute URL.
+#[derive(Default)]
+pub struct Assets {
+    files: HashMap<Url, Asset>,
+    /// Files the crawl reached in place of pages, which are attachments whatever their
+    /// extension.
+    crawled: HashSet<Url>,
+}
+
+impl Assets {
+    pub fn get(&self, url: &Url) -> Option<&Asset> {
+        self.files.get(url)
+    }
+
+    /// Whether a link to `url` leads to a file downloaded for `--attachments`.
+    pub fn is_attachment(&self, url: &Url, extensions: &[String]) -> bool {
+        self.files.contains_key(url)
+            && (self.crawled.contains(url) || has_extension(url, extensions))
+    }
+
+    /// Absolute URLs of the downloaded files a stylesheet refers to.
+    pub fn dependencies(&self, url: &Url) -> Vec<Url> {
+        match self.files.get(url) {
+            Some(asset) if asset.media_type == "text/css" => {
+                css::url_references(&String::from_utf8_lossy(&asset.bytes))
+                    .into_iter()
+                    .filter_map(|reference| absolute_url(url, reference))
+                    .filter(|dependency| self.files.contains_key(dependency))
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The asset as a `data:` URI. Stylesheets have the fonts, images and stylesheets
+    /// they refer to inlined as well, so they load nothing from the network.
+    pub fn data_uri(&self, url: &Url) -> Option<String> {
+        self.data_uri_at_depth(url, 0)
+    }
+
+    fn data_uri_at_depth(&self, url: &Url, depth: usize) -> Option<String> {
+        let asset = self.files.get(url)?;
+        let encoded = if asset.media_type == "text/css" && depth < MAX_STYLESHEET_DEPTH {
+            let stylesheet = String::from_utf8_lossy(&asset.bytes);
+            let inlined = css::rewrite_urls(&stylesheet, |reference| {
+                url.join(reference)
+                    .ok()
+                    .and_then(|dependency| self.data_uri_at_depth(&dependency, depth + 1))
+            });
+            BASE64.encode(inlined)
+        } else {
+            BASE64.encode(&asset.bytes)
+        };
+        Some(format!("data:{};base64,{}", asset.media_type, encoded))
+    }
+}
+
+/// Absolute URLs of the images shown on a page, in document order.
+pub fn image_urls(page: &Page) -> Vec<Url> {
+    let fragment = Html::parse_fragment(&page.html);
+    let selector = Selector::parse("img[src]").unwrap();
+    fragment
+        .select(&selector)
+        .filter_map(|img| img.value().attr("src"))
+        .filter_map(|src| absolute_url(&page.url, src))
+        .collect()
+}
+
+/// Absolute URLs of everything a page loads: images, stylesheets, icons, media posters
+/// and the files referenced from inline styles.
+pub fn resource_urls(page: &Page) -> Vec<Url> {
+    let fragment = Html::parse_fragment(&page.html);
+    let selector = Selector::parse(
+        "img[src], source[src], video[src], video[poster], audio[src], \
+         link[rel~='stylesheet'][href], link[rel~='icon'][href], [style], style",
+    )
+    .unwrap();
+    let mut references = Vec::new();
+    for element in fragment.select(&selector) {
+        let value = element.value();
+        match value.name() {
+            "style" => {
+                let stylesheet = element.text().collect::<String>();
+                references.extend(
+                    css::url_references(&stylesheet)
+                        .into_iter()
+                        .map(String::from),
+                );
+            }
+            "link" => references.extend(value.attr("href").map(String::from)),
+            "video" => {
+                references.extend(value.attr("src").map(String::from));
+                references.extend(value.attr("poster").map(String::from));
+            }
+            _ => references.extend(value.attr("src").map(String::from)),
+        }
+        if let Some(style) = value.attr("style") {
+            references.extend(css::url_references(style).into_iter().map(String::from));
+        }
+    }
+    references
+        .iter()
+        .filter_map(|reference| absolute_url(&page.url, reference))
+        .collect()
+}
+
+/// Absolute URLs of everything a page links to, without their fragments, in document
+/// order.
+pub fn link_urls(page: &Page) -> Vec<Url> {
+    let fragment = Html::parse_fragment(&page.html);
+    let selector = Selector::parse("a[href]").unwrap();
+    fragment
+        .select(&selector)
+        .filter_map(|link| link.value().attr("href"))
+        .filter_map(|href| absolute_url(&page.url, href))
+        .map(|mut url| {
+            url.set_fragment(None);
+            url
+        })
+        .collect()
+}
+
+/// Whether the extension of `url` is one of `extensions`, such as `pdf` for a datasheet.
+fn has_extension(url: &Url, extensions: &[String]) -> bool {
+    url.path().rsplit_once('.').is_some_and(|(_, extension)| {
+        extensions
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(extension))
+    })
+}
+
+/// Adds the files the pages link to whose extension is one of `extensions`, such as PDF
+/// datasheets and example archives, for `--attachments`, leaving out those larger than
+/// `max_size` bytes and links that turn out to lead to a web page. Files the crawl itself
+/// came across are added without being fetched again.
+pub async fn download_attachments(
+    crawler: Arc<Crawler>,
+    pages: &[Page],
+    extensions: &[String],
+    max_size: usize,
+    assets: &mut Assets,
+) {
+    for (url, asset) in crawler.take_files() {
+        assets.crawled.insert(url.clone());
+        assets.files.insert(url, asset);
+    }
+    let mut seen = HashSet::new();
+    let mut tasks = JoinSet::new();
+    for url in pages
+        .iter()
+        .flat_map(link_urls)
+        .filter(|url| has_extension(url, extensions))
+    {
+        if assets.files.contains_key(&url) || !seen.insert(url.clone()) {
+            continue;
+        }
+        let crawler = crawler.clone();
+        tasks.spawn(async move {
+            let fetched = crawler.fetch_bytes(&url, Some(max_size)).await;
+            (url, fetched)
+        });
+    }
+    if tasks.is_empty() {
+        return;
+    }
+    info!("Downloading attachments...");
+    while let Some(joined) = tasks.join_next().await {
+        let Ok((url, fetched)) = joined else {
+            continue;
+        };
+        match fetched {
+            Ok((Some(media_type), _)) if media_type == "text/html" => {
+                warning!("Not saving {}: it is a web page", url)
+            }
+            Ok((media_type, bytes)) => {
+                let media_type = media_type
+                    .or_else(|| media_type_from_extension(&url).map(str::to_string))
+                    .unwrap_or_else(|| "application/octet-stream".to_string());
+                assets.files.insert(url, Asset { media_type, bytes });
+            }
+            Err(e) => warning!("Failed to download {}: {}", url, e),
+        }
+    }
+}
+
+/// Downloads every image used by `pages`. Images that fail to download are left out
+/// with a warning, so the output links to them on the origin site instead.
+pub async fn download_images(crawler: Arc<Crawler>, pages: &[Page]) -> Assets {
+    let urls = pages.iter().flat_map(image_urls).collect::<Vec<_>>();
+    if urls.is_empty() {
+        return Assets::default();
+    }
+    info!("Downloading images...");
+    let mut files = HashMap::new();
+    download(&crawler, urls, true, &mut files).await;
+    Assets {
+        files,
+        crawled: HashSet::new(),
+    }
+}
+
+/// Downloads everything `pages` load, including the fonts and images their stylesheets
+/// refer to, so the output can be made to work offline.
+pub async fn download_resources(crawler: Arc<Crawler>, pages: &[Page]) -> Assets {
+    let urls = pages.iter().flat_map(resource_urls).collect::<Vec<_>>();
+    if urls.is_empty() {
+        return Assets::default();
+    }
+    info!("Downloading images and stylesheets...");
+    let mut files = HashMap::new();
+    download_with_dependencies(&crawler, urls, &mut files).await;
+    Assets {
+        files,
+        crawled: HashSet::new(),
+    }
+}
+
+/// Adds the stylesheets the pages link to from their `<head>`, for `--site-css`, with the
+/// stylesheets, fonts and images they load.
+pub async fn download_stylesheets(crawler: Arc<Crawler>, pages: &[Page], assets: &mut Assets) {
+    let urls = pages
+        .iter()
+        .flat_map(|page| page.stylesheets.iter().cloned())
+        .collect::<Vec<_>>();
+    if urls.is_empty() {
+        return;
+    }
+    info!("Downloading the site's stylesheets...");
+    download_with_dependencies(&crawler, urls, &mut assets.files).await;
+}
+
+/// Adds the icons of the pages, for `--favicon`, and the `cover_image`, which is read from
+/// disk if it is a `file:` URL.
+pub async fn download_icons(
+    crawler: Arc<Crawler>,
+    pages: &[Page],
+    cover_image: Option<&Url>,
+    assets: &mut Assets,
+) {
+    let mut urls = pages
+        .iter()
+        .filter_map(|page| page.icon.clone())
+        .collect::<Vec<_>>();
+    match cover_image {
+        Some(url) if url.scheme() == "file" => {
+            let path = url.to_file_path().unwrap_or_default();
+            match fs::read(&path) {
+                Ok(bytes) => {
+                    let media_type = media_type_from_extension(url).unwrap_or("image/png");
+                    let asset = Asset {
+                        media_type: media_type.to_string(),
+                        bytes,
+                    };
+                    assets.files.insert(url.clone(), asset);
+                }
+                Err(e) => warning!("Failed to read cover image {}: {}", path.display(), e),
+            }
+        }
+        Some(url) => urls.push(url.clone()),
+        None => {}
+    }
+    if urls.is_empty() {
+        return;
+    }
+    info!("Downloading the icon and cover image...");
+    download(&crawler, urls, true, &mut assets.files).await;
+}
+
+/// Downloads `urls`, then whatever the stylesheets among them load, and so on.
+async fn download_with_dependencies(
+    crawler: &Arc<Crawler>,
+    mut urls: Vec<Url>,
+    files: &mut HashMap<Url, Asset>,
+) {
+    for _ in 0..=MAX_STYLESHEET_DEPTH {
+        let stylesheets = download(crawler, urls, false, files).await;
+        urls = stylesheets
+            .iter()
+            .flat_map(|url| {
+                let stylesheet = String::from_utf8_lossy(&files[url].bytes).into_owned();
+                css::url_references(&stylesheet)
+                    .into_iter()
+                    .filter_map(|reference| absolute_url(url, reference))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        if urls.is_empty() {
+            break;
+        }
+    }
+}
+
+/// Downloads the URLs not already in `files`, adding them to it. With `images_only`,
+/// files that are not images are left out. Returns the URLs of downloaded stylesheets.
+async fn download(
+    crawler: &Arc<Crawler>,
+    urls: Vec<Url>,
+    images_only: bool,
+    files: &mut HashMap<Url, Asset>,
+) -> Vec<Url> {
+    let mut seen = HashSet::new();
+    let mut tasks = JoinSet::new();
+    for url in urls {
+        if files.contains_key(&url) || !seen.insert(url.clone()) {
+            continue;
+        }
+        let crawler = crawler.clone();
+        tasks.spawn(async move {
+            let fetched = crawler.fetch_bytes(&url, None).await;
+            (url, fetched)
+        });
+    }
+
+    let mut stylesheets = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        let Ok((url, fetched)) = joined else {
+            continue;
+        };
+        match fetched {
+            Ok((content_type, bytes)) => {
+                let media_type = content_type
+                    .filter(|media_type| !images_only || media_type.starts_with("image/"))
+                    .filter(|media_type| media_type != "application/octet-stream")
+                    .or_else(|| media_type_from_extension(&url).map(str::to_string))
+                    .filter(|media_type| !images_only || media_type.starts_with("image/"));
+                match media_type {
+                    Some(media_type) => {
+                        if media_type == "text/css" {
+                            stylesheets.push(url.clone());
+                        }
+                        files.insert(url, Asset { media_type, bytes });
+                    }
+                    None if images_only => warning!("Not embedding {}: it is not an image", url),
+                    None => {
+                        let media_type = "application/octet-stream".to_string();
+                        files.insert(url, Asset { media_type, bytes });
+                    }
+                }
+            }
+            Err(e) => warning!("Failed to download {}: {}", url, e),
+        }
+    }
+    stylesheets
+}
+
+/// File extension conventionally used for a media type, e.g. `png` for `image/png`.
+pub fn extension_for(media_type: &str) -> &str {
+    match media_type {
+        "image/jpeg" => "jpg",
+        "image/svg+xml" => "svg",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        _ => media_type
+            .rsplit('/')
+            .next()
+            .filter(|subtype| subtype.chars().all(|c| c.is_ascii_alphanumeric()))
+            .unwrap_or("bin"),
+    }
+}
+
+fn absolute_url(base: &Url, reference: &str) -> Option<Url> {
+    let url = base.join(reference).ok()?;
+    (url.scheme() == "http" || url.scheme() == "https").then_some(url)
+}
+
+fn media_type_from_extension(url: &Url) -> Option<&'static str> {
+    let extension = url.path().rsplit_once('.')?.1.to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "svg" => Some("image/svg+xml"),
+        "webp" => Some("image/webp"),
+        "avif" => Some("image/avif"),
+        "ico" => Some("image/x-icon"),
+        "css" => Some("text/css"),
+        "woff" => Some("font/woff"),
+        "woff2" => Some("font/woff2"),
+        "ttf" => Some("font/ttf"),
+        "otf" => Some("font/otf"),
+        "eot" => Some("application/vnd.ms-fontobject"),
+        _ => None,
+    }
+}
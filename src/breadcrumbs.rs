@@ -0,0 +1,119 @@
+use crate::crawl::Page;
+use scraper::{ElementRef, Html, Selector};
+use std::sync::LazyLock;
+
+/// Breadcrumb trails: the ARIA and schema.org markup, and the classes of common themes
+/// (Read the Docs, Docusaurus, MkDocs Material, Bootstrap), and rustdoc's path to an item.
+static TRAILS: LazyLock<Selector> = LazyLock::new(|| {
+    Selector::parse(
+        "nav[aria-label='breadcrumb' i], nav[aria-label='breadcrumbs' i], \
+         [itemtype$='BreadcrumbList'], .wy-breadcrumbs, .theme-doc-breadcrumbs, .md-path, \
+         .rustdoc-breadcrumbs, ol.breadcrumb, ul.breadcrumb, .breadcrumbs, .breadcrumb",
+    )
+    .unwrap()
+});
+
+static ITEMS: LazyLock<Selector> = LazyLock::new(|| Selector::parse("li").unwrap());
+
+static LINKS: LazyLock<Selector> = LazyLock::new(|| Selector::parse("a").unwrap());
+
+/// Characters themes put between breadcrumbs.
+const SEPARATORS: &[char] = &['»', '›', '>', '/', '|', '·', '→', '\u{a0}'];
+
+/// Classes of items in a trail that are not breadcrumbs, such as Read the Docs's
+/// "Edit on GitHub" link.
+const ASIDES: &[&str] = &["wy-breadcrumbs-aside"];
+
+/// The titles of the sections above a page in its site's hierarchy, outermost first, from
+/// the page's breadcrumb trail. The page itself, which trails usually end with, and items
+/// without text, such as a home icon, are left out. Empty for a page without a trail.
+pub fn trail(document: &Html) -> Vec<String> {
+    let Some(trail) = document.select(&TRAILS).next() else {
+        return Vec::new();
+    };
+    let items = trail
+        .select(&ITEMS)
+        .filter(|item| !is_nested(*item, trail))
+        .filter(|item| !item.value().classes().any(|class| ASIDES.contains(&class)))
+        .collect::<Vec<_>>();
+    let items = if items.is_empty() {
+        trail.select(&LINKS).collect()
+    } else {
+        items
+    };
+    let mut titles = items
+        .iter()
+        .map(|item| (text(*item), is_current(*item)))
+        .filter(|(title, _)| !title.is_empty())
+        .collect::<Vec<_>>();
+    // The last item names the page itself unless it links somewhere else.
+    if titles.last().is_some_and(|(_, current)| *current) {
+        titles.pop();
+    }
+    titles.into_iter().map(|(title, _)| title).collect()
+}
+
+/// Where each of `pages` sits in the hierarchy of the docs, as the sections above it,
+/// outermost first, and how deep that is. The sections come from the breadcrumb trails,
+/// without the levels every trail starts with, such as "Home" or "Docs". When no page
+/// has a trail the crawl is the hierarchy: a page is one level below the page whose link
+/// led to it, and has no sections.
+pub fn hierarchy<'a>(pages: &[&'a Page]) -> Vec<(usize, &'a [String])> {
+    let trails = pages
+        .iter()
+        .map(|page| page.breadcrumbs.as_slice())
+        .filter(|trail| !trail.is_empty())
+        .collect::<Vec<_>>();
+    let Some(first) = trails.first() else {
+        return pages
+            .iter()
+            .map(|page| (page.order.len().saturating_sub(1), &[][..]))
+            .collect();
+    };
+    let shared = trails.iter().fold(first.len(), |shared, trail| {
+        first
+            .iter()
+            .zip(trail.iter())
+            .take(shared)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+    pages
+        .iter()
+        .map(|page| {
+            let sections = page.breadcrumbs.get(shared..).unwrap_or_default();
+            (sections.len(), sections)
+        })
+        .collect()
+}
+
+/// Whether the item belongs to a list nested in another item, such as a dropdown of
+/// sibling pages, rather than to the trail.
+fn is_nested(item: ElementRef, trail: ElementRef) -> bool {
+    item.ancestors()
+        .take_while(|node| node.id() != trail.id())
+        .filter_map(ElementRef::wrap)
+        .any(|ancestor| ancestor.value().name() == "li")
+}
+
+/// Whether a trail item may stand for the page it is on: it is marked `aria-current` or
+/// links nowhere.
+fn is_current(item: ElementRef) -> bool {
+    let marked = std::iter::once(item)
+        .chain(item.descendants().filter_map(ElementRef::wrap))
+        .any(|element| element.value().attr("aria-current").is_some());
+    let linked = item.value().name() == "a" || item.select(&LINKS).next().is_some();
+    marked || !linked
+}
+
+/// The item's text, with whitespace collapsed and the separators around it removed.
+fn text(item: ElementRef) -> String {
+    let text = item
+        .text()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    text.trim_matches(|c: char| c.is_whitespace() || SEPARATORS.contains(&c))
+        .to_string()
+}
@@ -0,0 +1,81 @@
+use crate::config::Options;
+use clap::{ArgAction, Parser, Subcommand};
+use clap_complete::Shell;
+use std::path::PathBuf;
+
+/// Name of the installed binary, used in generated completion scripts.
+pub const BIN_NAME: &str = "documentation_scraper";
+
+/// Crawl a documentation site and combine its pages into a single HTML file.
+#[derive(Parser, Debug)]
+#[command(name = "documentation_scraper", version, about)]
+pub struct Cli {
+    /// TOML config file with per-site settings. Defaults to ./scraper.toml when present.
+    ///
+    /// Can also be set with DOCSCRAPER_CONFIG.
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Print more detail: -v logs every request, -vv also logs response headers.
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Only print errors.
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Read additional start URLs from standard input, one per line.
+    #[arg(long)]
+    pub stdin: bool,
+
+    /// Follow the crawl and print the ordered list of pages that would be scraped,
+    /// without writing any output.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub options: Options,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print a shell completion script, e.g. `documentation_scraper completions zsh > _documentation_scraper`.
+    ///
+    /// Completions include the names of profiles, output formats and other fixed choices.
+    Completions {
+        /// Shell to generate the script for.
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Search an index written with `--format tantivy`, e.g.
+    /// `documentation_scraper search rust_book.index "borrow checker"`.
+    Search {
+        /// Index directory.
+        index: PathBuf,
+
+        /// Words or "phrases" to look for; `title:word` searches titles only.
+        query: String,
+
+        /// Maximum number of results.
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Scrape the API documentation of a crate from docs.rs, e.g.
+    /// `documentation_scraper docs-rs serde@1.0`.
+    ///
+    /// The crate's modules and their items are crawled from its root module, keeping their
+    /// hierarchy. Every other option can follow the crate.
+    DocsRs {
+        /// Crate name, optionally with `@` and a version; the latest release otherwise.
+        #[arg(value_name = "CRATE[@VERSION]")]
+        krate: String,
+
+        #[command(flatten)]
+        options: Box<Options>,
+    },
+}
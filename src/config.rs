@@ -0,0 +1,1058 @@
+use crate::filter::{Scope, UrlFilter};
+use crate::highlight::{self, DEFAULT_THEME};
+use crate::locale::{normalize, with_language};
+use crate::output::validate_template;
+use crate::policy::FailOn;
+use crate::profile::Profile;
+use crate::version::with_doc_version;
+use clap::{Args, ValueEnum};
+use scraper::Selector;
+use serde::{Deserialize, Deserializer};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use url::Url;
+
+/// Config file picked up from the working directory when `--config` is not given.
+pub const DEFAULT_CONFIG_FILE: &str = "scraper.toml";
+
+const DEFAULT_START_URL: &str = "https://doc.rust-lang.org/stable/book/title-page.html";
+/// Output file used when none is given and no site title could be found.
+pub const DEFAULT_OUTPUT: &str = "scraped_book_concurrent.html";
+const DEFAULT_CONCURRENCY: usize = 50;
+/// What `--attachments` downloads without `--attachment-type`.
+const DEFAULT_ATTACHMENT_TYPES: &[&str] = &[
+    "pdf", "zip", "tar", "gz", "tgz", "bz2", "xz", "7z", "csv", "xls", "xlsx", "doc", "docx",
+    "ppt", "pptx", "odt", "ods", "odp", "epub", "ipynb",
+];
+const DEFAULT_MAX_ATTACHMENT_SIZE: usize = 20_000_000;
+const DEFAULT_USER_AGENT: &str = concat!("documentation_scraper/", env!("CARGO_PKG_VERSION"));
+
+/// Prefix of the environment variables read by [`Options::from_env`].
+pub const ENV_PREFIX: &str = "DOCSCRAPER_";
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Html,
+    Markdown,
+    Text,
+    Jsonl,
+    Epub,
+    Pdf,
+    Mdbook,
+    Warc,
+    Zip,
+    Sqlite,
+    Tantivy,
+    Mirror,
+    Pandoc,
+    Latex,
+    Asciidoc,
+    Org,
+    Chunks,
+}
+
+/// Extra text added to output file names.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputSuffix {
+    /// The local time the run started, e.g. `book-20240131-154500.html`.
+    Timestamp,
+}
+
+/// Compression applied to output files.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// `.gz`, readable everywhere.
+    Gzip,
+    /// `.zst`, smaller and faster.
+    Zstd,
+}
+
+/// Settings that can come from the command line or a config file.
+///
+/// Every field is optional so that layers can be merged, see [`Options::merge`].
+/// Flags take an optional value, e.g. `--force=false`, to switch off one a lower layer
+/// sets.
+#[derive(Args, Deserialize, Debug, Default)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Options {
+    /// Page to start crawling from. Repeat to crawl several independent sites or books.
+    #[arg(short, long = "url", value_name = "URL")]
+    #[serde(rename = "url", deserialize_with = "one_or_many")]
+    pub urls: Vec<String>,
+
+    /// File with additional start URLs, one per line. Blank lines and lines starting with # are skipped.
+    #[arg(long)]
+    pub url_file: Option<PathBuf>,
+
+    /// Write each start URL's pages to its own numbered output file instead of one combined file.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub separate_outputs: Option<bool>,
+
+    /// Scrape this release of versioned docs, e.g. "nightly" or "1.70.0", by rewriting the
+    /// version segment of the start URLs, which on Read the Docs follows the language.
+    /// Links to other versions are not followed.
+    #[arg(long, value_name = "VERSION")]
+    pub doc_version: Option<String>,
+
+    /// Language to scrape, e.g. "ja". Rewrites the language segment of the start URLs and skips
+    /// pages in other languages. Without it, each crawl stays in the language of its first page.
+    #[arg(long)]
+    pub lang: Option<String>,
+
+    /// Preset selectors for a known documentation generator. Detected from the first page when unset.
+    #[arg(short, long, value_enum)]
+    pub profile: Option<Profile>,
+
+    /// CSS selector for the element holding each page's content. Overrides the profile.
+    #[arg(long)]
+    pub content_selector: Option<String>,
+
+    /// CSS selector for the link pointing to the next page. Overrides the profile.
+    #[arg(long)]
+    pub next_selector: Option<String>,
+
+    /// CSS selector for extra links to follow from every page, e.g. "nav a", for
+    /// crawls that go beyond the next-page chain.
+    #[arg(long)]
+    pub follow_selector: Option<String>,
+
+    /// CSS selector for the table of contents on the start page, e.g. "nav.sidebar", for
+    /// sites without next links: every page it links to is scraped in its order, nested as
+    /// its lists are, and next links are not followed.
+    #[arg(long)]
+    pub toc_selector: Option<String>,
+
+    /// CSS selector for boilerplate to remove from the content, e.g. "div.ad". Repeatable;
+    /// adds to the built-in list of navigation bars, sidebars, edit buttons, cookie banners
+    /// and footers.
+    #[arg(long = "strip-selector", value_name = "SELECTOR")]
+    #[serde(rename = "strip-selector")]
+    pub strip_selectors: Vec<String>,
+
+    /// Keep the navigation bars, sidebars and other boilerplate the built-in list removes.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub keep_boilerplate: Option<bool>,
+
+    /// Run the content through an allowlist sanitizer that removes scripts, event handlers,
+    /// styles and unsafe URLs, for opening scrapes of untrusted sites.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub sanitize: Option<bool>,
+
+    /// Keep the `<script>` elements of the content, which are removed by default along
+    /// with analytics frames and tracking pixels.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub keep_scripts: Option<bool>,
+
+    /// Keep pages whose content another page already has, such as `/` and `/index.html`,
+    /// which are dropped by default and listed as aliases of the first in the manifest.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub keep_duplicates: Option<bool>,
+
+    /// Keep embedded videos, frames and plugins, such as YouTube players and CodePen
+    /// demos, which are replaced by default with a placeholder linking to what they show.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub keep_embeds: Option<bool>,
+
+    /// How many followed links deep to crawl from each start URL. Next-page links do not count.
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Which discovered URLs may be crawled, relative to the start URL [default: same-domain].
+    #[arg(long, value_enum)]
+    pub scope: Option<Scope>,
+
+    /// Only crawl discovered URLs matching this regex. Repeatable; start URLs are always crawled.
+    #[arg(long = "include-url", value_name = "REGEX")]
+    #[serde(rename = "include-url")]
+    pub include_urls: Vec<String>,
+
+    /// Never crawl discovered URLs matching this regex. Repeatable.
+    #[arg(long = "exclude-url", value_name = "REGEX")]
+    #[serde(rename = "exclude-url")]
+    pub exclude_urls: Vec<String>,
+
+    /// File to write the combined output to. Named after the site title when unset.
+    ///
+    /// `sqlite:<path>` stores the pages in a SQLite database instead, updating it in place
+    /// on later runs.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Overwrite the output file if it already exists.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub force: Option<bool>,
+
+    /// Add a suffix to output file names so repeated runs do not collide.
+    #[arg(long, value_enum)]
+    pub output_suffix: Option<OutputSuffix>,
+
+    /// Compress each output file as it is written, adding `.gz` or `.zst` to its name.
+    /// Outputs that are directories or databases are written uncompressed.
+    #[arg(long, value_enum)]
+    pub compress: Option<Compression>,
+
+    /// Write each chapter to disk as soon as every chapter before it has been written,
+    /// instead of holding the whole site in memory until the crawl ends. Works with the
+    /// jsonl, markdown, text and chunks formats.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub stream: Option<bool>,
+
+    /// Output format. Separate several with commas, e.g. `html,epub`, to write each of them
+    /// from the same crawl; their outputs share the output name and differ in extension.
+    #[arg(short, long, value_enum, value_delimiter = ',')]
+    #[serde(deserialize_with = "one_or_many")]
+    pub format: Vec<OutputFormat>,
+
+    /// Write one file per chapter into the output directory instead of one combined file.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub split_chapters: Option<bool>,
+
+    /// Split the HTML output into volumes of at most this size, e.g. "2MB", with links
+    /// between them and an index page. The size counts the chapters' HTML.
+    #[arg(long, value_name = "SIZE")]
+    pub split_size: Option<String>,
+
+    /// Split the HTML output into volumes of this many chapters, with links between them
+    /// and an index page.
+    #[arg(long, value_name = "N")]
+    pub split_every: Option<usize>,
+
+    /// Limit `--format chunks` to this many tokens per chunk, counted with the cl100k
+    /// tokenizer used by OpenAI embedding models, instead of 2000 characters.
+    #[arg(long, value_name = "N")]
+    pub max_tokens: Option<usize>,
+
+    /// How much of the end of each chunk `--format chunks` repeats at the start of the
+    /// next: tokens with `--max-tokens` (default an eighth of it), otherwise characters
+    /// (default 200).
+    #[arg(long, value_name = "N")]
+    pub overlap: Option<usize>,
+
+    /// Embed images, stylesheets and fonts as data URIs so the HTML output works offline
+    /// without loading anything from the site.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub self_contained: Option<bool>,
+
+    /// Download the images of the HTML output and save them next to it, in `<name>_files`
+    /// beside a single file or `images` inside a directory, so they show offline.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub localize_images: Option<bool>,
+
+    /// With `--localize-images`, embed images smaller than this many bytes as data URIs
+    /// instead of saving them as files.
+    #[arg(long, value_name = "BYTES")]
+    pub inline_images_under: Option<usize>,
+
+    /// Also download the files the pages link to, such as PDF datasheets and example
+    /// archives, save them in an `attachments` directory beside the HTML, Markdown or text
+    /// output and point the links there.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub attachments: Option<bool>,
+
+    /// File extension of the links `--attachments` downloads, e.g. "pdf". Repeatable;
+    /// replaces the default list of documents, spreadsheets, notebooks and archives.
+    #[arg(long = "attachment-type", value_name = "EXTENSION")]
+    #[serde(rename = "attachment-type")]
+    pub attachment_types: Vec<String>,
+
+    /// Largest file `--attachments` downloads, e.g. "50MB" [default: 20MB].
+    #[arg(long, value_name = "SIZE")]
+    pub max_attachment_size: Option<String>,
+
+    /// Download the SVG images of the pages and put their markup in place of the `<img>`
+    /// tags, so diagrams scale with the text in every output and work offline.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub inline_svg: Option<bool>,
+
+    /// Download the site's stylesheets and embed them in the HTML and PDF output, keeping
+    /// only the rules for the scraped content, so the offline copy looks like the site.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub site_css: Option<bool>,
+
+    /// Colour the code blocks whose language is known from their classes, with inline
+    /// styles, so the HTML, EPUB and PDF output shows highlighted code without scripts.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub highlight: Option<bool>,
+
+    /// Theme for `--highlight`, e.g. `base16-ocean.dark` or `Solarized (light)`
+    /// (default InspiredGitHub).
+    #[arg(long, value_name = "NAME")]
+    pub highlight_theme: Option<String>,
+
+    /// Convert TeX formulas meant for MathJax or KaTeX into MathML, which displays
+    /// without their scripts.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub math: Option<bool>,
+
+    /// Open every `<details>` element and theme collapsible, so their content shows in
+    /// the PDF and EPUB output instead of staying folded away.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub expand_details: Option<bool>,
+
+    /// Number the footnotes through the whole book instead of restarting on every page,
+    /// with ids unique across the book.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub renumber_footnotes: Option<bool>,
+
+    /// Number the chapters from the structure of the crawl (1, 1.1, 1.2, 2, ...) in their
+    /// headings and in the table of contents.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub number_chapters: Option<bool>,
+
+    /// Renumber the headings of every chapter so they run from `<h1>` down without gaps,
+    /// giving the combined document one consistent outline.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub normalize_headings: Option<bool>,
+
+    /// Shrink the HTML output: drop comments, collapse whitespace outside `<pre>` and
+    /// minify inline CSS.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub minify: Option<bool>,
+
+    /// Make the output byte-identical for the same pages: sort attributes, trim trailing
+    /// whitespace and leave out generation times and fetch timings.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub reproducible: Option<bool>,
+
+    /// Also write `<output>.manifest.json`, listing every fetched page with its status,
+    /// SHA-256, title, size and fetch time, and every page that failed.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub manifest: Option<bool>,
+
+    /// Also write `<output>.alt-text.json`, listing the images of every page that have no
+    /// alt text, so they can be fixed on the site before the book is published.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub alt_text_report: Option<bool>,
+
+    /// Start the book with a title page giving its title, where and when it was scraped
+    /// and the documentation version, for the HTML, EPUB and PDF output.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub cover: Option<bool>,
+
+    /// Download the site's favicon and embed it as the icon of the HTML output and, without
+    /// `--cover-image`, the cover image of the EPUB, so the book is easy to recognise.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub favicon: Option<bool>,
+
+    /// Image to embed as the cover of the EPUB and, without `--favicon`, the icon of the
+    /// HTML output: a local file or a URL.
+    #[arg(long, value_name = "FILE|URL")]
+    pub cover_image: Option<String>,
+
+    /// End the book with an attribution section listing the license and copyright
+    /// statements found in the pages' footers and the sites they apply to, for
+    /// redistributing the scrape under their terms.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub attribution: Option<bool>,
+
+    /// End the HTML, PDF and EPUB output with a back-of-book index of the terms the pages
+    /// define or emphasize, their code identifiers and their section headings, each
+    /// linking to the chapters it appears in.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub term_index: Option<bool>,
+
+    /// End every chapter with a footer giving the URL it was scraped from and when it was
+    /// fetched.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub provenance: Option<bool>,
+
+    /// Also give the SHA-256 of the page in the footer; implies `--provenance`.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub provenance_hash: Option<bool>,
+
+    /// Number the links to other sites in the PDF and LaTeX output and list their URLs at
+    /// the end of each chapter, so printed copies keep where they lead.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub link_footnotes: Option<bool>,
+
+    /// Add a search box to the HTML output that searches every chapter in the browser.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub search: Option<bool>,
+
+    /// Tera template for the HTML output, replacing the built-in layout.
+    #[arg(long, value_name = "FILE")]
+    pub template: Option<PathBuf>,
+
+    /// Stylesheet to add to the HTML, PDF and EPUB output after the built-in styles, to
+    /// restyle the book without a template. Repeatable; later files win.
+    #[arg(long, value_name = "FILE")]
+    pub css: Vec<PathBuf>,
+
+    /// Chrome, Chromium or Edge executable used to print `--format pdf`. Looked up on PATH by default.
+    #[arg(long, value_name = "PATH")]
+    pub browser: Option<PathBuf>,
+
+    /// Maximum number of requests in flight at once.
+    #[arg(short = 'j', long)]
+    pub concurrency: Option<usize>,
+
+    /// Maximum number of requests started per second.
+    #[arg(long, value_name = "PER_SECOND")]
+    pub rate_limit: Option<f64>,
+
+    /// Stop after fetching this many pages and write whatever was collected.
+    #[arg(long, value_name = "N")]
+    pub max_pages: Option<usize>,
+
+    /// Stop gracefully after this much time, e.g. "10m" or "1h 30m", and write what was collected.
+    #[arg(long, value_name = "DURATION")]
+    pub max_duration: Option<String>,
+
+    /// When failed pages make the run exit with an error: any, none, or threshold:<percent>
+    /// such as threshold:5% [default: none].
+    #[arg(long, value_name = "POLICY")]
+    pub fail_on: Option<String>,
+
+    /// Proxy to send every request through, e.g. http://localhost:3128.
+    #[arg(long)]
+    pub proxy: Option<String>,
+
+    /// User-Agent header sent with every request.
+    #[arg(long)]
+    pub user_agent: Option<String>,
+}
+
+impl Options {
+    /// Fills every unset field from `lower`, keeping the values already set here.
+    pub fn merge(self, lower: Options) -> Options {
+        Options {
+            urls: or_vec(self.urls, lower.urls),
+            url_file: self.url_file.or(lower.url_file),
+            separate_outputs: self.separate_outputs.or(lower.separate_outputs),
+            doc_version: self.doc_version.or(lower.doc_version),
+            lang: self.lang.or(lower.lang),
+            profile: self.profile.or(lower.profile),
+            content_selector: self.content_selector.or(lower.content_selector),
+            next_selector: self.next_selector.or(lower.next_selector),
+            follow_selector: self.follow_selector.or(lower.follow_selector),
+            toc_selector: self.toc_selector.or(lower.toc_selector),
+            strip_selectors: or_vec(self.strip_selectors, lower.strip_selectors),
+            keep_boilerplate: self.keep_boilerplate.or(lower.keep_boilerplate),
+            sanitize: self.sanitize.or(lower.sanitize),
+            keep_scripts: self.keep_scripts.or(lower.keep_scripts),
+            keep_duplicates: self.keep_duplicates.or(lower.keep_duplicates),
+            keep_embeds: self.keep_embeds.or(lower.keep_embeds),
+            max_depth: self.max_depth.or(lower.max_depth),
+            scope: self.scope.or(lower.scope),
+            include_urls: or_vec(self.include_urls, lower.include_urls),
+            exclude_urls: or_vec(self.exclude_urls, lower.exclude_urls),
+            output: self.output.or(lower.output),
+            force: self.force.or(lower.force),
+            output_suffix: self.output_suffix.or(lower.output_suffix),
+            compress: self.compress.or(lower.compress),
+            stream: self.stream.or(lower.stream),
+            format: or_vec(self.format, lower.format),
+            split_chapters: self.split_chapters.or(lower.split_chapters),
+            split_size: self.split_size.or(lower.split_size),
+            split_every: self.split_every.or(lower.split_every),
+            max_tokens: self.max_tokens.or(lower.max_tokens),
+            overlap: self.overlap.or(lower.overlap),
+            self_contained: self.self_contained.or(lower.self_contained),
+            localize_images: self.localize_images.or(lower.localize_images),
+            inline_images_under: self.inline_images_under.or(lower.inline_images_under),
+            attachments: self.attachments.or(lower.attachments),
+            attachment_types: or_vec(self.attachment_types, lower.attachment_types),
+            max_attachment_size: self.max_attachment_size.or(lower.max_attachment_size),
+            inline_svg: self.inline_svg.or(lower.inline_svg),
+            site_css: self.site_css.or(lower.site_css),
+            highlight: self.highlight.or(lower.highlight),
+            highlight_theme: self.highlight_theme.or(lower.highlight_theme),
+            math: self.math.or(lower.math),
+            expand_details: self.expand_details.or(lower.expand_details),
+            renumber_footnotes: self.renumber_footnotes.or(lower.renumber_footnotes),
+            number_chapters: self.number_chapters.or(lower.number_chapters),
+            normalize_headings: self.normalize_headings.or(lower.normalize_headings),
+            minify: self.minify.or(lower.minify),
+            reproducible: self.reproducible.or(lower.reproducible),
+            manifest: self.manifest.or(lower.manifest),
+            alt_text_report: self.alt_text_report.or(lower.alt_text_report),
+            cover: self.cover.or(lower.cover),
+            favicon: self.favicon.or(lower.favicon),
+            cover_image: self.cover_image.or(lower.cover_image),
+            attribution: self.attribution.or(lower.attribution),
+            term_index: self.term_index.or(lower.term_index),
+            provenance: self.provenance.or(lower.provenance),
+            provenance_hash: self.provenance_hash.or(lower.provenance_hash),
+            link_footnotes: self.link_footnotes.or(lower.link_footnotes),
+            search: self.search.or(lower.search),
+            template: self.template.or(lower.template),
+            css: or_vec(self.css, lower.css),
+            browser: self.browser.or(lower.browser),
+            concurrency: self.concurrency.or(lower.concurrency),
+            rate_limit: self.rate_limit.or(lower.rate_limit),
+            max_pages: self.max_pages.or(lower.max_pages),
+            max_duration: self.max_duration.or(lower.max_duration),
+            fail_on: self.fail_on.or(lower.fail_on),
+            proxy: self.proxy.or(lower.proxy),
+            user_agent: self.user_agent.or(lower.user_agent),
+        }
+    }
+
+    /// Reads options from `DOCSCRAPER_*` environment variables, e.g. `DOCSCRAPER_CONCURRENCY`.
+    pub fn from_env() -> Result<Options, String> {
+        Ok(Options {
+            urls: env_var("URL")?.into_iter().collect(),
+            url_file: env_var("URL_FILE")?,
+            separate_outputs: env_var("SEPARATE_OUTPUTS")?,
+            doc_version: env_var("DOC_VERSION")?,
+            lang: env_var("LANG")?,
+            profile: env_enum("PROFILE")?,
+            content_selector: env_var("CONTENT_SELECTOR")?,
+            next_selector: env_var("NEXT_SELECTOR")?,
+            follow_selector: env_var("FOLLOW_SELECTOR")?,
+            toc_selector: env_var("TOC_SELECTOR")?,
+            strip_selectors: env_var("STRIP_SELECTOR")?.into_iter().collect(),
+            keep_boilerplate: env_var("KEEP_BOILERPLATE")?,
+            sanitize: env_var("SANITIZE")?,
+            keep_scripts: env_var("KEEP_SCRIPTS")?,
+            keep_duplicates: env_var("KEEP_DUPLICATES")?,
+            keep_embeds: env_var("KEEP_EMBEDS")?,
+            max_depth: env_var("MAX_DEPTH")?,
+            scope: env_enum("SCOPE")?,
+            include_urls: env_var("INCLUDE_URL")?.into_iter().collect(),
+            exclude_urls: env_var("EXCLUDE_URL")?.into_iter().collect(),
+            output: env_var("OUTPUT")?,
+            force: env_var("FORCE")?,
+            output_suffix: env_enum("OUTPUT_SUFFIX")?,
+            compress: env_enum("COMPRESS")?,
+            stream: env_var("STREAM")?,
+            format: env_enum_list("FORMAT")?,
+            split_chapters: env_var("SPLIT_CHAPTERS")?,
+            split_size: env_var("SPLIT_SIZE")?,
+            split_every: env_var("SPLIT_EVERY")?,
+            max_tokens: env_var("MAX_TOKENS")?,
+            overlap: env_var("OVERLAP")?,
+            self_contained: env_var("SELF_CONTAINED")?,
+            localize_images: env_var("LOCALIZE_IMAGES")?,
+            inline_images_under: env_var("INLINE_IMAGES_UNDER")?,
+            attachments: env_var("ATTACHMENTS")?,
+            attachment_types: env_var("ATTACHMENT_TYPE")?.into_iter().collect(),
+            max_attachment_size: env_var("MAX_ATTACHMENT_SIZE")?,
+            inline_svg: env_var("INLINE_SVG")?,
+            site_css: env_var("SITE_CSS")?,
+            highlight: env_var("HIGHLIGHT")?,
+            highlight_theme: env_var("HIGHLIGHT_THEME")?,
+            math: env_var("MATH")?,
+            expand_details: env_var("EXPAND_DETAILS")?,
+            renumber_footnotes: env_var("RENUMBER_FOOTNOTES")?,
+            number_chapters: env_var("NUMBER_CHAPTERS")?,
+            normalize_headings: env_var("NORMALIZE_HEADINGS")?,
+            minify: env_var("MINIFY")?,
+            reproducible: env_var("REPRODUCIBLE")?,
+            manifest: env_var("MANIFEST")?,
+            alt_text_report: env_var("ALT_TEXT_REPORT")?,
+            cover: env_var("COVER")?,
+            favicon: env_var("FAVICON")?,
+            cover_image: env_var("COVER_IMAGE")?,
+            attribution: env_var("ATTRIBUTION")?,
+            term_index: env_var("TERM_INDEX")?,
+            provenance: env_var("PROVENANCE")?,
+            provenance_hash: env_var("PROVENANCE_HASH")?,
+            link_footnotes: env_var("LINK_FOOTNOTES")?,
+            search: env_var("SEARCH")?,
+            template: env_var("TEMPLATE")?,
+            css: env_var("CSS")?.into_iter().collect(),
+            browser: env_var("BROWSER")?,
+            concurrency: env_var("CONCURRENCY")?,
+            rate_limit: env_var("RATE_LIMIT")?,
+            max_pages: env_var("MAX_PAGES")?,
+            max_duration: env_var("MAX_DURATION")?,
+            fail_on: env_var("FAIL_ON")?,
+            proxy: env_var("PROXY")?,
+            user_agent: env_var("USER_AGENT")?,
+        })
+    }
+
+    /// Reads options from a TOML file.
+    pub fn from_file(path: &Path) -> Result<Options, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+    }
+}
+
+/// Fully resolved settings for a run.
+#[derive(Debug)]
+pub struct Config {
+    pub start_urls: Vec<Url>,
+    pub separate_outputs: bool,
+    pub doc_version: Option<String>,
+    pub lang: Option<String>,
+    pub profile: Option<Profile>,
+    pub content_selector: Option<Selector>,
+    pub next_selector: Option<Selector>,
+    pub follow_selector: Option<Selector>,
+    pub toc_selector: Option<Selector>,
+    pub strip_selectors: Vec<Selector>,
+    pub keep_boilerplate: bool,
+    pub sanitize: bool,
+    pub keep_scripts: bool,
+    pub keep_duplicates: bool,
+    pub keep_embeds: bool,
+    pub max_depth: Option<usize>,
+    pub scope: Scope,
+    pub url_filter: UrlFilter,
+    pub output: Option<PathBuf>,
+    pub force: bool,
+    pub output_suffix: Option<OutputSuffix>,
+    pub compress: Option<Compression>,
+    pub stream: bool,
+    /// Formats to write, without duplicates, in the order given.
+    pub formats: Vec<OutputFormat>,
+    pub split_chapters: bool,
+    /// Largest volume of the HTML output, in bytes of chapter HTML.
+    pub split_size: Option<usize>,
+    pub split_every: Option<usize>,
+    /// Chunk limit of `--format chunks` in tokens; characters when unset.
+    pub max_tokens: Option<usize>,
+    pub overlap: Option<usize>,
+    pub self_contained: bool,
+    pub localize_images: bool,
+    pub inline_images_under: Option<usize>,
+    pub attachments: bool,
+    /// Lowercase, without the dot.
+    pub attachment_types: Vec<String>,
+    /// In bytes.
+    pub max_attachment_size: usize,
+    pub inline_svg: bool,
+    pub site_css: bool,
+    /// The theme to colour code blocks with, for `--highlight`.
+    pub highlight: Option<String>,
+    pub math: bool,
+    pub expand_details: bool,
+    pub renumber_footnotes: bool,
+    pub number_chapters: bool,
+    pub normalize_headings: bool,
+    pub minify: bool,
+    pub reproducible: bool,
+    pub manifest: bool,
+    pub alt_text_report: bool,
+    pub cover: bool,
+    pub favicon: bool,
+    /// The `--cover-image`, with local files as `file:` URLs.
+    pub cover_image: Option<Url>,
+    pub attribution: bool,
+    pub term_index: bool,
+    pub provenance: bool,
+    pub provenance_hash: bool,
+    pub link_footnotes: bool,
+    pub search: bool,
+    /// Source of the `--template` file.
+    pub template: Option<String>,
+    /// Contents of the `--css` files, in order.
+    pub css: Vec<String>,
+    pub browser: Option<PathBuf>,
+    pub concurrency: usize,
+    pub rate_limit: Option<f64>,
+    pub max_pages: Option<usize>,
+    pub max_duration: Option<Duration>,
+    pub fail_on: FailOn,
+    pub proxy: Option<String>,
+    pub user_agent: String,
+}
+
+impl Config {
+    /// Applies defaults to the merged options and validates them.
+    pub fn resolve(options: Options) -> Result<Config, String> {
+        let mut urls = options.urls;
+        if let Some(path) = &options.url_file {
+            let text = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read URL file {}: {}", path.display(), e))?;
+            urls.extend(parse_url_list(&text));
+        }
+        if urls.is_empty() {
+            urls.push(DEFAULT_START_URL.to_string());
+        }
+        let mut start_urls = urls
+            .iter()
+            .map(|url| Url::parse(url).map_err(|e| format!("Invalid start URL '{}': {}", url, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(version) = &options.doc_version {
+            start_urls = start_urls
+                .iter()
+                .map(|url| with_doc_version(url, version))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(lang) = &options.lang {
+            start_urls = start_urls
+                .iter()
+                .map(|url| with_language(url, lang))
+                .collect();
+        }
+
+        let mut formats = Vec::new();
+        for format in options.format {
+            if !formats.contains(&format) {
+                formats.push(format);
+            }
+        }
+        let output = match options
+            .output
+            .as_ref()
+            .and_then(|output| output.to_str())
+            .and_then(|output| output.strip_prefix("sqlite:"))
+        {
+            Some(path) => {
+                if let Some(format) = formats
+                    .iter()
+                    .find(|format| **format != OutputFormat::Sqlite)
+                {
+                    return Err(format!(
+                        "--output sqlite:{} cannot be combined with --format {}",
+                        path,
+                        format.to_possible_value().unwrap().get_name()
+                    ));
+                }
+                formats = vec![OutputFormat::Sqlite];
+                Some(PathBuf::from(path))
+            }
+            None => options.output,
+        };
+        if formats.is_empty() {
+            formats.push(OutputFormat::Html);
+        }
+        if options.stream == Some(true) {
+            if let Some(format) = formats.iter().find(|format| {
+                !matches!(
+                    format,
+                    OutputFormat::Jsonl
+                        | OutputFormat::Markdown
+                        | OutputFormat::Text
+                        | OutputFormat::Chunks
+                )
+            }) {
+                return Err(format!(
+                    "--stream cannot write --format {}; use jsonl, markdown, text or chunks",
+                    format.to_possible_value().unwrap().get_name()
+                ));
+            }
+            if options.split_chapters == Some(true) {
+                return Err("--stream cannot be combined with --split-chapters".to_string());
+            }
+        }
+
+        let template = options
+            .template
+            .as_deref()
+            .map(|path| {
+                fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read template {}: {}", path.display(), e))
+            })
+            .transpose()?;
+        if let Some(template) = &template {
+            validate_template(template)?;
+        }
+        let cover_image = options
+            .cover_image
+            .as_deref()
+            .map(cover_image_url)
+            .transpose()?;
+        let css = options
+            .css
+            .iter()
+            .map(|path| {
+                fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read stylesheet {}: {}", path.display(), e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(theme) = &options.highlight_theme {
+            highlight::check_theme(theme)?;
+        }
+        let highlight = options.highlight.unwrap_or_default().then(|| {
+            options
+                .highlight_theme
+                .clone()
+                .unwrap_or_else(|| DEFAULT_THEME.to_string())
+        });
+
+        let split_size = options.split_size.as_deref().map(parse_size).transpose()?;
+        let attachment_types = if options.attachment_types.is_empty() {
+            DEFAULT_ATTACHMENT_TYPES
+                .iter()
+                .map(|extension| extension.to_string())
+                .collect()
+        } else {
+            options
+                .attachment_types
+                .iter()
+                .map(|extension| extension.trim_start_matches('.').to_ascii_lowercase())
+                .collect()
+        };
+        let max_attachment_size = match &options.max_attachment_size {
+            Some(size) => parse_size(size)?,
+            None => DEFAULT_MAX_ATTACHMENT_SIZE,
+        };
+        if split_size == Some(0) || options.split_every == Some(0) {
+            return Err("Volumes must hold at least one byte and one chapter".to_string());
+        }
+        if options.split_chapters == Some(true)
+            && (split_size.is_some() || options.split_every.is_some())
+        {
+            return Err(
+                "--split-chapters cannot be combined with --split-size or --split-every"
+                    .to_string(),
+            );
+        }
+        if options.localize_images == Some(true) && options.self_contained == Some(true) {
+            return Err("--localize-images cannot be combined with --self-contained".to_string());
+        }
+        if options.max_tokens == Some(0) {
+            return Err("--max-tokens must be at least 1".to_string());
+        }
+        if let (Some(overlap), Some(max)) = (options.overlap, options.max_tokens)
+            && overlap >= max
+        {
+            return Err("--overlap must be smaller than --max-tokens".to_string());
+        }
+
+        if let Some(rate) = options.rate_limit
+            && (!rate.is_finite() || rate <= 0.0)
+        {
+            return Err(format!("Rate limit must be positive, got {}", rate));
+        }
+
+        Ok(Config {
+            start_urls,
+            separate_outputs: options.separate_outputs.unwrap_or_default(),
+            doc_version: options.doc_version,
+            lang: options.lang.as_deref().map(normalize),
+            profile: options.profile,
+            content_selector: options
+                .content_selector
+                .as_deref()
+                .map(parse_selector)
+                .transpose()?,
+            next_selector: options
+                .next_selector
+                .as_deref()
+                .map(parse_selector)
+                .transpose()?,
+            follow_selector: options
+                .follow_selector
+                .as_deref()
+                .map(parse_selector)
+                .transpose()?,
+            toc_selector: options
+                .toc_selector
+                .as_deref()
+                .map(parse_selector)
+                .transpose()?,
+            strip_selectors: options
+                .strip_selectors
+                .iter()
+                .map(|selector| parse_selector(selector))
+                .collect::<Result<_, _>>()?,
+            keep_boilerplate: options.keep_boilerplate.unwrap_or_default(),
+            sanitize: options.sanitize.unwrap_or_default(),
+            keep_scripts: options.keep_scripts.unwrap_or_default(),
+            keep_duplicates: options.keep_duplicates.unwrap_or_default(),
+            keep_embeds: options.keep_embeds.unwrap_or_default(),
+            max_depth: options.max_depth,
+            scope: options.scope.unwrap_or_default(),
+            url_filter: UrlFilter::new(&options.include_urls, &options.exclude_urls)?,
+            output,
+            force: options.force.unwrap_or_default(),
+            output_suffix: options.output_suffix,
+            compress: options.compress,
+            stream: options.stream.unwrap_or_default(),
+            formats,
+            split_chapters: options.split_chapters.unwrap_or_default(),
+            split_size,
+            split_every: options.split_every,
+            max_tokens: options.max_tokens,
+            overlap: options.overlap,
+            self_contained: options.self_contained.unwrap_or_default(),
+            localize_images: options.localize_images.unwrap_or_default(),
+            inline_images_under: options.inline_images_under,
+            attachments: options.attachments.unwrap_or_default(),
+            attachment_types,
+            max_attachment_size,
+            inline_svg: options.inline_svg.unwrap_or_default(),
+            site_css: options.site_css.unwrap_or_default(),
+            highlight,
+            math: options.math.unwrap_or_default(),
+            expand_details: options.expand_details.unwrap_or_default(),
+            renumber_footnotes: options.renumber_footnotes.unwrap_or_default(),
+            number_chapters: options.number_chapters.unwrap_or_default(),
+            normalize_headings: options.normalize_headings.unwrap_or_default(),
+            minify: options.minify.unwrap_or_default(),
+            reproducible: options.reproducible.unwrap_or_default(),
+            manifest: options.manifest.unwrap_or_default(),
+            alt_text_report: options.alt_text_report.unwrap_or_default(),
+            cover: options.cover.unwrap_or_default(),
+            favicon: options.favicon.unwrap_or_default(),
+            cover_image,
+            attribution: options.attribution.unwrap_or_default(),
+            term_index: options.term_index.unwrap_or_default(),
+            provenance: options.provenance.unwrap_or_default()
+                || options.provenance_hash.unwrap_or_default(),
+            provenance_hash: options.provenance_hash.unwrap_or_default(),
+            link_footnotes: options.link_footnotes.unwrap_or_default(),
+            search: options.search.unwrap_or_default(),
+            template,
+            css,
+            browser: options.browser,
+            concurrency: options.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1),
+            rate_limit: options.rate_limit,
+            max_pages: options.max_pages,
+            max_duration: options
+                .max_duration
+                .as_deref()
+                .map(|duration| {
+                    humantime::parse_duration(duration)
+                        .map_err(|e| format!("Invalid duration '{}': {}", duration, e))
+                })
+                .transpose()?,
+            fail_on: options
+                .fail_on
+                .as_deref()
+                .map(str::parse)
+                .transpose()?
+                .unwrap_or_default(),
+            proxy: options.proxy,
+            user_agent: options
+                .user_agent
+                .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string()),
+        })
+    }
+}
+
+/// Keeps `higher` unless it is empty, for repeatable options.
+fn or_vec<T>(higher: Vec<T>, lower: Vec<T>) -> Vec<T> {
+    if higher.is_empty() { lower } else { higher }
+}
+
+/// Reads one URL per line, skipping blank lines and `#` comments.
+pub fn parse_url_list(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+}
+
+/// Accepts either a single string or a list of strings in the config file.
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// Parses a size such as "500KB", "2MB", "1.5MiB" or a plain number of bytes.
+/// KB and MB are powers of 1000, KiB and MiB powers of 1024.
+fn parse_size(text: &str) -> Result<usize, String> {
+    let trimmed = text.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "k" | "kb" => 1e3,
+        "m" | "mb" => 1e6,
+        "g" | "gb" => 1e9,
+        "kib" => 1024.0,
+        "mib" => 1024.0 * 1024.0,
+        "gib" => 1024.0 * 1024.0 * 1024.0,
+        _ => {
+            return Err(format!(
+                "Invalid size '{}': unknown unit '{}'",
+                text,
+                unit.trim()
+            ));
+        }
+    };
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| format!("Invalid size '{}'", text))?;
+    Ok((number * multiplier) as usize)
+}
+
+pub fn parse_selector(selector: &str) -> Result<Selector, String> {
+    Selector::parse(selector).map_err(|e| format!("Invalid CSS selector '{}': {}", selector, e))
+}
+
+/// Where to get the `--cover-image` from: an http(s) URL as given, or else a local file.
+fn cover_image_url(image: &str) -> Result<Url, String> {
+    if let Ok(url) = Url::parse(image)
+        && (url.scheme() == "http" || url.scheme() == "https")
+    {
+        return Ok(url);
+    }
+    let path = fs::canonicalize(image)
+        .map_err(|e| format!("Failed to read cover image {}: {}", image, e))?;
+    Url::from_file_path(&path).map_err(|_| format!("Invalid cover image path {}", path.display()))
+}
+
+/// Reads and parses `DOCSCRAPER_<name>`, treating an empty value as unset.
+pub fn env_var<T: FromStr>(name: &str) -> Result<Option<T>, String>
+where
+    T::Err: std::fmt::Display,
+{
+    let key = format!("{}{}", ENV_PREFIX, name);
+    match env::var(&key) {
+        Ok(value) if !value.is_empty() => value
+            .parse()
+            .map(Some)
+            .map_err(|e| format!("Invalid {}: {}", key, e)),
+        _ => Ok(None),
+    }
+}
+
+/// Like [`env_var`], for options that take one of a fixed set of values.
+fn env_enum<T: ValueEnum>(name: &str) -> Result<Option<T>, String> {
+    env_var::<String>(name)?
+        .map(|value| {
+            T::from_str(&value, true).map_err(|e| format!("Invalid {}{}: {}", ENV_PREFIX, name, e))
+        })
+        .transpose()
+}
+
+/// Like [`env_enum`], for options that take a comma-separated list of values.
+fn env_enum_list<T: ValueEnum>(name: &str) -> Result<Vec<T>, String> {
+    env_var::<String>(name)?
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            T::from_str(value, true).map_err(|e| format!("Invalid {}{}: {}", ENV_PREFIX, name, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::Parser;
+
+    #[test]
+    fn command_line_flag_switches_off_config_file_flag() {
+        let cli = Cli::parse_from(["documentation_scraper", "--force=false", "--minify"]);
+        let file: Options = toml::from_str("force = true\nsanitize = true").unwrap();
+        let options = cli.options.merge(file);
+        assert_eq!(options.force, Some(false));
+        assert_eq!(options.sanitize, Some(true));
+        assert_eq!(options.minify, Some(true));
+        assert_eq!(options.keep_scripts, None);
+    }
+}
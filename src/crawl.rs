@@ -0,0 +1,1577 @@
+use crate::assets::Asset;
+use crate::breadcrumbs;
+use crate::config::{Config, OutputFormat};
+use crate::detect::detect_profile;
+use crate::dom;
+use crate::lazy::load_images;
+use crate::license::{self, License};
+use crate::locale::{page_language, same_language, url_language};
+use crate::log::{debug, error, info, trace, warning};
+use crate::output::escape_html;
+use crate::profile::{Profile, TocScope};
+use crate::rate_limit::RateLimiter;
+use crate::readability;
+use crate::readthedocs::{self, Build};
+use crate::sanitize;
+use crate::title::{first_heading, page_title, site_name};
+use crate::version::doc_version;
+use chrono::{DateTime, Utc};
+use ego_tree::NodeId;
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use regex::bytes::Regex;
+use reqwest::header::{CONTENT_TYPE, HeaderMap};
+use reqwest::{Client, Proxy, Version};
+use scraper::node::Element;
+use scraper::{ElementRef, Html, Node, Selector};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Semaphore, mpsc};
+use tokio::time::{self, sleep_until};
+use url::Url;
+
+/// Analytics and advertising hosts whose frames and images are removed from the content,
+/// with their subdomains.
+const TRACKER_HOSTS: &[&str] = &[
+    "google-analytics.com",
+    "googletagmanager.com",
+    "doubleclick.net",
+    "googlesyndication.com",
+    "facebook.net",
+    "scorecardresearch.com",
+    "quantserve.com",
+    "hotjar.com",
+    "segment.io",
+    "mixpanel.com",
+    "plausible.io",
+    "pixel.wp.com",
+    "stats.wp.com",
+];
+
+/// Attributes holding a URL that [`rebase`] makes absolute.
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "poster", "data", "cite"];
+
+/// The charset of a `<meta charset>` tag or of the Content-Type in `<meta http-equiv>`.
+static META_CHARSET: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i-u)<meta\s[^>]*charset\s*=\s*["']?\s*([a-z0-9_:.+-]+)"#).unwrap()
+});
+
+/// The `path_to_root` variable mdBook declares on every page, for finding `toc.html`.
+static PATH_TO_ROOT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"path_to_root\s*=\s*"([^"]*)""#).unwrap());
+
+static LINKS: LazyLock<Selector> = LazyLock::new(|| Selector::parse("a[href]").unwrap());
+
+/// The `menudata.js` Doxygen's script builds the menu of every page from.
+static MENU_DATA: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("script[src$='menudata.js']").unwrap());
+
+/// The link of an entry of Doxygen's `menudata.js`.
+static MENU_URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"url:\s*"([^"]*)""#).unwrap());
+
+/// The start of a `<frameset>`, which only pages laid out in frames have.
+static FRAMESET: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i-u)<frameset[\s>]").unwrap());
+
+/// The frame of a Javadoc frames layout that shows the overview, package and class pages.
+static MAIN_FRAME: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("frameset frame[name='classFrame'][src]").unwrap());
+
+/// Where a page sorts in the combined output: the index of its start URL, then its
+/// [`Page::order`].
+pub type SortKey = (usize, Vec<usize>);
+
+/// A scraped page, numbered by its start URL and its position in that URL's crawl.
+pub struct Page {
+    pub seed: usize,
+    /// Position within the crawl of `seed`. Following the next link moves to the next
+    /// sibling, following an ordinary link descends one level, so sorting by this key
+    /// keeps linked pages right after the page that linked to them.
+    pub order: Vec<usize>,
+    pub url: Url,
+    pub title: Option<String>,
+    pub site_name: Option<String>,
+    pub lang: Option<String>,
+    pub html: String,
+    /// The stylesheets the page links to, collected only for `--site-css`.
+    pub stylesheets: Vec<Url>,
+    /// The icon the page declares, or its site's `/favicon.ico`, collected only for
+    /// `--favicon`.
+    pub icon: Option<Url>,
+    /// The license or copyright statement in the page's footer, collected only for
+    /// `--attribution`.
+    pub license: Option<License>,
+    /// The titles of the sections above the page in the site's hierarchy, outermost
+    /// first, from its breadcrumb trail.
+    pub breadcrumbs: Vec<String>,
+    /// SHA-256 of the extracted content, whitespace aside and before any `--provenance`
+    /// footer, in hex: pages served at several URLs share it.
+    pub content_sha256: String,
+    /// The URL the page declares with `<link rel="canonical">`, when it is not `url`.
+    pub canonical: Option<Url>,
+    /// The other URLs that served the same content, whose chapters were dropped.
+    pub aliases: Vec<Url>,
+    pub fetch: FetchInfo,
+}
+
+/// How a page was fetched.
+pub struct FetchInfo {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub bytes: usize,
+    /// SHA-256 of the response body as received, in hex.
+    pub sha256: String,
+    pub elapsed: Duration,
+    pub fetched_at: DateTime<Utc>,
+    /// The raw response, kept only when it is needed for `--format warc`.
+    pub exchange: Option<Exchange>,
+}
+
+/// A page that could not be scraped.
+pub struct Failure {
+    /// Index of the start URL the page was reached from.
+    pub seed: usize,
+    pub url: Url,
+    pub error: String,
+}
+
+/// A response as received, before the body is decoded.
+pub struct Exchange {
+    pub version: Version,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// What was pulled out of a fetched page.
+struct Scraped {
+    title: Option<String>,
+    site_name: Option<String>,
+    lang: Option<String>,
+    read_the_docs: Option<Build>,
+    html: String,
+    stylesheets: Vec<Url>,
+    icon: Option<Url>,
+    license: Option<License>,
+    breadcrumbs: Vec<String>,
+    /// The pages the table of contents links to, in its order, each with its position in
+    /// the nesting of the table's lists.
+    toc: Vec<(Vec<usize>, Url)>,
+    /// The page holding the table of contents when the page itself has none, such as the
+    /// `toc.html` of mdBook versions that fill in the sidebar with a script, or the
+    /// `menudata.js` of Doxygen's menu.
+    toc_page: Option<Url>,
+    content_sha256: String,
+    canonical: Option<Url>,
+    fetch: FetchInfo,
+    next_url: Option<Url>,
+    links: Vec<Url>,
+}
+
+/// Selectors used to pull content and links out of each page, tried in order.
+struct Selectors {
+    profile: Profile,
+    content: Vec<Selector>,
+    next: Vec<Selector>,
+    /// The table of contents, read from the start page.
+    toc: Vec<Selector>,
+    /// Which pages the table of contents is read from.
+    toc_scope: TocScope,
+    /// Boilerplate removed from the content.
+    strip: Vec<Selector>,
+}
+
+impl Selectors {
+    /// Combines the explicitly configured selectors with those of `profile`.
+    fn new(config: &Config, profile: Profile) -> Selectors {
+        let preset = |selectors: &[&str]| {
+            selectors
+                .iter()
+                .map(|selector| Selector::parse(selector).unwrap())
+                .collect()
+        };
+        Selectors {
+            profile,
+            content: match &config.content_selector {
+                Some(selector) => vec![selector.clone()],
+                None => preset(profile.content_selectors()),
+            },
+            next: match &config.next_selector {
+                Some(selector) => vec![selector.clone()],
+                None => preset(profile.next_selectors()),
+            },
+            // An explicit next selector asks for the crawl to follow next links.
+            toc: match (&config.toc_selector, &config.next_selector) {
+                (Some(selector), _) => vec![selector.clone()],
+                (None, Some(_)) => Vec::new(),
+                (None, None) => preset(profile.toc_selectors()),
+            },
+            toc_scope: match config.toc_selector {
+                Some(_) => TocScope::StartPage,
+                None => profile.toc_scope(),
+            },
+            strip: if config.keep_boilerplate {
+                Vec::new()
+            } else {
+                preset(&profile.boilerplate_selectors())
+            }
+            .into_iter()
+            .chain(config.strip_selectors.iter().cloned())
+            .collect(),
+        }
+    }
+
+    fn first_match<'a>(selectors: &[Selector], document: &'a Html) -> Option<ElementRef<'a>> {
+        selectors
+            .iter()
+            .find_map(|selector| document.select(selector).next())
+    }
+
+    /// Every element the first of `selectors` that matches anything matches.
+    fn all_matches<'a>(selectors: &[Selector], document: &'a Html) -> Vec<ElementRef<'a>> {
+        selectors
+            .iter()
+            .map(|selector| document.select(selector).collect::<Vec<_>>())
+            .find(|matches| !matches.is_empty())
+            .unwrap_or_default()
+    }
+}
+
+/// State shared by every scraping task of a crawl.
+pub struct Crawler {
+    client: Client,
+    config: Arc<Config>,
+    /// Selectors for each start URL, resolved from the first page fetched for it.
+    selectors: Vec<OnceLock<Selectors>>,
+    /// Language each start URL's crawl is pinned to: `--lang`, or that of its first page.
+    languages: Vec<OnceLock<Option<String>>>,
+    /// The Read the Docs build each start URL's crawl is of, from its first page.
+    builds: Vec<OnceLock<Option<Build>>>,
+    /// Whether each start URL's crawl follows the table of contents of its start page
+    /// instead of next links, settled once the start page is scraped.
+    toc_driven: Vec<OnceLock<bool>>,
+    semaphore: Semaphore,
+    visited: Mutex<HashSet<Url>>,
+    /// Number of pages fetched so far, checked against `--max-pages`.
+    fetched: AtomicUsize,
+    /// URLs of spawned tasks that have not finished yet, with how many tasks hold each.
+    pending: std::sync::Mutex<HashMap<Url, usize>>,
+    /// Sort keys of spawned tasks whose page has not been received yet, with how many
+    /// tasks hold each. No page still to come sorts before the first of them.
+    in_flight: std::sync::Mutex<BTreeMap<SortKey, usize>>,
+    /// Pages that could not be scraped, with the reason.
+    failures: std::sync::Mutex<Vec<Failure>>,
+    /// Crawled URLs that turned out to hold files rather than pages, kept for
+    /// `--attachments`.
+    files: std::sync::Mutex<Vec<(Url, Asset)>>,
+    /// Set once the time budget runs out; tasks that have not started yet give up.
+    stopped: AtomicBool,
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl Crawler {
+    pub fn new(config: Arc<Config>) -> Result<Crawler, String> {
+        let mut builder = Client::builder().user_agent(&config.user_agent);
+        if let Some(proxy) = &config.proxy {
+            let proxy =
+                Proxy::all(proxy).map_err(|e| format!("Invalid proxy '{}': {}", proxy, e))?;
+            builder = builder.proxy(proxy);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+        Ok(Crawler {
+            client,
+            selectors: config.start_urls.iter().map(|_| OnceLock::new()).collect(),
+            languages: config.start_urls.iter().map(|_| OnceLock::new()).collect(),
+            builds: config.start_urls.iter().map(|_| OnceLock::new()).collect(),
+            toc_driven: config.start_urls.iter().map(|_| OnceLock::new()).collect(),
+            semaphore: Semaphore::new(config.concurrency),
+            visited: Mutex::new(HashSet::new()),
+            fetched: AtomicUsize::new(0),
+            pending: std::sync::Mutex::new(HashMap::new()),
+            in_flight: std::sync::Mutex::new(BTreeMap::new()),
+            failures: std::sync::Mutex::new(Vec::new()),
+            files: std::sync::Mutex::new(Vec::new()),
+            stopped: AtomicBool::new(false),
+            rate_limiter: config.rate_limit.map(RateLimiter::new),
+            config,
+        })
+    }
+
+    /// Keeps a crawled URL that turned out to be a file, such as a PDF, to be saved with
+    /// `--attachments`, or else skips it.
+    fn keep_file(&self, url: &Url, media_type: String, body: &[u8]) {
+        if !self.config.attachments {
+            info!("Skipping {}: it is {}, not a web page", url, media_type);
+        } else if body.len() > self.config.max_attachment_size {
+            warning!(
+                "Skipping {}: it is {}, larger than {} bytes",
+                url,
+                media_type,
+                self.config.max_attachment_size
+            );
+        } else {
+            info!("Keeping {} as an attachment: it is {}", url, media_type);
+            let asset = Asset {
+                media_type,
+                bytes: body.to_vec(),
+            };
+            self.files.lock().unwrap().push((url.clone(), asset));
+        }
+    }
+
+    /// The files the crawl came across instead of pages, for `--attachments`.
+    pub fn take_files(&self) -> Vec<(Url, Asset)> {
+        std::mem::take(&mut *self.files.lock().unwrap())
+    }
+
+    /// Downloads a file referenced by a scraped page, such as an image, within the same
+    /// concurrency and rate limits as the crawl. Returns its media type and contents.
+    ///
+    /// With `max_size`, gives up on files larger than that many bytes without reading
+    /// more of them.
+    pub async fn fetch_bytes(
+        &self,
+        url: &Url,
+        max_size: Option<usize>,
+    ) -> Result<(Option<String>, Vec<u8>), String> {
+        let _permit = self.semaphore.acquire().await.unwrap();
+        self.get_bytes(url, max_size).await
+    }
+
+    /// Like [`Crawler::fetch_bytes`], for a task that already holds a place within the
+    /// concurrency limit.
+    async fn get_bytes(
+        &self,
+        url: &Url,
+        max_size: Option<usize>,
+    ) -> Result<(Option<String>, Vec<u8>), String> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.wait().await;
+        }
+
+        debug!("GET {}", url);
+        let mut response = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(|e| format!("Request failed for {}: {}", url, e))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("Server returned {}", status));
+        }
+        let too_large = |size: usize| max_size.is_some_and(|max_size| size > max_size);
+        if response
+            .content_length()
+            .is_some_and(|length| too_large(length as usize))
+        {
+            return Err(format!("It is larger than {} bytes", max_size.unwrap()));
+        }
+        let media_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_ascii_lowercase());
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| format!("Failed to read response from {}: {}", url, e))?
+        {
+            bytes.extend_from_slice(&chunk);
+            if too_large(bytes.len()) {
+                return Err(format!("It is larger than {} bytes", max_size.unwrap()));
+            }
+        }
+        Ok((media_type, bytes))
+    }
+
+    /// Moves a task from the sort key `from` to `to`, for a page whose place in the book
+    /// turns out to be elsewhere than where it was queued.
+    fn relocate(&self, from: &SortKey, to: &SortKey) {
+        *self
+            .in_flight
+            .lock()
+            .unwrap()
+            .entry(to.clone())
+            .or_default() += 1;
+        self.settle(from);
+    }
+
+    /// Marks the task at `key` as done, because its page has been received or because it
+    /// gave up without one.
+    fn settle(&self, key: &SortKey) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if let Some(count) = in_flight.get_mut(key) {
+            *count -= 1;
+            if *count == 0 {
+                in_flight.remove(key);
+            }
+        }
+    }
+}
+
+/// Outcome of a crawl.
+pub struct CrawlReport {
+    /// Scraped pages in reading order; empty when they were handed out by [`run_streaming`].
+    pub pages: Vec<Page>,
+    /// URLs still queued or in flight when the crawl was cut short by `--max-duration`.
+    pub pending: Vec<Url>,
+    /// Pages that could not be scraped, with the reason.
+    pub failures: Vec<Failure>,
+}
+
+/// Crawls from every configured start URL and returns the scraped pages in order, without
+/// the duplicates of earlier pages unless `--keep-duplicates` is set.
+pub async fn run(crawler: Arc<Crawler>) -> CrawlReport {
+    let config = crawler.config.clone();
+    let mut pages = Vec::new();
+    let mut report = run_streaming(crawler, |page, _| {
+        pages.push(page);
+        Ok(())
+    })
+    .await
+    .expect("collecting pages cannot fail");
+    pages.sort_by(|a, b| (a.seed, &a.order).cmp(&(b.seed, &b.order)));
+    if !config.keep_duplicates {
+        pages = drop_duplicates(&config, pages);
+    }
+    report.pages = pages;
+    report
+}
+
+/// What makes two pages duplicates, within the same output: the same content, or the
+/// same canonical URL, a page's own URL counting as its canonical one if it declares
+/// none. With `--separate-outputs` each start URL's book keeps its own copy.
+pub fn duplicate_keys(config: &Config, page: &Page) -> [(usize, String); 2] {
+    let seed = if config.separate_outputs {
+        page.seed
+    } else {
+        0
+    };
+    let canonical = page.canonical.as_ref().unwrap_or(&page.url);
+    [
+        (seed, page.content_sha256.clone()),
+        (seed, canonical.to_string()),
+    ]
+}
+
+/// Drops the pages an earlier page in reading order duplicates, such as `/` and
+/// `/index.html` or a print variant, recording their URLs as its aliases.
+fn drop_duplicates(config: &Config, pages: Vec<Page>) -> Vec<Page> {
+    let mut kept: Vec<Page> = Vec::with_capacity(pages.len());
+    let mut positions = HashMap::new();
+    for page in pages {
+        let keys = duplicate_keys(config, &page);
+        match keys.iter().find_map(|key| positions.get(key)) {
+            Some(&position) => {
+                let original: &mut Page = &mut kept[position];
+                info!("Skipping {}: duplicate of {}", page.url, original.url);
+                original.aliases.push(page.url);
+            }
+            None => {
+                for key in keys {
+                    positions.insert(key, kept.len());
+                }
+                kept.push(page);
+            }
+        }
+    }
+    kept
+}
+
+/// Crawls from every configured start URL, handing each page to `on_page` as it arrives,
+/// in no particular order, together with the sort key of the first page that may still
+/// come. Every page that sorts before that key has already been handed out; `None` means
+/// no more pages are in flight.
+///
+/// Stops the crawl and returns the error if `on_page` fails.
+pub async fn run_streaming(
+    crawler: Arc<Crawler>,
+    mut on_page: impl FnMut(Page, Option<&SortKey>) -> Result<(), String>,
+) -> Result<CrawlReport, String> {
+    let (tx, mut rx) = mpsc::channel(100);
+    let mut deadline = crawler
+        .config
+        .max_duration
+        .map(|budget| time::Instant::now() + budget);
+
+    for (seed, start_url) in crawler.config.start_urls.iter().enumerate() {
+        spawn_scraping_task(
+            seed,
+            vec![0],
+            start_url.clone(),
+            crawler.clone(),
+            tx.clone(),
+        );
+    }
+    drop(tx);
+
+    let mut pending = Vec::new();
+    loop {
+        let received = match deadline {
+            Some(until) => tokio::select! {
+                page = rx.recv() => page,
+                _ = sleep_until(until) => {
+                    crawler.stopped.store(true, Ordering::SeqCst);
+                    pending = crawler.pending.lock().unwrap().keys().cloned().collect();
+                    pending.sort();
+                    warning!(
+                        "Reached the time budget of {}, stopping with {} pages still pending.",
+                        humantime::format_duration(crawler.config.max_duration.unwrap()),
+                        pending.len()
+                    );
+                    // The pages already waiting in the channel are still handed out; the
+                    // ones still being scraped are among the pending.
+                    rx.close();
+                    deadline = None;
+                    continue;
+                }
+            },
+            None => rx.recv().await,
+        };
+        let Some(page) = received else {
+            break;
+        };
+        crawler.settle(&(page.seed, page.order.clone()));
+        // A page sent just as the time ran out is handed out rather than pending.
+        pending.retain(|url| *url != page.url);
+        let first_in_flight = crawler.in_flight.lock().unwrap().keys().next().cloned();
+        if let Err(e) = on_page(page, first_in_flight.as_ref()) {
+            crawler.stopped.store(true, Ordering::SeqCst);
+            return Err(e);
+        }
+    }
+
+    let failures = std::mem::take(&mut *crawler.failures.lock().unwrap());
+    Ok(CrawlReport {
+        pages: Vec::new(),
+        pending,
+        failures,
+    })
+}
+
+/// Fetches and scrapes one page. Returns `None` for a URL that turns out to hold a file
+/// rather than a page, which is kept for `--attachments` and otherwise skipped.
+async fn scrape_content(
+    crawler: &Crawler,
+    seed: usize,
+    url: &Url,
+) -> Result<Option<Scraped>, String> {
+    if let Some(rate_limiter) = &crawler.rate_limiter {
+        rate_limiter.wait().await;
+    }
+
+    debug!("GET {}", url);
+    let fetched_at = Utc::now();
+    let started = Instant::now();
+    let response = crawler
+        .client
+        .get(url.clone())
+        .send()
+        .await
+        .map_err(|e| format!("Request failed for {}: {}", url, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Server returned {}", status));
+    }
+    trace!("{} {:?}", url, response.headers());
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let version = response.version();
+    let headers = response.headers().clone();
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read response from {}:{}", url, e))?;
+    let response_text = decode_body(&body, content_type.as_deref());
+    let fetch = FetchInfo {
+        status: status.as_u16(),
+        content_type,
+        bytes: body.len(),
+        sha256: Sha256::digest(&body)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+        elapsed: started.elapsed(),
+        fetched_at,
+        exchange: (crawler.config.formats.contains(&OutputFormat::Warc)).then(|| Exchange {
+            version,
+            headers,
+            body: body.to_vec(),
+        }),
+    };
+    debug!(
+        "{} {} ({} bytes in {} ms)",
+        fetch.status,
+        url,
+        fetch.bytes,
+        fetch.elapsed.as_millis()
+    );
+
+    match body_kind(fetch.content_type.as_deref(), &body) {
+        BodyKind::Html => {}
+        BodyKind::Text(language) => {
+            info!("{} is not a web page; keeping it as plain text", url);
+            return Ok(Some(text_page(
+                crawler,
+                url,
+                &response_text,
+                language,
+                fetch,
+            )));
+        }
+        BodyKind::File(media_type) => {
+            crawler.keep_file(url, media_type, &body);
+            return Ok(None);
+        }
+    }
+
+    // The `index.html` of a Javadoc frames layout only lays out the frames, so the page
+    // in its main frame stands in for it.
+    if let Some(frame) = main_frame(&response_text, url).filter(|frame| frame != url) {
+        info!("{} is a frameset; scraping its main frame {}", url, frame);
+        let mut scraped = Box::pin(scrape_content(crawler, seed, &frame)).await?;
+        if let Some(scraped) = &mut scraped {
+            let mut fragment = Html::parse_fragment(&scraped.html);
+            let root = fragment.root_element().id();
+            rebase(&mut fragment, root, &frame);
+            scraped.html = fragment.root_element().inner_html();
+            scraped.canonical.get_or_insert(frame);
+        }
+        return Ok(scraped);
+    }
+
+    let mut document = Html::parse_document(&response_text);
+    load_images(&mut document);
+    let base = base_url(&document, url);
+    let base = &base;
+    if crawler.config.reproducible {
+        sort_attributes(&mut document);
+    }
+
+    let selectors = crawler.selectors[seed].get_or_init(|| {
+        Selectors::new(&crawler.config, resolve_profile(&crawler.config, &document))
+    });
+
+    let content = match Selectors::first_match(&selectors.content, &document) {
+        Some(content_div) => content_div.id(),
+        None => {
+            let content = readability::main_content(&document).ok_or_else(|| {
+                format!("Could not find div content on the current page : {}", url)
+            })?;
+            warning!(
+                "No content selector matched {}; guessed the main content from its text",
+                url
+            );
+            content
+        }
+    };
+
+    let next_chapter_url = if crawler.toc_driven[seed].get() == Some(&true)
+        && selectors.toc_scope != TocScope::EveryPage
+    {
+        None
+    } else if let Some(link_element) = Selectors::first_match(&selectors.next, &document) {
+        link_element
+            .value()
+            .attr("href")
+            .and_then(|href| resolve_link(base, href))
+            .filter(|link| is_eligible(crawler, seed, link))
+    } else {
+        None
+    };
+
+    let links = match &crawler.config.follow_selector {
+        Some(selector) => document
+            .select(selector)
+            .filter_map(|link| link.value().attr("href"))
+            .filter_map(|href| resolve_link(base, href))
+            .filter(|link| is_eligible(crawler, seed, link))
+            .collect(),
+        None => Vec::new(),
+    };
+    let toc = toc_links(
+        &Selectors::all_matches(&selectors.toc, &document),
+        base,
+        selectors.toc_scope == TocScope::EveryPage,
+    );
+    let toc_page = match selectors.profile {
+        _ if !toc.is_empty() => None,
+        Profile::Mdbook => PATH_TO_ROOT
+            .captures(response_text.as_bytes())
+            .and_then(|captures| std::str::from_utf8(captures.get(1)?.as_bytes()).ok())
+            .and_then(|root| url.join(&format!("{}toc.html", root)).ok()),
+        Profile::Doxygen => document
+            .select(&MENU_DATA)
+            .find_map(|script| resolve_link(base, script.value().attr("src")?)),
+        _ => None,
+    };
+
+    // The license is usually in the footer, so it is looked for before the boilerplate goes,
+    // as are the breadcrumbs.
+    let license = if crawler.config.attribution {
+        license::detect(&document, base)
+    } else {
+        None
+    };
+    let breadcrumbs = breadcrumbs::trail(&document);
+    // Links are taken first, as the next link often sits in the boilerplate.
+    strip_boilerplate(&mut document, content, &selectors.strip);
+    strip_tracking(&mut document, content, base, crawler.config.keep_scripts);
+    if base != url {
+        rebase(&mut document, content, base);
+    }
+    let content = ElementRef::wrap(document.tree.get(content).unwrap()).unwrap();
+    let title = page_title(&document).or_else(|| first_heading(content));
+    let mut chapter_html = content.inner_html();
+    if crawler.config.sanitize {
+        chapter_html = sanitize::clean(&chapter_html);
+    }
+    let mut html = if crawler.config.reproducible {
+        trim_lines(&chapter_html)
+    } else {
+        chapter_html
+    };
+    let content_sha256 = content_hash(&html);
+    if crawler.config.provenance {
+        html.push_str(&provenance_footer(&crawler.config, url, &fetch));
+    }
+
+    Ok(Some(Scraped {
+        title,
+        site_name: site_name(&document),
+        lang: page_language(&document),
+        read_the_docs: readthedocs::build(&document),
+        html,
+        stylesheets: if crawler.config.site_css {
+            stylesheets(&document, base)
+        } else {
+            Vec::new()
+        },
+        icon: if crawler.config.favicon {
+            icon(&document, base)
+        } else {
+            None
+        },
+        license,
+        breadcrumbs,
+        toc,
+        toc_page,
+        content_sha256,
+        canonical: canonical(&document, base).filter(|canonical| canonical != url),
+        fetch,
+        next_url: next_chapter_url,
+        links,
+    }))
+}
+
+/// What a response holds, going by its Content-Type, or by its first bytes when the
+/// server does not say.
+enum BodyKind {
+    Html,
+    /// Plain text, JSON, XML and the like, with the language of their code block.
+    Text(Option<&'static str>),
+    /// Anything else, such as a PDF or an archive, with its media type.
+    File(String),
+}
+
+fn body_kind(content_type: Option<&str>, body: &[u8]) -> BodyKind {
+    let media_type = content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|media_type| media_type.trim().to_ascii_lowercase())
+        .filter(|media_type| !media_type.is_empty() && media_type != "application/octet-stream");
+    let Some(media_type) = media_type else {
+        return sniff(body);
+    };
+    let (kind, subtype) = media_type.split_once('/').unwrap_or((&media_type, ""));
+    match (kind, subtype) {
+        ("text", "html") | ("application", "xhtml+xml") => BodyKind::Html,
+        ("image" | "audio" | "video" | "font", _) => BodyKind::File(media_type),
+        ("application", "json") => BodyKind::Text(Some("json")),
+        (_, "xml") => BodyKind::Text(Some("xml")),
+        ("application" | "text", "javascript") => BodyKind::Text(Some("javascript")),
+        ("application" | "text", "yaml" | "x-yaml") => BodyKind::Text(Some("yaml")),
+        ("text", "markdown") => BodyKind::Text(Some("markdown")),
+        ("text", "css") => BodyKind::Text(Some("css")),
+        ("text", _) => BodyKind::Text(None),
+        (_, subtype) if subtype.ends_with("+json") => BodyKind::Text(Some("json")),
+        (_, subtype) if subtype.ends_with("+xml") => BodyKind::Text(Some("xml")),
+        _ => BodyKind::File(media_type),
+    }
+}
+
+/// Tells HTML from text and binary files the way browsers do when a server gives no
+/// usable Content-Type: HTML starts with a tag, and binary files hold control bytes.
+fn sniff(body: &[u8]) -> BodyKind {
+    let start = &body[..body.len().min(1024)];
+    let text = String::from_utf8_lossy(start).to_ascii_lowercase();
+    let text = text.trim_start_matches(['\u{feff}', ' ', '\t', '\r', '\n']);
+    if ["<!doctype html", "<html", "<head", "<body", "<!--"]
+        .iter()
+        .any(|tag| text.starts_with(tag))
+    {
+        return BodyKind::Html;
+    }
+    let binary = start
+        .iter()
+        .any(|&byte| byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b));
+    if binary {
+        BodyKind::File("application/octet-stream".to_string())
+    } else {
+        BodyKind::Text(None)
+    }
+}
+
+/// A chapter showing a plain-text response as a code block, titled with its file name.
+fn text_page(
+    crawler: &Crawler,
+    url: &Url,
+    text: &str,
+    language: Option<&str>,
+    fetch: FetchInfo,
+) -> Scraped {
+    let title = url
+        .path_segments()
+        .and_then(|mut segments| segments.rfind(|segment| !segment.is_empty()))
+        .map_or_else(|| url.to_string(), str::to_string);
+    let class = language
+        .map(|language| format!(" class=\"language-{}\"", language))
+        .unwrap_or_default();
+    let mut html = format!("<pre><code{}>{}</code></pre>", class, escape_html(text));
+    let content_sha256 = content_hash(&html);
+    if crawler.config.provenance {
+        html.push_str(&provenance_footer(&crawler.config, url, &fetch));
+    }
+    Scraped {
+        title: Some(title),
+        site_name: None,
+        lang: None,
+        read_the_docs: None,
+        html,
+        stylesheets: Vec::new(),
+        icon: None,
+        license: None,
+        breadcrumbs: Vec::new(),
+        toc: Vec::new(),
+        toc_page: None,
+        content_sha256,
+        canonical: None,
+        fetch,
+        next_url: None,
+        links: Vec::new(),
+    }
+}
+
+/// The URL relative links in the page are resolved against: the page's `<base href>`,
+/// or else its own URL.
+fn base_url(document: &Html, url: &Url) -> Url {
+    let selector = Selector::parse("base[href]").unwrap();
+    document
+        .select(&selector)
+        .next()
+        .and_then(|base| url.join(base.value().attr("href").unwrap()).ok())
+        .filter(|base| base.scheme() == "http" || base.scheme() == "https")
+        .unwrap_or_else(|| url.clone())
+}
+
+/// The page shown in the main frame of a Javadoc frameset.
+fn main_frame(html: &str, url: &Url) -> Option<Url> {
+    if !FRAMESET.is_match(html.as_bytes()) {
+        return None;
+    }
+    let document = Html::parse_document(html);
+    let frame = document.select(&MAIN_FRAME).next()?;
+    resolve_link(&base_url(&document, url), frame.value().attr("src")?)
+}
+
+/// The canonical URL the page declares.
+fn canonical(document: &Html, base: &Url) -> Option<Url> {
+    let selector = Selector::parse("link[rel~='canonical'][href]").unwrap();
+    document
+        .select(&selector)
+        .filter_map(|link| link.value().attr("href"))
+        .find_map(|href| resolve_link(base, href))
+}
+
+/// The icon the page declares with `<link rel="icon">`, or else the one browsers look for
+/// at the root of the site.
+fn icon(document: &Html, url: &Url) -> Option<Url> {
+    let selector = Selector::parse("link[rel~='icon'][href]").unwrap();
+    document
+        .select(&selector)
+        .filter_map(|link| link.value().attr("href"))
+        .find_map(|href| resolve_link(url, href))
+        .or_else(|| url.join("/favicon.ico").ok())
+}
+
+/// The stylesheets a page links to, in the order they apply.
+fn stylesheets(document: &Html, url: &Url) -> Vec<Url> {
+    let selector = Selector::parse("link[rel~='stylesheet'][href]").unwrap();
+    document
+        .select(&selector)
+        .filter_map(|link| link.value().attr("href"))
+        .filter_map(|href| resolve_link(url, href))
+        .collect()
+}
+
+/// The SHA-256 of `html` in hex, ignoring how its whitespace is laid out.
+fn content_hash(html: &str) -> String {
+    let mut hasher = Sha256::new();
+    for word in html.split_whitespace() {
+        hasher.update(word.as_bytes());
+        hasher.update(b" ");
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Makes the relative links and resource URLs inside `content` absolute against the
+/// page's `<base href>`, since the chapter is later read against the page's own URL.
+/// Links to a fragment of the page stay as they are.
+fn rebase(document: &mut Html, content: NodeId, base: &Url) {
+    let Some(element) = document.tree.get(content).and_then(ElementRef::wrap) else {
+        return;
+    };
+    let edits = element
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .flat_map(|element| {
+            URL_ATTRIBUTES.iter().filter_map(move |&name| {
+                let value = element.value().attr(name)?;
+                if value.starts_with('#') {
+                    return None;
+                }
+                let url = base.join(value.trim()).ok()?;
+                Some((element.id(), name, url.to_string()))
+            })
+        })
+        .collect::<Vec<_>>();
+    for (id, name, value) in edits {
+        if let Node::Element(element) = document.tree.get_mut(id).unwrap().value() {
+            dom::set_attr(&mut element.attrs, name, &value);
+        }
+    }
+}
+
+/// Removes the elements inside `content` that match any of `selectors`.
+fn strip_boilerplate(document: &mut Html, content: NodeId, selectors: &[Selector]) {
+    let Some(element) = document.tree.get(content).and_then(ElementRef::wrap) else {
+        return;
+    };
+    let matches = selectors
+        .iter()
+        .flat_map(|selector| element.select(selector))
+        .map(|matched| matched.id())
+        .filter(|&id| id != content)
+        .collect::<Vec<_>>();
+    for id in matches {
+        document.tree.get_mut(id).unwrap().detach();
+    }
+}
+
+/// Removes the scripts inside `content`, unless `keep_scripts` is set, along with frames
+/// and images loaded from analytics services and 1x1 tracking pixels. MathJax's
+/// `math/tex` scripts hold formulas rather than code and are kept.
+fn strip_tracking(document: &mut Html, content: NodeId, base: &Url, keep_scripts: bool) {
+    let Some(element) = document.tree.get(content).and_then(ElementRef::wrap) else {
+        return;
+    };
+    let is_tracker = |src: &str| {
+        base.join(src)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .is_some_and(|host| {
+                TRACKER_HOSTS
+                    .iter()
+                    .any(|tracker| host == *tracker || host.ends_with(&format!(".{}", tracker)))
+            })
+    };
+    let matches = element
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|element| {
+            let element = element.value();
+            match element.name() {
+                "script" => {
+                    !keep_scripts
+                        && !element
+                            .attr("type")
+                            .is_some_and(|kind| kind.starts_with("math/"))
+                }
+                "iframe" => element.attr("src").is_some_and(is_tracker),
+                "img" => is_pixel(element) || element.attr("src").is_some_and(is_tracker),
+                _ => false,
+            }
+        })
+        .map(|matched| matched.id())
+        .collect::<Vec<_>>();
+    for id in matches {
+        document.tree.get_mut(id).unwrap().detach();
+    }
+}
+
+/// Whether an image is declared at most one pixel wide and high, as tracking pixels are.
+fn is_pixel(img: &Element) -> bool {
+    let size = |name| {
+        img.attr(name)
+            .and_then(|value| value.trim().trim_end_matches("px").parse::<f64>().ok())
+    };
+    matches!(
+        (size("width"), size("height")),
+        (Some(width), Some(height)) if width <= 1.0 && height <= 1.0
+    )
+}
+
+/// Puts every element's attributes in alphabetical order, for `--reproducible`.
+fn sort_attributes(document: &mut Html) {
+    for node in document.tree.values_mut() {
+        if let Node::Element(element) = node {
+            element.attrs.sort_keys();
+        }
+    }
+}
+
+/// The HTML without whitespace at the ends of its lines, which does not show but differs
+/// between otherwise identical pages.
+fn trim_lines(html: &str) -> String {
+    html.trim()
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The `--provenance` footer: where the page came from and when, leaving out the time
+/// with `--reproducible`, and its hash with `--provenance-hash`.
+fn provenance_footer(config: &Config, url: &Url, fetch: &FetchInfo) -> String {
+    let mut footer = format!(
+        "\n<footer class=\"provenance\"><p>Source: <a href=\"{url}\">{url}</a>",
+        url = escape_html(url.as_str())
+    );
+    if !config.reproducible {
+        footer.push_str(&format!(
+            ", fetched {}",
+            fetch.fetched_at.format("%Y-%m-%d %H:%M:%S UTC")
+        ));
+    }
+    if config.provenance_hash {
+        footer.push_str(&format!("<br />SHA-256: <code>{}</code>", fetch.sha256));
+    }
+    footer.push_str("</p></footer>");
+    footer
+}
+
+/// Decodes a response body the way browsers pick its encoding: from a byte order mark,
+/// else the charset named in its Content-Type, else a `<meta charset>` or
+/// `<meta http-equiv="Content-Type">` near its start. Older pages that name none are
+/// decoded as UTF-8 when they are valid UTF-8 and as Windows-1252, the superset of
+/// ISO-8859-1 browsers use, when they are not.
+fn decode_body(body: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .into_iter()
+        .flat_map(|content_type| content_type.split(';'))
+        .filter_map(|parameter| parameter.trim().strip_prefix("charset="))
+        .find_map(|label| Encoding::for_label(label.trim_matches('"').as_bytes()))
+        .or_else(|| meta_charset(body))
+        .unwrap_or_else(|| {
+            if std::str::from_utf8(body).is_ok() {
+                UTF_8
+            } else {
+                WINDOWS_1252
+            }
+        });
+    // `decode` sniffs the byte order mark itself, which wins over any label.
+    encoding.decode(body).0.into_owned()
+}
+
+/// The encoding a `<meta>` tag in the first 1024 bytes of an HTML document declares, as
+/// browsers look for it before parsing. A page cannot be UTF-16 if its ASCII tags can be
+/// read, so a declaration of UTF-16 means UTF-8.
+fn meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    let start = &body[..body.len().min(1024)];
+    let label = META_CHARSET.captures(start)?.get(1)?.as_bytes();
+    Encoding::for_label(label).map(Encoding::output_encoding)
+}
+
+/// Whether a discovered link is in scope for the crawl of `seed` and passes the URL filters.
+fn is_eligible(crawler: &Crawler, seed: usize, url: &Url) -> bool {
+    let config = &crawler.config;
+    let same_version = match &config.doc_version {
+        Some(version) => doc_version(url).is_none_or(|found| found == version),
+        None => true,
+    };
+    let same_language = match crawler.languages[seed].get().cloned().flatten() {
+        Some(lang) => url_language(url).is_none_or(|found| same_language(found, &lang)),
+        None => true,
+    };
+    same_version
+        && same_language
+        && config.scope.contains(&config.start_urls[seed], url)
+        && config.url_filter.allows(url)
+}
+
+/// The links in a table of contents made of the elements `tocs`, in document order and
+/// without repeats, each with its position in the nested lists: the third link of a list
+/// inside the second item of the top list is at `[1, 2]`. Labels, such as section titles,
+/// are not a level of the nesting.
+///
+/// With `structural`, a link's position counts every item before it, linked or not, as
+/// well as the items of collapsed sublists that are not shown, so it is the same on every
+/// page whichever parts of the table are expanded. The first link of an item stands for it.
+fn toc_links(tocs: &[ElementRef], base: &Url, structural: bool) -> Vec<(Vec<usize>, Url)> {
+    let mut seen = HashSet::new();
+    let mut counters: Vec<usize> = Vec::new();
+    let mut links = Vec::new();
+    for (toc, link) in tocs
+        .iter()
+        .flat_map(|toc| toc.select(&LINKS).map(move |link| (toc, link)))
+    {
+        // Links within the page, such as the `#` of a category that is only a label, are
+        // not entries.
+        let Some(url) = link
+            .value()
+            .attr("href")
+            .filter(|href| !href.starts_with('#'))
+            .and_then(|href| resolve_link(base, href))
+        else {
+            continue;
+        };
+        if structural {
+            let mut position = link
+                .ancestors()
+                .take_while(|node| node.id() != toc.id())
+                .filter_map(ElementRef::wrap)
+                .filter(|ancestor| ancestor.value().name() == "li")
+                .map(|item| {
+                    item.prev_siblings()
+                        .filter_map(ElementRef::wrap)
+                        .filter(|sibling| sibling.value().name() == "li")
+                        .count()
+                })
+                .collect::<Vec<_>>();
+            position.reverse();
+            if tocs.len() > 1 {
+                position.insert(
+                    0,
+                    tocs.iter()
+                        .position(|other| other.id() == toc.id())
+                        .unwrap(),
+                );
+            }
+            if !position.is_empty()
+                && !links.iter().any(|(other, _)| *other == position)
+                && seen.insert(url.clone())
+            {
+                links.push((position, url));
+            }
+            continue;
+        }
+        if !seen.insert(url.clone()) {
+            continue;
+        }
+        let level = link
+            .ancestors()
+            .take_while(|node| node.id() != toc.id())
+            .filter_map(ElementRef::wrap)
+            .filter(|ancestor| ancestor.value().name() == "li" && !is_label(*ancestor))
+            .count()
+            .clamp(1, counters.len() + 1);
+        if level > counters.len() {
+            counters.push(0);
+        } else {
+            counters.truncate(level);
+            counters[level - 1] += 1;
+        }
+        links.push((counters.clone(), url));
+    }
+    links
+}
+
+/// Whether a table of contents item is only a label: it has text of its own, outside its
+/// sublists, but no link. An item with neither just holds the sublist of the item before
+/// it, as in mdBook.
+fn is_label(item: ElementRef) -> bool {
+    let mut text = String::new();
+    for child in item.children() {
+        match ElementRef::wrap(child) {
+            // MkDocs Material puts each sublist in a `<nav>` of its own.
+            Some(element) if matches!(element.value().name(), "ol" | "ul" | "nav") => {}
+            Some(element) => {
+                if element.value().attr("href").is_some() || element.select(&LINKS).next().is_some()
+                {
+                    return false;
+                }
+                text.extend(element.text());
+            }
+            None => text.push_str(child.value().as_text().map_or("", |text| text)),
+        }
+    }
+    !text.trim().is_empty()
+}
+
+/// The table of contents on `page`, such as mdBook's `toc.html`, read with the selectors
+/// of the start URL `seed`, or the menu in Doxygen's `menudata.js`. Gives none if the page
+/// cannot be fetched.
+async fn toc_from_page(crawler: &Crawler, seed: usize, page: &Url) -> Vec<(Vec<usize>, Url)> {
+    let body = match crawler.get_bytes(page, None).await {
+        Ok((content_type, body)) => decode_body(&body, content_type.as_deref()),
+        Err(e) => {
+            warning!("Could not fetch the table of contents {}: {}", page, e);
+            return Vec::new();
+        }
+    };
+    let Some(selectors) = crawler.selectors[seed].get() else {
+        return Vec::new();
+    };
+    if selectors.profile == Profile::Doxygen {
+        return menu_links(&body, page);
+    }
+    let document = Html::parse_document(&body);
+    toc_links(
+        &Selectors::all_matches(&selectors.toc, &document),
+        page,
+        selectors.toc_scope == TocScope::EveryPage,
+    )
+}
+
+/// The links of the top level of the menu in Doxygen's `menudata.js`, in its order, such
+/// as "Related Pages", "Classes" and "Files". Its entries read
+/// `{text:"Classes",url:"annotated.html",children:[...]}`, so an entry is at the top
+/// level when it is inside just the outermost brackets.
+fn menu_links(script: &str, base: &Url) -> Vec<(Vec<usize>, Url)> {
+    let mut seen = HashSet::new();
+    let mut depth = 0isize;
+    let mut scanned = 0;
+    let mut links = Vec::new();
+    for captures in MENU_URL.captures_iter(script.as_bytes()) {
+        let entry = captures.get(0).unwrap();
+        for byte in &script.as_bytes()[scanned..entry.start()] {
+            match byte {
+                b'[' => depth += 1,
+                b']' => depth -= 1,
+                _ => {}
+            }
+        }
+        scanned = entry.end();
+        let Some(link) = std::str::from_utf8(&captures[1])
+            .ok()
+            .and_then(|href| resolve_link(base, href))
+        else {
+            continue;
+        };
+        if depth == 1 && seen.insert(link.clone()) {
+            links.push((vec![links.len()], link));
+        }
+    }
+    links
+}
+
+/// Resolves `href` against the page URL, dropping the fragment so every page is only
+/// visited once. Links to anything other than http(s) are ignored.
+pub fn resolve_link(base: &Url, href: &str) -> Option<Url> {
+    let mut url = base.join(href).ok()?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+    url.set_fragment(None);
+    Some(url)
+}
+
+/// Uses the configured profile, or detects one from the first page of the crawl.
+fn resolve_profile(config: &Config, document: &Html) -> Profile {
+    if let Some(profile) = config.profile {
+        return profile;
+    }
+    match detect_profile(document) {
+        Some(profile) => {
+            info!("Detected {:?} documentation", profile);
+            profile
+        }
+        None => {
+            warning!("Could not detect the documentation generator, using generic selectors");
+            Profile::Generic
+        }
+    }
+}
+
+/// Helper function to spawn a new scraping task.
+fn spawn_scraping_task(
+    seed: usize,
+    order: Vec<usize>,
+    url: Url,
+    crawler: Arc<Crawler>,
+    tx: mpsc::Sender<Page>,
+) {
+    *crawler
+        .pending
+        .lock()
+        .unwrap()
+        .entry(url.clone())
+        .or_default() += 1;
+    let key = (seed, order.clone());
+    *crawler
+        .in_flight
+        .lock()
+        .unwrap()
+        .entry(key.clone())
+        .or_default() += 1;
+
+    tokio::spawn(async move {
+        // A page that was sent is settled when it is received, so that it is never
+        // overtaken by a later page while it waits in the channel.
+        if !scrape_page(seed, order, url.clone(), crawler.clone(), tx).await {
+            crawler.settle(&key);
+        }
+
+        let mut pending = crawler.pending.lock().unwrap();
+        if let Some(count) = pending.get_mut(&url) {
+            *count -= 1;
+            if *count == 0 {
+                pending.remove(&url);
+            }
+        }
+    });
+}
+
+/// Queues the pages the table of contents of a page lists. It is read from the start page,
+/// and for a [`TocScope`] other than [`TocScope::StartPage`] from every page. When the
+/// start page has one, its pages follow the start page as the table lists and nests them,
+/// whatever `--max-depth` says, and next links are not followed. A Sphinx toctree adds its
+/// top entries, below the page it is on; their own pages add theirs.
+///
+/// Docusaurus and hosted GitBook spaces show the pages of a section only on the pages
+/// inside it, so their crawls follow next links as well to reach them. Returns where the
+/// page sits in its own table of contents, which is where it goes in the book however it
+/// was reached.
+async fn follow_toc(
+    crawler: &Arc<Crawler>,
+    seed: usize,
+    order: &[usize],
+    url: &Url,
+    scraped: &mut Scraped,
+    tx: &mpsc::Sender<Page>,
+) -> Option<Vec<usize>> {
+    let scope = crawler.selectors[seed]
+        .get()
+        .map_or(TocScope::StartPage, |selectors| selectors.toc_scope);
+    let mut toc = match scraped.toc_page.take() {
+        Some(page) if scraped.toc.is_empty() && order == [0] => {
+            toc_from_page(crawler, seed, &page).await
+        }
+        _ => std::mem::take(&mut scraped.toc),
+    };
+    if scope == TocScope::Nested {
+        toc.retain(|(position, _)| position.len() == 1);
+    }
+    let own = toc
+        .iter()
+        .find(|(_, link)| link == url)
+        .filter(|_| scope == TocScope::EveryPage && order != [0])
+        .map(|(position, _)| {
+            let mut position = position.clone();
+            position[0] += 1;
+            position
+        });
+    toc.retain(|(_, link)| link != url && is_eligible(crawler, seed, link));
+    if order == [0] {
+        if toc.is_empty() && crawler.config.toc_selector.is_some() {
+            warning!(
+                "The --toc-selector matched no links on {}; following next links instead",
+                url
+            );
+        }
+        let _ = crawler.toc_driven[seed].set(!toc.is_empty());
+        if !toc.is_empty() {
+            info!("Following the table of contents of {}", url);
+            if scope != TocScope::EveryPage {
+                scraped.next_url = None;
+            }
+        }
+    } else if scope == TocScope::StartPage || crawler.toc_driven[seed].get() != Some(&true) {
+        return None;
+    }
+    for (position, link) in toc {
+        let entry = if order == [0] || scope == TocScope::EveryPage {
+            let mut entry = position;
+            entry[0] += 1;
+            entry
+        } else {
+            [order, &position].concat()
+        };
+        // Every page lists the whole book, so most of its entries are already queued.
+        if scope == TocScope::EveryPage
+            && (crawler.visited.lock().await.contains(&link)
+                || crawler.pending.lock().unwrap().contains_key(&link))
+        {
+            continue;
+        }
+        spawn_scraping_task(seed, entry, link, crawler.clone(), tx.clone());
+    }
+    own
+}
+
+/// Remembers the Read the Docs build of the first page of the crawl of `seed`, and warns
+/// when a later page is from another version or project, or the first one is not the
+/// version `--doc-version` asked for.
+fn check_build(crawler: &Crawler, seed: usize, url: &Url, build: Option<&Build>) {
+    let pinned = crawler.builds[seed].get_or_init(|| {
+        let build = build?;
+        info!("{} is Read the Docs project {}", url, build);
+        if let Some(version) = &crawler.config.doc_version
+            && build.version != *version
+        {
+            warning!(
+                "Read the Docs serves version '{}' of {} at {}, not the requested '{}'",
+                build.version,
+                build.project,
+                url,
+                version
+            );
+        }
+        Some(build.clone())
+    });
+    if let (Some(pinned), Some(build)) = (pinned, build)
+        && (build.project != pinned.project || build.version != pinned.version)
+    {
+        warning!(
+            "{} is from Read the Docs project {} but the crawl started in {}; the output \
+             mixes versions, which --doc-version {} avoids",
+            url,
+            build,
+            pinned,
+            pinned.version
+        );
+    }
+}
+
+/// Scrapes one page and queues the pages it leads to. Returns whether a page was sent.
+async fn scrape_page(
+    seed: usize,
+    mut order: Vec<usize>,
+    url: Url,
+    crawler: Arc<Crawler>,
+    tx: mpsc::Sender<Page>,
+) -> bool {
+    let _permit = crawler.semaphore.acquire().await.unwrap();
+    if crawler.stopped.load(Ordering::SeqCst) {
+        return false;
+    }
+
+    let mut visited_lock = crawler.visited.lock().await;
+    if !visited_lock.insert(url.clone()) {
+        return false;
+    }
+    drop(visited_lock);
+
+    if let Some(max_pages) = crawler.config.max_pages {
+        let fetched = crawler.fetched.fetch_add(1, Ordering::SeqCst);
+        if fetched >= max_pages {
+            if fetched == max_pages {
+                info!("Reached the limit of {} pages, stopping.", max_pages);
+            }
+            return false;
+        }
+    }
+
+    info!("Scraping chapter {:?}: {}", order, url);
+
+    match scrape_content(&crawler, seed, &url).await {
+        Ok(None) => false,
+        Ok(Some(mut scraped)) => {
+            let pinned = crawler.languages[seed]
+                .get_or_init(|| crawler.config.lang.clone().or(scraped.lang.clone()));
+            if let (Some(pinned), Some(lang)) = (pinned, &scraped.lang)
+                && !same_language(pinned, lang)
+            {
+                warning!(
+                    "Skipping {}: it is in '{}' but the crawl is pinned to '{}'",
+                    url,
+                    lang,
+                    pinned
+                );
+                return false;
+            }
+            check_build(&crawler, seed, &url, scraped.read_the_docs.as_ref());
+
+            // Links to the canonical URL now lead to this page, so it is not fetched again.
+            if let Some(canonical) = &scraped.canonical
+                && !crawler.config.keep_duplicates
+            {
+                crawler.visited.lock().await.insert(canonical.clone());
+            }
+
+            if let Some(position) =
+                follow_toc(&crawler, seed, &order, &url, &mut scraped, &tx).await
+            {
+                crawler.relocate(&(seed, order), &(seed, position.clone()));
+                order = position;
+            }
+
+            // The start page sits at depth 0; every followed link adds one level.
+            let depth = order.len() - 1;
+            if crawler
+                .config
+                .max_depth
+                .is_none_or(|max_depth| depth < max_depth)
+            {
+                for (position, link) in std::mem::take(&mut scraped.links).into_iter().enumerate() {
+                    let mut child = order.clone();
+                    child.push(position);
+                    spawn_scraping_task(seed, child, link, crawler.clone(), tx.clone());
+                }
+            }
+
+            if let Some(next_url) = scraped.next_url {
+                let mut sibling = order.clone();
+                *sibling.last_mut().unwrap() += 1;
+                spawn_scraping_task(seed, sibling, next_url, crawler.clone(), tx.clone());
+            }
+
+            let page = Page {
+                seed,
+                order,
+                url,
+                title: scraped.title,
+                site_name: scraped.site_name,
+                lang: scraped.lang,
+                html: scraped.html,
+                stylesheets: scraped.stylesheets,
+                icon: scraped.icon,
+                license: scraped.license,
+                breadcrumbs: scraped.breadcrumbs,
+                content_sha256: scraped.content_sha256,
+                canonical: scraped.canonical,
+                aliases: Vec::new(),
+                fetch: scraped.fetch,
+            };
+            if tx.send(page).await.is_err() {
+                // After the time budget runs out the page is listed as pending instead.
+                if !crawler.stopped.load(Ordering::SeqCst) {
+                    error!("Failed to send scraped content back to main. Receiver closed.");
+                }
+                return false;
+            }
+            true
+        }
+        Err(e) => {
+            error!("Failed to scrape {}: {}", url, e);
+            crawler.failures.lock().unwrap().push(Failure {
+                seed,
+                url,
+                error: e,
+            });
+            false
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+/// `url(...)` references and `@import "..."` rules, quoted or not.
+static URL_REFERENCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"url\(\s*(?:"([^"]*)"|'([^']*)'|([^)'"\s]+))\s*\)|@import\s+(?:"([^"]*)"|'([^']*)')"#,
+    )
+    .unwrap()
+});
+
+/// The URLs a stylesheet refers to: fonts, background images and imported stylesheets.
+pub fn url_references(css: &str) -> Vec<&str> {
+    URL_REFERENCE
+        .captures_iter(css)
+        .filter_map(|captures| reference(&captures))
+        .filter(|reference| !reference.starts_with("data:"))
+        .collect()
+}
+
+/// Replaces every URL the stylesheet refers to with `replace(url)`, keeping those for
+/// which it returns `None`.
+pub fn rewrite_urls(css: &str, replace: impl Fn(&str) -> Option<String>) -> String {
+    URL_REFERENCE
+        .replace_all(css, |captures: &Captures| {
+            let original = captures[0].to_string();
+            let Some(reference) = reference(captures).filter(|r| !r.starts_with("data:")) else {
+                return original;
+            };
+            match replace(reference) {
+                Some(replacement) if original.starts_with("@import") => {
+                    format!("@import url(\"{}\")", replacement)
+                }
+                Some(replacement) => format!("url(\"{}\")", replacement),
+                None => original,
+            }
+        })
+        .into_owned()
+}
+
+fn reference<'a>(captures: &Captures<'a>) -> Option<&'a str> {
+    (1..=5)
+        .find_map(|group| captures.get(group))
+        .map(|reference| reference.as_str().trim())
+        .filter(|reference| !reference.is_empty())
+}
+
+/// Strips comments and the whitespace a stylesheet does not need, leaving strings alone.
+///
+/// Spaces are only removed where they cannot matter: around braces, semicolons, commas and
+/// `>` and after colons and opening parentheses. They stay elsewhere, since `a :hover`
+/// and `calc(1px + 2px)` need theirs.
+pub fn minify(css: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut chars = css.chars().peekable();
+    let mut space = false;
+    while let Some(c) = chars.next() {
+        if c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            let mut previous = ' ';
+            for c in chars.by_ref() {
+                if previous == '*' && c == '/' {
+                    break;
+                }
+                previous = c;
+            }
+            space = true;
+            continue;
+        }
+        if c.is_whitespace() {
+            space = true;
+            continue;
+        }
+        if space
+            && !out.is_empty()
+            && !out.ends_with(['{', '}', ';', ',', '>', ':', '('])
+            && !matches!(c, '{' | '}' | ';' | ',' | '>' | ')')
+        {
+            out.push(' ');
+        }
+        space = false;
+        if c == '}' && out.ends_with(';') {
+            out.pop();
+        }
+        out.push(c);
+        if c == '"' || c == '\'' {
+            let mut escaped = false;
+            for inner in chars.by_ref() {
+                out.push(inner);
+                if inner == c && !escaped {
+                    break;
+                }
+                escaped = inner == '\\' && !escaped;
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,84 @@
+use crate::crawl::Page;
+use crate::dom;
+use scraper::{ElementRef, Html, Node};
+
+/// Text suggesting a page has collapsed content, so pages without any are not parsed.
+const MARKERS: &[&str] = &["<details", "collapse", "aria-expanded"];
+
+/// Opens every collapsed section of every page, for `--expand-details`, since a PDF or
+/// EPUB has no way to unfold it: `<details>` elements get `open`, and the content that
+/// Docusaurus folds away with inline styles, Bootstrap's `.collapse` blocks and other
+/// elements marked `aria-expanded="false"` are shown.
+pub fn expand(pages: &mut [Page]) {
+    for page in pages {
+        if !MARKERS.iter().any(|marker| page.html.contains(marker)) {
+            continue;
+        }
+        let mut fragment = Html::parse_fragment(&page.html);
+        let ids = fragment
+            .root_element()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .filter(|element| is_collapsible(*element) || in_details(*element))
+            .map(|element| element.id())
+            .collect::<Vec<_>>();
+        if ids.is_empty() {
+            continue;
+        }
+        for id in ids {
+            if let Node::Element(element) = fragment.tree.get_mut(id).unwrap().value() {
+                let classes = element.classes().map(str::to_string).collect::<Vec<_>>();
+                if element.name() == "details" {
+                    dom::set_attr(&mut element.attrs, "open", "");
+                }
+                if classes.iter().any(|class| class == "collapse")
+                    && !classes.iter().any(|class| class == "show")
+                {
+                    dom::set_attr(
+                        &mut element.attrs,
+                        "class",
+                        &format!("{} show", classes.join(" ")),
+                    );
+                }
+                if element.attr("aria-expanded") == Some("false") {
+                    dom::set_attr(&mut element.attrs, "aria-expanded", "true");
+                }
+                let folded = element.attr("style").is_some_and(is_folding_style);
+                element.attrs.retain(|name, _| {
+                    !(&*name.local == "hidden" || (folded && &*name.local == "style"))
+                });
+            }
+        }
+        page.html = fragment.root_element().inner_html();
+    }
+}
+
+fn is_collapsible(element: ElementRef) -> bool {
+    let value = element.value();
+    value.name() == "details"
+        || value.classes().any(|class| class == "collapse")
+        || value.attr("aria-expanded") == Some("false")
+}
+
+/// Whether the element is inside a `<details>`, where Docusaurus hides the content
+/// until its script unfolds it.
+fn in_details(element: ElementRef) -> bool {
+    element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(|ancestor| ancestor.value().name() == "details")
+}
+
+/// Whether an inline style hides the element, as `display: none` or `height: 0` do.
+fn is_folding_style(style: &str) -> bool {
+    style.split(';').any(|declaration| {
+        let Some((property, value)) = declaration.split_once(':') else {
+            return false;
+        };
+        let property = property.trim().to_ascii_lowercase();
+        matches!(
+            (property.as_str(), value.trim()),
+            ("display", "none") | ("height", "0" | "0px")
+        )
+    })
+}
@@ -0,0 +1,76 @@
+use crate::profile::Profile;
+use scraper::{Html, Selector};
+
+/// Guesses which documentation generator produced a page.
+///
+/// Looks at the generator meta tag first, then at asset paths and DOM landmarks
+/// that each generator leaves behind. Returns `None` for unrecognised sites.
+pub fn detect_profile(document: &Html) -> Option<Profile> {
+    let generator = Selector::parse("meta[name='generator']").unwrap();
+    let from_generator = document
+        .select(&generator)
+        .filter_map(|meta| meta.value().attr("content"))
+        .find_map(|content| profile_from_generator(&content.to_lowercase()));
+    if from_generator.is_some() {
+        return from_generator;
+    }
+
+    const FINGERPRINTS: &[(Profile, &str)] = &[
+        (
+            Profile::Mdbook,
+            "script[src*='book.js'], #mdbook-help-container",
+        ),
+        (Profile::Mdbook, "link[href*='css/chrome.css']"),
+        (Profile::Docusaurus, "#__docusaurus, .theme-doc-markdown"),
+        (
+            Profile::MkdocsMaterial,
+            ".md-container, link[href*='assets/stylesheets/main']",
+        ),
+        (
+            Profile::Sphinx,
+            "script[src*='_static/documentation_options.js']",
+        ),
+        (
+            Profile::Sphinx,
+            "link[href*='_static/pygments.css'], div.sphinxsidebar",
+        ),
+        (
+            Profile::Gitbook,
+            "link[href*='gitbook/'], .book-summary, .gitbook-root",
+        ),
+        (Profile::Rustdoc, "meta[name='rustdoc-vars'], #rustdoc-vars"),
+        (
+            Profile::Javadoc,
+            "frame[name='classFrame'], ul.navList[title='Navigation'], \
+             ul.nav-list[title='Navigation']",
+        ),
+        (Profile::Doxygen, "div.headertitle, #doc-content, #nav-tree"),
+    ];
+
+    FINGERPRINTS.iter().find_map(|(profile, selector)| {
+        let selector = Selector::parse(selector).unwrap();
+        document.select(&selector).next().map(|_| *profile)
+    })
+}
+
+fn profile_from_generator(generator: &str) -> Option<Profile> {
+    if generator.contains("mkdocs-material") {
+        Some(Profile::MkdocsMaterial)
+    } else if generator.contains("docusaurus") {
+        Some(Profile::Docusaurus)
+    } else if generator.contains("sphinx") || generator.contains("docutils") {
+        Some(Profile::Sphinx)
+    } else if generator.contains("gitbook") {
+        Some(Profile::Gitbook)
+    } else if generator.contains("mdbook") {
+        Some(Profile::Mdbook)
+    } else if generator.contains("rustdoc") {
+        Some(Profile::Rustdoc)
+    } else if generator.contains("javadoc") {
+        Some(Profile::Javadoc)
+    } else if generator.contains("doxygen") {
+        Some(Profile::Doxygen)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,34 @@
+use crate::config::Options;
+use crate::filter::Scope;
+use crate::profile::Profile;
+
+/// Where docs.rs serves the documentation it builds for every published crate.
+const DOCS_RS: &str = "https://docs.rs";
+
+/// Settings for `docs-rs <crate>[@<version>]`: a crawl of the rustdoc pages of the crate's
+/// library on docs.rs, from its root module down through the modules and their items.
+/// Without a version the latest release is scraped; a partial one such as `1.0` means its
+/// newest match, as docs.rs resolves it.
+pub fn options(spec: &str) -> Result<Options, String> {
+    let (name, version) = spec.split_once('@').unwrap_or((spec, "latest"));
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("Not a crate name: {:?}", name));
+    }
+    if version.is_empty() || version.contains('/') {
+        return Err(format!("Not a version of {}: {:?}", name, version));
+    }
+    // The library target of a crate is named after it, with underscores for hyphens.
+    let library = name.replace('-', "_");
+    Ok(Options {
+        urls: vec![format!("{DOCS_RS}/{name}/{version}/{library}/index.html")],
+        profile: Some(Profile::Rustdoc),
+        // Keeps the crawl to the crate's own items, away from its source files and the
+        // crates it depends on.
+        scope: Some(Scope::SamePath),
+        ..Options::default()
+    })
+}
@@ -0,0 +1,72 @@
+use ego_tree::{NodeId, NodeRef, Tree};
+use scraper::node::Attributes;
+use scraper::{ElementRef, Html, Node};
+
+/// Puts the nodes of `html` in place of the node `id`.
+pub fn replace(tree: &mut Tree<Node>, id: NodeId, html: &str) {
+    let fragment = Html::parse_fragment(html);
+    for child in fragment.root_element().children() {
+        let copy = tree
+            .get_mut(id)
+            .unwrap()
+            .insert_before(child.value().clone())
+            .id();
+        copy_children(tree, copy, child);
+    }
+    tree.get_mut(id).unwrap().detach();
+}
+
+fn copy_children(tree: &mut Tree<Node>, parent: NodeId, source: NodeRef<Node>) {
+    for child in source.children() {
+        let copy = tree
+            .get_mut(parent)
+            .unwrap()
+            .append(child.value().clone())
+            .id();
+        copy_children(tree, copy, child);
+    }
+}
+
+/// Puts the nodes of `html` at the start of the element `parent`.
+pub fn prepend(tree: &mut Tree<Node>, parent: NodeId, html: &str) {
+    let fragment = Html::parse_fragment(html);
+    for child in fragment.root_element().children().rev() {
+        let copy = tree
+            .get_mut(parent)
+            .unwrap()
+            .prepend(child.value().clone())
+            .id();
+        copy_children(tree, copy, child);
+    }
+}
+
+/// Sets an attribute, keeping its place if the element already has it.
+pub fn set_attr(attrs: &mut Attributes, name: &str, value: &str) {
+    match attrs.iter_mut().find(|(key, _)| &*key.local == name) {
+        Some((_, existing)) => *existing = value.into(),
+        None => {
+            // Any attribute's name will do as a template; only the local name differs.
+            // Elements without attributes borrow one from a parsed element.
+            let mut key = match attrs.keys().next() {
+                Some(key) => key.clone(),
+                None => {
+                    let fragment = Html::parse_fragment("<p a></p>");
+                    let template = fragment
+                        .root_element()
+                        .first_child()
+                        .and_then(ElementRef::wrap);
+                    template
+                        .unwrap()
+                        .value()
+                        .attrs
+                        .keys()
+                        .next()
+                        .unwrap()
+                        .clone()
+                }
+            };
+            key.local = name.into();
+            attrs.insert(key, value.into());
+        }
+    }
+}
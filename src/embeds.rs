@@ -0,0 +1,126 @@
+use crate::crawl::Page;
+use crate::dom;
+use crate::output::escape_html;
+use scraper::{ElementRef, Html, Selector};
+use std::sync::LazyLock;
+use url::Url;
+
+/// Frames and plugins that show another page or an interactive widget.
+static EMBEDS: LazyLock<Selector> = LazyLock::new(|| {
+    Selector::parse("iframe[src], iframe[data-src], embed[src], object[data]").unwrap()
+});
+
+/// Sites commonly embedded in documentation, by host, with what their embeds show.
+const KNOWN_HOSTS: &[(&str, &str)] = &[
+    ("youtube.com", "YouTube video"),
+    ("youtube-nocookie.com", "YouTube video"),
+    ("vimeo.com", "Vimeo video"),
+    ("loom.com", "Loom video"),
+    ("codepen.io", "CodePen"),
+    ("codesandbox.io", "CodeSandbox"),
+    ("stackblitz.com", "StackBlitz"),
+    ("jsfiddle.net", "JSFiddle"),
+    ("play.rust-lang.org", "Rust Playground"),
+    ("replit.com", "Replit"),
+    ("figma.com", "Figma design"),
+    ("docs.google.com", "Google document"),
+    ("gist.github.com", "GitHub gist"),
+    ("asciinema.org", "Terminal recording"),
+];
+
+/// Replaces the embedded videos, frames and plugins of every page, which show nothing
+/// offline, with a placeholder naming them and linking to what they showed, unless
+/// `--keep-embeds` is set:
+///
+/// ```html
+/// <div class="embed"><p class="embed-title"><strong>YouTube video: Intro</strong></p><p><a href="...">...</a></p></div>
+/// ```
+///
+/// Embedded players link to the page of the video rather than to the bare player.
+pub fn replace(pages: &mut [Page]) {
+    for page in pages {
+        if !["<iframe", "<embed", "<object"]
+            .iter()
+            .any(|marker| page.html.contains(marker))
+        {
+            continue;
+        }
+        let mut fragment = Html::parse_fragment(&page.html);
+        let placeholders = fragment
+            .select(&EMBEDS)
+            .filter(|embed| !in_embed(*embed))
+            .map(|embed| (embed.id(), placeholder(embed, &page.url)))
+            .collect::<Vec<_>>();
+        if placeholders.is_empty() {
+            continue;
+        }
+        for (id, html) in placeholders {
+            dom::replace(&mut fragment.tree, id, &html);
+        }
+        page.html = fragment.root_element().inner_html();
+    }
+}
+
+/// Whether the element is the fallback content of another embed, which goes with it.
+fn in_embed(element: ElementRef) -> bool {
+    element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(|ancestor| EMBEDS.matches(&ancestor))
+}
+
+fn placeholder(embed: ElementRef, base: &Url) -> String {
+    let value = embed.value();
+    let source = ["src", "data-src", "data"]
+        .iter()
+        .find_map(|name| value.attr(name))
+        .unwrap_or_default();
+    let url = base.join(source.trim()).ok().map(|url| page_url(&url));
+    let kind = url
+        .as_ref()
+        .and_then(|url| url.host_str())
+        .and_then(|host| {
+            KNOWN_HOSTS
+                .iter()
+                .find(|(known, _)| host == *known || host.ends_with(&format!(".{}", known)))
+        })
+        .map_or("Embedded content", |(_, kind)| kind);
+    let title = value
+        .attr("title")
+        .or_else(|| value.attr("aria-label"))
+        .map(str::trim)
+        .filter(|title| !title.is_empty());
+    let heading = match title {
+        Some(title) => format!("{}: {}", kind, title),
+        None => kind.to_string(),
+    };
+    let mut html = format!(
+        "<div class=\"embed\"><p class=\"embed-title\"><strong>{}</strong></p>",
+        escape_html(&heading)
+    );
+    if let Some(url) = url {
+        html.push_str(&format!(
+            "<p><a href=\"{url}\">{url}</a></p>",
+            url = escape_html(url.as_str())
+        ));
+    }
+    html.push_str("</div>");
+    html
+}
+
+/// The page to watch an embedded video on, for the players of YouTube and Vimeo, or
+/// else `url` itself.
+fn page_url(url: &Url) -> Url {
+    let host = url.host_str().unwrap_or_default();
+    let segments = url.path_segments().map(Iterator::collect::<Vec<_>>);
+    let watch = match (host, segments.as_deref()) {
+        (host, Some(["embed", id]))
+            if host.ends_with("youtube.com") || host.ends_with("youtube-nocookie.com") =>
+        {
+            format!("https://www.youtube.com/watch?v={}", id)
+        }
+        ("player.vimeo.com", Some(["video", id])) => format!("https://vimeo.com/{}", id),
+        _ => return url.clone(),
+    };
+    Url::parse(&watch).unwrap_or_else(|_| url.clone())
+}
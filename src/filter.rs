@@ -0,0 +1,63 @@
+use clap::ValueEnum;
+use regex::Regex;
+use serde::Deserialize;
+use url::Url;
+
+/// Which discovered URLs count as part of the site being crawled, relative to the start URL.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    /// Same host, and at or below the start URL's directory.
+    SamePath,
+    /// Same host.
+    #[default]
+    SameDomain,
+    /// Anything reachable.
+    Any,
+}
+
+impl Scope {
+    pub fn contains(self, start: &Url, url: &Url) -> bool {
+        match self {
+            Scope::Any => true,
+            Scope::SameDomain => start.host_str() == url.host_str(),
+            Scope::SamePath => {
+                let directory = &start.path()[..=start.path().rfind('/').unwrap_or(0)];
+                start.host_str() == url.host_str() && url.path().starts_with(directory)
+            }
+        }
+    }
+}
+
+/// Decides which discovered URLs are eligible for crawling.
+#[derive(Debug, Default)]
+pub struct UrlFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl UrlFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<UrlFilter, String> {
+        Ok(UrlFilter {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// A URL passes if it matches at least one include pattern (when any are given)
+    /// and none of the exclude patterns.
+    pub fn allows(&self, url: &Url) -> bool {
+        let url = url.as_str();
+        (self.include.is_empty() || self.include.iter().any(|re| re.is_match(url)))
+            && !self.exclude.iter().any(|re| re.is_match(url))
+    }
+}
+
+fn compile(patterns: &[String]) -> Result<Vec<Regex>, String> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern).map_err(|e| format!("Invalid URL pattern '{}': {}", pattern, e))
+        })
+        .collect()
+}
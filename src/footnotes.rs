@@ -0,0 +1,174 @@
+use crate::crawl::Page;
+use crate::dom;
+use ego_tree::NodeId;
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Footnotes as Pandoc, Python-Markdown, GitHub-flavoured Markdown, Sphinx and mdBook
+/// write them.
+static DEFINITIONS: LazyLock<Selector> = LazyLock::new(|| {
+    Selector::parse(
+        ".footnotes li[id], .footnote li[id], [data-footnotes] li[id], aside.footnote[id], \
+         table.footnote[id], dl.footnote > dt[id], div.footnote-definition[id]",
+    )
+    .unwrap()
+});
+
+/// The number a footnote shows, inside it.
+static LABELS: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse(".label, .footnote-definition-label").unwrap());
+
+static LINKS: LazyLock<Selector> = LazyLock::new(|| Selector::parse("a[href^='#']").unwrap());
+
+static NUMBER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+").unwrap());
+
+/// A change to make to a page.
+enum Edit {
+    /// Set an attribute of the element.
+    Attr(NodeId, &'static str, String),
+    /// Put the number in place of the first number in the element's text.
+    Number(NodeId, usize),
+}
+
+/// Numbers the footnotes of all pages in one sequence, for `--renumber-footnotes`,
+/// instead of starting again on every page, and gives them ids unique across the book:
+/// the footnotes become `id="fn-N"`, the references to them `id="fnref-N"`.
+///
+/// The numbers the references and footnotes show, the links between them both ways and
+/// the `start` of footnote lists are updated to match. References showing a name rather
+/// than a number keep it.
+pub fn renumber(pages: &mut [Page]) {
+    let mut count = 0;
+    for page in pages {
+        if !page.html.contains("footnote") {
+            continue;
+        }
+        let mut fragment = Html::parse_fragment(&page.html);
+        let edits = edits(&fragment, &mut count);
+        if edits.is_empty() {
+            continue;
+        }
+        for edit in edits {
+            match edit {
+                Edit::Attr(id, name, value) => {
+                    if let Node::Element(element) = fragment.tree.get_mut(id).unwrap().value() {
+                        dom::set_attr(&mut element.attrs, name, &value);
+                    }
+                }
+                Edit::Number(id, number) => {
+                    if let Some(text) = number_text(&fragment, id)
+                        && let Node::Text(text) = fragment.tree.get_mut(text).unwrap().value()
+                    {
+                        let new = NUMBER.replacen(&text.text, 1, number.to_string());
+                        text.text = new.as_ref().into();
+                    }
+                }
+            }
+        }
+        page.html = fragment.root_element().inner_html();
+    }
+}
+
+/// The changes renumbering the page's footnotes from `count` on makes, counting them.
+fn edits(fragment: &Html, count: &mut usize) -> Vec<Edit> {
+    let definitions = fragment.select(&DEFINITIONS).collect::<Vec<_>>();
+    let mut numbers = HashMap::new();
+    let mut edits = Vec::new();
+    for definition in &definitions {
+        let id = definition.value().id().unwrap();
+        if numbers.contains_key(id) {
+            continue;
+        }
+        *count += 1;
+        numbers.insert(id, *count);
+        edits.push(Edit::Attr(definition.id(), "id", format!("fn-{}", count)));
+        let label = if definition.value().classes().any(|class| class == "label") {
+            Some(*definition)
+        } else {
+            definition.select(&LABELS).next()
+        };
+        if let Some(label) = label {
+            edits.push(Edit::Number(label.id(), *count));
+        }
+        if definition.value().name() == "li"
+            && *count > 1
+            && let Some(list) = definition.parent().and_then(ElementRef::wrap)
+            && list.value().name() == "ol"
+            && list
+                .children()
+                .filter_map(ElementRef::wrap)
+                .next()
+                .is_some_and(|first| first.id() == definition.id())
+        {
+            edits.push(Edit::Attr(list.id(), "start", count.to_string()));
+        }
+    }
+    if numbers.is_empty() {
+        return edits;
+    }
+
+    let in_definition = |link: ElementRef| {
+        link.ancestors().any(|ancestor| {
+            definitions
+                .iter()
+                .any(|definition| definition.id() == ancestor.id())
+        })
+    };
+    let mut references: HashMap<usize, usize> = HashMap::new();
+    let mut renamed = HashMap::new();
+    for link in fragment.select(&LINKS).filter(|link| !in_definition(*link)) {
+        let target = &link.value().attr("href").unwrap()[1..];
+        let Some(&number) = numbers.get(target) else {
+            continue;
+        };
+        edits.push(Edit::Attr(link.id(), "href", format!("#fn-{}", number)));
+        let text = link.text().collect::<String>();
+        if text
+            .trim()
+            .trim_matches(['[', ']'])
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+        {
+            edits.push(Edit::Number(link.id(), number));
+        }
+        // The reference's id is on the link or on the `<sup>` around it.
+        let holder = Some(link)
+            .filter(|link| link.value().id().is_some())
+            .or_else(|| link.parent().and_then(ElementRef::wrap))
+            .filter(|holder| holder.value().id().is_some());
+        if let Some(holder) = holder {
+            let seen = references.entry(number).or_insert(0);
+            *seen += 1;
+            let new = match seen {
+                1 => format!("fnref-{}", number),
+                seen => format!("fnref-{}-{}", number, seen),
+            };
+            renamed.insert(holder.value().id().unwrap(), new.clone());
+            edits.push(Edit::Attr(holder.id(), "id", new));
+        }
+    }
+
+    for link in fragment.select(&LINKS).filter(|link| in_definition(*link)) {
+        let target = &link.value().attr("href").unwrap()[1..];
+        if let Some(new) = renamed.get(target) {
+            edits.push(Edit::Attr(link.id(), "href", format!("#{}", new)));
+        }
+    }
+    edits
+}
+
+/// The first text node under the element with a number in it.
+fn number_text(fragment: &Html, id: NodeId) -> Option<NodeId> {
+    fragment
+        .tree
+        .get(id)?
+        .descendants()
+        .find(|node| {
+            node.value()
+                .as_text()
+                .is_some_and(|text| NUMBER.is_match(text))
+        })
+        .map(|node| node.id())
+}
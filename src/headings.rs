@@ -0,0 +1,40 @@
+use crate::crawl::Page;
+use scraper::{Html, Node};
+use std::collections::BTreeSet;
+
+/// The level of a heading element's name, 1 for `h1` to 6 for `h6`.
+fn level(name: &str) -> Option<usize> {
+    match name.as_bytes() {
+        [b'h', digit @ b'1'..=b'6'] => Some((digit - b'0') as usize),
+        _ => None,
+    }
+}
+
+/// Renumbers the headings of every page, for `--normalize-headings`, so the levels it
+/// uses run from `<h1>` down without gaps: a page using `<h2>` and `<h4>` gets `<h1>` and
+/// `<h2>`. Chapters then all start at the same level and nest the same way in the
+/// combined document and its outline, whichever level their site started at.
+pub fn normalize(pages: &mut [Page]) {
+    for page in pages {
+        let mut fragment = Html::parse_fragment(&page.html);
+        let used = fragment
+            .tree
+            .values()
+            .filter_map(|node| node.as_element())
+            .filter_map(|element| level(element.name()))
+            .collect::<BTreeSet<_>>();
+        if used.iter().copied().eq(1..=used.len()) {
+            continue;
+        }
+        let levels = used.into_iter().collect::<Vec<_>>();
+        for node in fragment.tree.values_mut() {
+            if let Node::Element(element) = node
+                && let Some(level) = level(element.name())
+            {
+                let new = levels.iter().position(|used| *used == level).unwrap() + 1;
+                element.name.local = format!("h{}", new).as_str().into();
+            }
+        }
+        page.html = fragment.root_element().inner_html();
+    }
+}
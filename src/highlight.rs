@@ -0,0 +1,120 @@
+use crate::crawl::Page;
+use crate::output::code_language;
+use regex::{Captures, Regex};
+use scraper::{ElementRef, Html, Selector};
+use std::sync::LazyLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::html::{IncludeBackground, styled_line_to_highlighted_html};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// Theme used unless `--highlight-theme` names another.
+pub const DEFAULT_THEME: &str = "InspiredGitHub";
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static THEMES: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+/// A `<pre>` element with its attributes and content.
+static PRE_BLOCK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<pre\b([^>]*)>(.*?)</pre>").unwrap());
+
+/// The `<code>` start tag a `<pre>` block begins with.
+static CODE_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*<code\b[^>]*>").unwrap());
+
+/// Checks that `--highlight-theme` names a built-in theme.
+pub fn check_theme(name: &str) -> Result<(), String> {
+    if THEMES.themes.contains_key(name) {
+        return Ok(());
+    }
+    let names = THEMES.themes.keys().cloned().collect::<Vec<_>>();
+    Err(format!(
+        "Unknown highlight theme '{}'; choose one of: {}",
+        name,
+        names.join(", ")
+    ))
+}
+
+/// Colours the code blocks of every page whose language is known from a class like
+/// `language-rust`, for `--highlight`. Each token becomes a `<span>` with its colour in a
+/// `style` attribute, so the output needs no stylesheet or script to show it.
+pub fn highlight(pages: &mut [Page], theme: &str) {
+    let theme = &THEMES.themes[theme];
+    for page in pages {
+        // Work out each block's language from the parsed page, where its ancestors are
+        // known, then rewrite the blocks in the same order in the text.
+        let fragment = Html::parse_fragment(&page.html);
+        let blocks = fragment
+            .select(&Selector::parse("pre").unwrap())
+            .map(|pre| {
+                let language = pre
+                    .descendants()
+                    .filter_map(ElementRef::wrap)
+                    .chain(pre.ancestors().filter_map(ElementRef::wrap))
+                    .find_map(code_language)?;
+                let code = pre.text().collect::<String>();
+                highlight_code(&code, &language, theme)
+            })
+            .collect::<Vec<_>>();
+        if blocks.iter().all(Option::is_none)
+            || PRE_BLOCK.find_iter(&page.html).count() != blocks.len()
+        {
+            continue;
+        }
+
+        let mut blocks = blocks.into_iter();
+        let html = PRE_BLOCK.replace_all(&page.html, |pre: &Captures| {
+            let Some(Some(highlighted)) = blocks.next() else {
+                return pre[0].to_string();
+            };
+            let attributes = &pre[1];
+            let style = if attributes.contains("style=") {
+                String::new()
+            } else {
+                background(theme)
+            };
+            match CODE_TAG.find(&pre[2]) {
+                Some(code) => format!(
+                    "<pre{}{}>{}{}</code></pre>",
+                    attributes,
+                    style,
+                    code.as_str().trim_start(),
+                    highlighted
+                ),
+                None => format!("<pre{}{}>{}</pre>", attributes, style, highlighted),
+            }
+        });
+        page.html = html.into_owned();
+    }
+}
+
+/// The code as `<span>`s coloured by `theme`, or `None` if the language is unknown.
+fn highlight_code(code: &str, language: &str, theme: &Theme) -> Option<String> {
+    let syntax = SYNTAXES.find_syntax_by_token(language)?;
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let mut html = String::new();
+    for line in LinesWithEndings::from(code) {
+        let regions = highlighter.highlight_line(line, &SYNTAXES).ok()?;
+        html.push_str(&styled_line_to_highlighted_html(&regions, IncludeBackground::No).ok()?);
+    }
+    Some(html)
+}
+
+/// A `style` attribute giving a block the theme's colours.
+fn background(theme: &Theme) -> String {
+    let hex = |color: syntect::highlighting::Color| {
+        format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+    };
+    let mut style = String::new();
+    if let Some(color) = theme.settings.background {
+        style.push_str(&format!("background-color:{};", hex(color)));
+    }
+    if let Some(color) = theme.settings.foreground {
+        style.push_str(&format!("color:{};", hex(color)));
+    }
+    if style.is_empty() {
+        style
+    } else {
+        format!(" style=\"{}\"", style)
+    }
+}
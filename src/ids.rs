@@ -0,0 +1,157 @@
+use crate::crawl::Page;
+use crate::title::slugify;
+use scraper::node::Element;
+use scraper::{ElementRef, Html, Node};
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+/// Ids the combined HTML gives elements of its own, besides the `chapter-N` sections.
+const RESERVED: &[&str] = &[
+    "toc",
+    "search",
+    "search-input",
+    "search-results",
+    "search-index",
+];
+
+/// Attributes holding the ids of other elements of the same page, separated by spaces.
+const ID_REFERENCES: &[&str] = &[
+    "for",
+    "headers",
+    "aria-controls",
+    "aria-describedby",
+    "aria-labelledby",
+    "aria-owns",
+];
+
+/// Gives every id that an earlier page, or the book layout, already uses a prefix naming
+/// its page, so anchors still lead to the right place once the pages share one document:
+/// the second page's `id="content"` becomes `id="ch02-content"` for a page at `ch02.html`.
+///
+/// Links to a renamed id are rewritten to match, both within its page and from the other
+/// pages, as are `for` and `aria-*` references. Ids used only once are left alone.
+pub fn deduplicate(pages: &mut [Page]) {
+    let mut fragments = pages
+        .iter()
+        .map(|page| Html::parse_fragment(&page.html))
+        .collect::<Vec<_>>();
+
+    let mut taken = RESERVED
+        .iter()
+        .map(|id| id.to_string())
+        .collect::<HashSet<_>>();
+    let mut renames: HashMap<Url, HashMap<String, String>> = HashMap::new();
+    for (position, (page, fragment)) in pages.iter().zip(&fragments).enumerate() {
+        let mut own = HashSet::new();
+        let mut renamed = HashMap::new();
+        for element in fragment
+            .root_element()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+        {
+            let Some(id) = element.value().attr("id").filter(|id| !id.is_empty()) else {
+                continue;
+            };
+            // A page repeating one of its own ids is left as the site wrote it.
+            if !own.insert(id) || (!is_reserved(id) && taken.insert(id.to_string())) {
+                continue;
+            }
+            let prefix = page_prefix(&page.url, position);
+            let mut new = format!("{}-{}", prefix, id);
+            let mut count = 2;
+            while !taken.insert(new.clone()) {
+                new = format!("{}-{}-{}", prefix, id, count);
+                count += 1;
+            }
+            renamed.insert(id.to_string(), new);
+        }
+        if !renamed.is_empty() {
+            renames.insert(without_fragment(&page.url), renamed);
+        }
+    }
+    if renames.is_empty() {
+        return;
+    }
+
+    for (page, fragment) in pages.iter_mut().zip(&mut fragments) {
+        let own = renames.get(&without_fragment(&page.url));
+        let mut changed = false;
+        for node in fragment.tree.values_mut() {
+            if let Node::Element(element) = node {
+                changed |= rewrite(element, &page.url, own, &renames);
+            }
+        }
+        if changed {
+            page.html = fragment.root_element().inner_html();
+        }
+    }
+}
+
+/// Points the element's id, id references and link at the renamed ids. Returns whether
+/// anything changed.
+fn rewrite(
+    element: &mut Element,
+    base: &Url,
+    own: Option<&HashMap<String, String>>,
+    renames: &HashMap<Url, HashMap<String, String>>,
+) -> bool {
+    let mut changed = false;
+    for (name, value) in element.attrs.iter_mut() {
+        let new = match &*name.local {
+            "id" => own.and_then(|own| own.get(&**value).cloned()),
+            name if ID_REFERENCES.contains(&name) => own.and_then(|own| {
+                let ids = value
+                    .split_whitespace()
+                    .map(|id| own.get(id).map_or(id, String::as_str))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (ids != value.trim()).then_some(ids)
+            }),
+            "href" => {
+                let href = &**value;
+                base.join(href).ok().and_then(|url| {
+                    let id = url.fragment()?;
+                    let new = renames.get(&without_fragment(&url))?.get(id)?;
+                    let (path, _) = href.split_once('#')?;
+                    Some(format!("{}#{}", path, new))
+                })
+            }
+            _ => None,
+        };
+        if let Some(new) = new {
+            *value = new.into();
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Whether the book layout gives an element this id.
+fn is_reserved(id: &str) -> bool {
+    RESERVED.contains(&id)
+        || id
+            .strip_prefix("chapter-")
+            .is_some_and(|number| number.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// What renamed ids of a page start with: the slug of its file name, e.g. `ch04_01` for
+/// `ch04-01.html`, or its position in the crawl if the URL has no file name.
+fn page_prefix(url: &Url, position: usize) -> String {
+    let name = url
+        .path_segments()
+        .and_then(|mut segments| segments.rfind(|segment| !segment.is_empty()))
+        .unwrap_or_default();
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    let slug = slugify(stem);
+    if slug.is_empty() || slug == "index" {
+        format!("page{}", position + 1)
+    } else {
+        slug
+    }
+}
+
+fn without_fragment(url: &Url) -> Url {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url
+}
@@ -0,0 +1,128 @@
+use crate::dom::set_attr;
+use scraper::node::Element;
+use scraper::{Html, Node};
+
+/// Attributes lazy-loading scripts read the real image URL from, most common first.
+const LAZY_SRC: &[&str] = &[
+    "data-src",
+    "data-lazy-src",
+    "data-original",
+    "data-lazy",
+    "data-url",
+];
+
+/// Attributes lazy-loading scripts read the real `srcset` from.
+const LAZY_SRCSET: &[&str] = &["data-srcset", "data-lazy-srcset"];
+
+/// Rewrites images so they show without scripts or a browser choosing a size: the URL a
+/// lazy-loading script would have set becomes `src`, and an image with a `srcset`, or
+/// inside a `<picture>` with one, gets its largest candidate as `src` instead.
+///
+/// The `srcset`, `sizes` and lazy-loading attributes and the `<source>` elements of
+/// pictures are removed, so the image that gets downloaded is the one shown.
+pub fn load_images(document: &mut Html) {
+    let pictures = document
+        .tree
+        .nodes()
+        .filter(|node| is_element(node.value(), "picture"))
+        .map(|node| node.id())
+        .collect::<Vec<_>>();
+    for picture in pictures {
+        let picture = document.tree.get(picture).unwrap();
+        let sources = picture
+            .children()
+            .filter(|child| is_element(child.value(), "source"))
+            .collect::<Vec<_>>();
+        let srcset = sources.iter().find_map(|source| match source.value() {
+            Node::Element(element) => srcset(element).map(str::to_string),
+            _ => None,
+        });
+        let img = picture
+            .children()
+            .find(|child| is_element(child.value(), "img"))
+            .map(|img| img.id());
+        let sources = sources.iter().map(|source| source.id()).collect::<Vec<_>>();
+
+        if let (Some(srcset), Some(img)) = (srcset, img)
+            && let Node::Element(element) = document.tree.get_mut(img).unwrap().value()
+            && self::srcset(element).is_none()
+        {
+            set_attr(&mut element.attrs, "srcset", &srcset);
+        }
+        for source in sources {
+            document.tree.get_mut(source).unwrap().detach();
+        }
+    }
+
+    for node in document.tree.values_mut() {
+        let Node::Element(element) = node else {
+            continue;
+        };
+        if element.name() != "img" {
+            continue;
+        }
+        let src = srcset(element)
+            .and_then(best_candidate)
+            .or_else(|| first_attr(element, LAZY_SRC))
+            .map(str::to_string);
+        if let Some(src) = src {
+            set_attr(&mut element.attrs, "src", &src);
+        }
+        element.attrs.retain(|name, _| {
+            let name = &*name.local;
+            !matches!(name, "srcset" | "sizes" | "loading")
+                && !LAZY_SRC.contains(&name)
+                && !LAZY_SRCSET.contains(&name)
+        });
+    }
+}
+
+fn is_element(node: &Node, name: &str) -> bool {
+    matches!(node, Node::Element(element) if element.name() == name)
+}
+
+/// The `srcset` of an image or `<source>`, preferring one a lazy-loading script would set.
+fn srcset(element: &Element) -> Option<&str> {
+    first_attr(element, LAZY_SRCSET).or_else(|| element.attr("srcset"))
+}
+
+/// The first of `names` the element has with a value other than whitespace.
+fn first_attr<'a>(element: &'a Element, names: &[&str]) -> Option<&'a str> {
+    names
+        .iter()
+        .filter_map(|name| element.attr(name))
+        .find(|value| !value.trim().is_empty())
+}
+
+/// The URL of the widest or densest candidate of a `srcset`, e.g. `big.png` for
+/// `small.png 480w, big.png 1080w`. A candidate without a descriptor counts as `1x`.
+fn best_candidate(srcset: &str) -> Option<&str> {
+    let mut best: Option<(f64, &str)> = None;
+    let mut rest = srcset;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let mut url = &rest[..end];
+        rest = &rest[end..];
+        let descriptor = if url.ends_with(',') {
+            url = url.trim_end_matches(',');
+            ""
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            let descriptor = rest[..end].trim();
+            rest = &rest[end..];
+            descriptor
+        };
+        let size = descriptor
+            .strip_suffix(['w', 'x'])
+            .and_then(|value| value.parse::<f64>().ok())
+            .unwrap_or(1.0);
+        if !url.is_empty() && best.is_none_or(|(largest, _)| size > largest) {
+            best = Some((size, url));
+        }
+    }
+    best.map(|(_, url)| url)
+}
@@ -0,0 +1,111 @@
+use crate::crawl::resolve_link;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use std::sync::LazyLock;
+use url::Url;
+
+/// Where sites put their license and copyright statements.
+static FOOTERS: LazyLock<Selector> = LazyLock::new(|| {
+    Selector::parse(
+        "footer, [role=contentinfo], .footer, #footer, .copyright, .license, .md-copyright, \
+         .footer__copyright",
+    )
+    .unwrap()
+});
+
+static LICENSE_LINKS: LazyLock<Selector> = LazyLock::new(|| {
+    Selector::parse("link[rel~='license'][href], a[rel~='license'][href]").unwrap()
+});
+
+static LINKS: LazyLock<Selector> = LazyLock::new(|| Selector::parse("a[href]").unwrap());
+
+/// Text that states a license or a copyright.
+static STATEMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)creative\s+commons|\bCC[\s-]BY\b|\bCC0\b|\bMIT\s+licen[sc]e|apache\s+licen[sc]e|\bGNU\b|\bGPL|\bBSD\b|\bMPL\b|public\s+domain|licen[sc]ed\s+under|copyright|\(c\)\s*\d{4}|©",
+    )
+    .unwrap()
+});
+
+/// Hosts and paths that license links point to.
+static LICENSE_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)creativecommons\.org|opensource\.org/licen|gnu\.org/licen|/licen[sc]e")
+        .unwrap()
+});
+
+/// Elements a statement is taken from whole, rather than from the inline element the
+/// match is in.
+const BLOCKS: &[&str] = &["aside", "div", "footer", "li", "p", "section", "small"];
+
+/// Longest statement kept, in characters, so a footer holding a whole sitemap is not
+/// copied into the book.
+const MAX_LENGTH: usize = 400;
+
+/// The license or copyright statement of a page.
+#[derive(Clone, PartialEq)]
+pub struct License {
+    /// The statement as the page words it, such as "© 2024 Example. Licensed under
+    /// CC BY 4.0.". Empty for a page that only links its license.
+    pub statement: String,
+    /// The license the statement links to.
+    pub url: Option<Url>,
+}
+
+/// Finds the license or copyright statement of a page, for `--attribution`: the innermost
+/// block of its footer that mentions a license or copyright, and the license it or a
+/// `rel="license"` link points to.
+pub fn detect(document: &Html, url: &Url) -> Option<License> {
+    let block = document
+        .select(&FOOTERS)
+        .flat_map(|footer| {
+            footer
+                .descendants()
+                .filter_map(ElementRef::wrap)
+                .collect::<Vec<_>>()
+        })
+        .filter(|element| BLOCKS.contains(&element.value().name()))
+        .filter(|element| STATEMENT.is_match(&text(*element)))
+        .find(|element| {
+            !element
+                .descendants()
+                .skip(1)
+                .filter_map(ElementRef::wrap)
+                .any(|inner| {
+                    BLOCKS.contains(&inner.value().name()) && STATEMENT.is_match(&text(inner))
+                })
+        });
+    let statement = block
+        .map(text)
+        .filter(|statement| statement.chars().count() <= MAX_LENGTH)
+        .unwrap_or_default();
+    let linked = |element: ElementRef| {
+        element
+            .select(&LINKS)
+            .filter_map(|link| link.value().attr("href"))
+            .filter(|href| LICENSE_URL.is_match(href))
+            .find_map(|href| resolve_link(url, href))
+    };
+    let license_url = block.and_then(linked).or_else(|| {
+        document
+            .select(&LICENSE_LINKS)
+            .filter_map(|link| link.value().attr("href"))
+            .find_map(|href| resolve_link(url, href))
+    });
+    if statement.is_empty() && license_url.is_none() {
+        return None;
+    }
+    Some(License {
+        statement,
+        url: license_url,
+    })
+}
+
+/// The element's text with its whitespace collapsed.
+fn text(element: ElementRef) -> String {
+    element
+        .text()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
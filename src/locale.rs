@@ -0,0 +1,75 @@
+use scraper::{Html, Selector};
+use url::Url;
+
+/// Primary language subtags that documentation sites commonly publish translations under.
+const LANGUAGES: &[&str] = &[
+    "ar", "bg", "bn", "ca", "cs", "da", "de", "el", "en", "es", "et", "fa", "fi", "fr", "he", "hi",
+    "hr", "hu", "id", "it", "ja", "ko", "lt", "lv", "ms", "nb", "nl", "no", "pl", "pt", "ro", "ru",
+    "sk", "sl", "sr", "sv", "th", "tr", "uk", "vi", "zh",
+];
+
+/// Lowercases a language tag and uses `-` as separator, e.g. `pt_BR` becomes `pt-br`.
+pub fn normalize(tag: &str) -> String {
+    tag.trim().replace('_', "-").to_lowercase()
+}
+
+/// Whether two language tags name the same language, ignoring region (`pt` and `pt-br` match).
+pub fn same_language(a: &str, b: &str) -> bool {
+    let primary = |tag: &str| {
+        normalize(tag)
+            .split('-')
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    };
+    primary(a) == primary(b)
+}
+
+/// The language declared on the page's `<html lang>` attribute.
+pub fn page_language(document: &Html) -> Option<String> {
+    let selector = Selector::parse("html[lang]").unwrap();
+    document
+        .select(&selector)
+        .next()
+        .and_then(|html| html.value().attr("lang"))
+        .map(normalize)
+        .filter(|lang| !lang.is_empty())
+}
+
+/// Whether a path segment names a language, such as `ja` or `pt-br`.
+pub fn is_language_segment(segment: &str) -> bool {
+    let tag = normalize(segment);
+    let mut parts = tag.split('-');
+    let primary = parts.next().unwrap_or_default();
+    let region_ok = parts.all(|part| (2..=4).contains(&part.len()));
+    LANGUAGES.contains(&primary) && region_ok && tag.split('-').count() <= 2
+}
+
+fn language_segment(url: &Url) -> Option<(usize, &str)> {
+    url.path_segments()?
+        .enumerate()
+        .find(|(_, segment)| is_language_segment(segment))
+}
+
+/// The language named by a path segment such as `/ja/` or `/pt-br/`, if any.
+pub fn url_language(url: &Url) -> Option<&str> {
+    language_segment(url).map(|(_, segment)| segment)
+}
+
+/// Replaces the language segment of `url` with `lang`. URLs without one are returned unchanged.
+pub fn with_language(url: &Url, lang: &str) -> Url {
+    let Some((index, _)) = language_segment(url) else {
+        return url.clone();
+    };
+    let segments = url
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, segment)| if i == index { lang } else { segment })
+        .collect::<Vec<_>>();
+
+    let mut rewritten = url.clone();
+    rewritten.set_path(&segments.join("/"));
+    rewritten
+}
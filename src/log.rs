@@ -0,0 +1,66 @@
+//! Levelled console output controlled by `-q`, `-v` and `-vv`.
+//!
+//! Errors and warnings go to stderr, everything else to stdout.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+pub const QUIET: u8 = 0;
+pub const INFO: u8 = 1;
+pub const DEBUG: u8 = 2;
+pub const TRACE: u8 = 3;
+
+static LEVEL: AtomicU8 = AtomicU8::new(INFO);
+
+/// Sets the level from the command-line flags: `-q` keeps only errors, each `-v` adds a level.
+pub fn init(verbose: u8, quiet: bool) {
+    let level = if quiet {
+        QUIET
+    } else {
+        INFO.saturating_add(verbose).min(TRACE)
+    };
+    LEVEL.store(level, Ordering::Relaxed);
+}
+
+pub fn enabled(level: u8) -> bool {
+    LEVEL.load(Ordering::Relaxed) >= level
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => {
+        eprintln!("error: {}", format_args!($($arg)*))
+    };
+}
+
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::INFO) {
+            eprintln!("warning: {}", format_args!($($arg)*));
+        }
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::INFO) {
+            println!($($arg)*);
+        }
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::DEBUG) {
+            println!($($arg)*);
+        }
+    };
+}
+
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::TRACE) {
+            println!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use {debug, error, info, trace, warning};
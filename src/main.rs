@@ -1,136 +1,205 @@
-use reqwest::Client;
-use scraper::{Html, Selector};
-use std::collections::HashSet;
-use std::fs;
+mod admonitions;
+mod annotations;
+mod assets;
+mod breadcrumbs;
+mod cli;
+mod config;
+mod crawl;
+mod css;
+mod details;
+mod detect;
+mod docs_rs;
+mod dom;
+mod embeds;
+mod filter;
+mod footnotes;
+mod headings;
+mod highlight;
+mod ids;
+mod lazy;
+mod license;
+mod locale;
+mod log;
+mod math;
+mod output;
+mod policy;
+mod profile;
+mod rate_limit;
+mod readability;
+mod readthedocs;
+mod sanitize;
+mod search;
+mod svg;
+mod tabs;
+mod title;
+mod version;
+
+use assets::{Assets, Required};
+use clap::{CommandFactory, Parser};
+use cli::{BIN_NAME, Cli, Command};
+use config::{Config, DEFAULT_CONFIG_FILE, Options, env_var, parse_url_list};
+use crawl::Crawler;
+use log::info;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::{Mutex, Semaphore, mpsc};
 use url::Url;
 
-async fn scrape_content(client: &Client, url: &Url) -> Result<(String, Option<Url>), String> {
-    println!("Scraping {}", url);
-
-    let response_text = client
-        .get(url.clone())
-        .send()
-        .await
-        .map_err(|e| format!("Request failed for {}: {}", url, e))?
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read response from {}:{}", url, e))?;
-
-    let document = Html::parse_document(&response_text);
-
-    let content = Selector::parse("main").unwrap();
-    let next_chaper_select = Selector::parse("a[title='Next chapter']").unwrap();
-
-    let chapter_html = if let Some(content_div) = document.select(&content).next() {
-        content_div.inner_html()
-    } else {
-        return Err(format!(
-            "Could not find div content on the current page : {}",
-            url,
-        ));
-    };
-
-    let next_chapter_url = if let Some(link_element) = document.select(&next_chaper_select).next() {
-        link_element
-            .value()
-            .attr("href")
-            .and_then(|href| url.join(href).ok())
-    } else {
-        None
-    };
-
-    Ok((chapter_html, next_chapter_url))
-}
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let start_url = Url::parse("https://doc.rust-lang.org/stable/book/title-page.html")?;
+    let mut cli = Cli::parse();
+    log::init(cli.verbose, cli.quiet);
 
-    const MAX_CONCURRENT_REQUESTS: usize = 50;
-    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS));
+    match &cli.command {
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(*shell, &mut Cli::command(), BIN_NAME, &mut io::stdout());
+            return Ok(());
+        }
+        Some(Command::Search {
+            index,
+            query,
+            limit,
+        }) => {
+            search::search(index, query, *limit)?;
+            return Ok(());
+        }
+        Some(Command::DocsRs { .. }) | None => {}
+    }
 
-    let (tx, mut rx) = mpsc::channel(100);
-    let client = Arc::new(Client::new());
-    let visited_urls = Arc::new(Mutex::new(HashSet::new()));
+    if let Some(Command::DocsRs { krate, options }) = cli.command.take() {
+        cli.options = (*options)
+            .merge(cli.options)
+            .merge(docs_rs::options(&krate)?);
+    }
 
-    spawn_scraping_task(
-        0,
-        start_url,
-        client.clone(),
-        tx.clone(),
-        semaphore.clone(),
-        visited_urls.clone(),
-    );
+    if cli.stdin {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        cli.options.urls.extend(parse_url_list(&input));
+    }
 
-    drop(tx);
+    let config_path = match cli.config {
+        Some(path) => Some(path),
+        None => env_var::<PathBuf>("CONFIG")?,
+    };
+    let file_options = match &config_path {
+        Some(path) => Options::from_file(path)?,
+        None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+            Options::from_file(Path::new(DEFAULT_CONFIG_FILE))?
+        }
+        None => Options::default(),
+    };
+    let options = cli.options.merge(file_options).merge(Options::from_env()?);
+    let config = Arc::new(Config::resolve(options)?);
 
-    let mut all_chapters = Vec::new();
-    while let Some((index, html)) = rx.recv().await {
-        all_chapters.push((index, html));
+    // Catch an existing output file before spending time on the crawl.
+    if !cli.dry_run {
+        output::check_output_path(&config)?;
     }
 
-    println!(
-        "\nCrawl complete. Scraped {} chapters. Sorting and saving to file...",
-        all_chapters.len()
-    );
+    let crawler = Arc::new(Crawler::new(config.clone())?);
+    if config.stream && !cli.dry_run {
+        let mut writer = output::StreamWriter::new(&config);
+        let report = crawl::run_streaming(crawler, |page, first_in_flight| {
+            writer.push(page, first_in_flight)
+        })
+        .await?;
+        let written = writer.finish(&report.failures)?;
+        list_pending(&report.pending);
+        info!(
+            "\nCrawl complete. Saved {} chapters ({} failed).",
+            written,
+            report.failures.len()
+        );
+        config.fail_on.check(written, report.failures.len())?;
+        return Ok(());
+    }
 
-    all_chapters.sort_by_key(|(index, _)| *index);
+    let report = crawl::run(crawler.clone()).await;
+    let mut pages = report.pages;
+    let failed = report.failures.len();
+    list_pending(&report.pending);
+
+    if cli.dry_run {
+        println!(
+            "\nDry run complete. {} pages would be scraped:",
+            pages.len()
+        );
+        for (position, page) in pages.iter().enumerate() {
+            println!("{:>4}. {}", position + 1, page.url);
+        }
+        config.fail_on.check(pages.len(), failed)?;
+        return Ok(());
+    }
 
-    let combined_html = all_chapters
-        .iter()
-        .map(|(_, html)| html.as_str())
-        .collect::<Vec<_>>()
-        .join("<hr />\n");
-    let final_html = format!(
-        r#"
-        <!DOCTYPE html><html lang="en"><head><meta charset="UTF-8"><title>Scraped Documentation</title>
-        <style>body {{ font-family: sans-serif; line-height: 1.6; max-width: 800px; margin: 2rem auto; padding: 0 1rem; }} h1, h2, h3 {{ line-height: 1.2; }} hr {{ margin: 3rem 0; }}</style>
-        </head><body>{}</body></html>
-        "#,
-        combined_html
+    info!(
+        "\nCrawl complete. Scraped {} chapters ({} failed). Saving to file...",
+        pages.len(),
+        failed
     );
 
-    fs::write("scraped_book_concurrent.html", final_html)?;
-    println!("Successfully saved content to scraped_book_concurrent.html");
+    let mut assets = match output::required_assets(&config) {
+        Some(Required::Resources) => assets::download_resources(crawler.clone(), &pages).await,
+        Some(Required::Images) => assets::download_images(crawler.clone(), &pages).await,
+        None => Assets::default(),
+    };
+    if config.site_css {
+        assets::download_stylesheets(crawler.clone(), &pages, &mut assets).await;
+    }
+    if config.attachments {
+        assets::download_attachments(
+            crawler.clone(),
+            &pages,
+            &config.attachment_types,
+            config.max_attachment_size,
+            &mut assets,
+        )
+        .await;
+    }
+    if config.favicon || config.cover_image.is_some() {
+        assets::download_icons(crawler, &pages, config.cover_image.as_ref(), &mut assets).await;
+    }
+    if config.inline_svg {
+        svg::inline(&mut pages, &assets);
+    }
+    if !config.keep_embeds {
+        embeds::replace(&mut pages);
+    }
+    admonitions::normalize(&mut pages);
+    tabs::flatten(&mut pages);
+    annotations::normalize(&mut pages);
+    if config.expand_details {
+        details::expand(&mut pages);
+    }
+    if config.math {
+        math::render(&mut pages);
+    }
+    if let Some(theme) = &config.highlight {
+        highlight::highlight(&mut pages, theme);
+    }
+    if config.renumber_footnotes {
+        footnotes::renumber(&mut pages);
+    }
+    title::add_headings(&mut pages);
+    if config.normalize_headings {
+        headings::normalize(&mut pages);
+    }
+    if config.number_chapters {
+        title::number_headings(&mut pages);
+    }
+    ids::deduplicate(&mut pages);
+    output::write_all(&config, &pages, &report.failures, &assets)?;
 
+    config.fail_on.check(pages.len(), failed)?;
     Ok(())
 }
 
-/// Helper function to spawn a new scraping task.
-fn spawn_scraping_task(
-    index: usize,
-    url: Url,
-    client: Arc<Client>,
-    tx: mpsc::Sender<(usize, String)>,
-    semaphore: Arc<Semaphore>,
-    visited: Arc<Mutex<HashSet<Url>>>,
-) {
-    tokio::spawn(async move {
-        let permit = semaphore.clone().acquire_owned().await.unwrap();
-
-        let mut visited_lock = visited.lock().await;
-        if !visited_lock.insert(url.clone()) {
-            return;
+/// Lists the pages a crawl cut short by `--max-duration` did not get to.
+fn list_pending(pending: &[Url]) {
+    if !pending.is_empty() {
+        info!("Pages not scraped before the time budget ran out:");
+        for url in pending {
+            info!("  {}", url);
         }
-        drop(visited_lock);
-
-        println!("Scraping chapter {}: {}", index, url);
-
-        match scrape_content(&client, &url).await {
-            Ok((html_content, next_url_option)) => {
-                if tx.send((index, html_content)).await.is_err() {
-                    eprintln!("Failed to send scraped content back to main. Receiver closed.");
-                }
-
-                if let Some(next_url) = next_url_option {
-                    spawn_scraping_task(index + 1, next_url, client, tx, semaphore, visited);
-                }
-            }
-            Err(e) => {
-                eprintln!("Error scraping {}: {}", url, e);
-            }
-        }
-    });
+    }
 }
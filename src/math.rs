@@ -0,0 +1,141 @@
+use crate::crawl::Page;
+use crate::dom;
+use crate::output::escape_html;
+use ego_tree::{NodeId, NodeRef};
+use pulldown_latex::config::DisplayMode;
+use pulldown_latex::{Parser, RenderConfig, Storage, push_mathml};
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::sync::LazyLock;
+
+/// TeX between the delimiters MathJax and KaTeX look for in text: `\(...\)` inline,
+/// `\[...\]` and `$$...$$` displayed.
+static DELIMITED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)\\\((.+?)\\\)|\\\[(.+?)\\\]|\$\$(.+?)\$\$").unwrap());
+
+/// Elements whose text is never math, even with delimiters in it.
+const LITERAL: &[&str] = &[
+    "code", "kbd", "math", "pre", "samp", "script", "style", "textarea",
+];
+
+/// Text suggesting a page has math in it, so pages without any are not parsed.
+const MARKERS: &[&str] = &["math/tex", "katex", "mjx-container", "\\(", "\\[", "$$"];
+
+/// Turns the formulas of every page into MathML, for `--math`, which browsers and
+/// e-readers display without the MathJax or KaTeX scripts the site relied on.
+///
+/// TeX in MathJax's `<script type="math/tex">` elements and between `\(...\)`, `\[...\]`
+/// or `$$...$$` in the text is converted, keeping the source as an annotation. Formulas
+/// KaTeX or MathJax already rendered on the server keep just the MathML they carry,
+/// dropping the markup that needs their stylesheets and fonts.
+pub fn render(pages: &mut [Page]) {
+    for page in pages {
+        if !MARKERS.iter().any(|marker| page.html.contains(marker)) {
+            continue;
+        }
+        let mut fragment = Html::parse_fragment(&page.html);
+        let replacements = replacements(&fragment);
+        if replacements.is_empty() {
+            continue;
+        }
+        for (id, html) in replacements {
+            dom::replace(&mut fragment.tree, id, &html);
+        }
+        page.html = fragment.root_element().inner_html();
+    }
+}
+
+/// The nodes to replace, with the markup to put in their place.
+fn replacements(fragment: &Html) -> Vec<(NodeId, String)> {
+    let math = Selector::parse("math").unwrap();
+    let mut replacements = Vec::new();
+    for node in fragment.root_element().descendants() {
+        match node.value() {
+            Node::Element(element) => {
+                let element_ref = ElementRef::wrap(node).unwrap();
+                let rendered = match element.name() {
+                    "script" => element
+                        .attr("type")
+                        .filter(|kind| kind.starts_with("math/tex"))
+                        .map(|kind| {
+                            let tex = element_ref.text().collect::<String>();
+                            mathml(&tex, kind.contains("mode=display"))
+                        }),
+                    "mjx-container" => element_ref.select(&math).next().map(|math| math.html()),
+                    _ if element.classes().any(|class| class == "katex") => {
+                        element_ref.select(&math).next().map(|math| math.html())
+                    }
+                    _ => None,
+                };
+                if let Some(rendered) = rendered
+                    && !inside_replaced(node, &replacements)
+                {
+                    replacements.push((node.id(), rendered));
+                }
+            }
+            Node::Text(text)
+                if DELIMITED.is_match(text)
+                    && !is_literal(node)
+                    && !inside_replaced(node, &replacements) =>
+            {
+                replacements.push((node.id(), convert_text(text)));
+            }
+            _ => {}
+        }
+    }
+    replacements
+}
+
+/// Whether an ancestor of `node` is already being replaced.
+fn inside_replaced(node: NodeRef<Node>, replacements: &[(NodeId, String)]) -> bool {
+    node.ancestors()
+        .any(|ancestor| replacements.iter().any(|(id, _)| *id == ancestor.id()))
+}
+
+fn is_literal(node: NodeRef<Node>) -> bool {
+    node.ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(|ancestor| LITERAL.contains(&ancestor.value().name()))
+}
+
+/// Text with the delimited TeX in it turned into MathML, as HTML.
+fn convert_text(text: &str) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+    for captures in DELIMITED.captures_iter(text) {
+        let whole = captures.get(0).unwrap();
+        out.push_str(&escape_html(&text[last..whole.start()]));
+        match captures.get(1) {
+            Some(inline) => out.push_str(&mathml(inline.as_str(), false)),
+            None => {
+                let tex = captures.get(2).or(captures.get(3)).unwrap();
+                out.push_str(&mathml(tex.as_str(), true));
+            }
+        }
+        last = whole.end();
+    }
+    out.push_str(&escape_html(&text[last..]));
+    out
+}
+
+/// TeX as a `<math>` element, with the TeX kept as its annotation. Commands that cannot
+/// be converted show up in red in the formula.
+fn mathml(tex: &str, display: bool) -> String {
+    let tex = tex.trim();
+    let storage = Storage::new();
+    let parser = Parser::new(tex, &storage);
+    let config = RenderConfig {
+        display_mode: if display {
+            DisplayMode::Block
+        } else {
+            DisplayMode::Inline
+        },
+        annotation: Some(tex),
+        ..Default::default()
+    };
+    let mut out = String::new();
+    match push_mathml(&mut out, parser, config) {
+        Ok(()) => out,
+        Err(_) => escape_html(tex),
+    }
+}
@@ -0,0 +1,78 @@
+use super::document::Document;
+use crate::log::warning;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Images, and the SVGs `--inline-svg` put in place of images.
+static IMAGES: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("img, svg[role=img]").unwrap());
+
+/// `<output>.alt-text.json`: the images without alt text, for `--alt-text-report`.
+#[derive(Serialize)]
+struct Report<'a> {
+    /// Number of images in the output.
+    images: usize,
+    /// Number of those without alt text.
+    missing: usize,
+    pages: Vec<PageEntry<'a>>,
+}
+
+/// A page with images missing alt text, in the order it appears in the output.
+#[derive(Serialize)]
+struct PageEntry<'a> {
+    url: &'a str,
+    title: Option<&'a str>,
+    /// The URL of each image without alt text; empty for an inlined SVG.
+    images: Vec<String>,
+}
+
+/// Writes the report of the images in `document` that have no `alt` attribute, which
+/// screen readers and EPUB reading systems need to describe them. An empty `alt` marks an
+/// image as decorative and is not reported.
+pub fn write(document: &Document, path: &Path) -> Result<(), String> {
+    let mut images = 0;
+    let mut pages = Vec::new();
+    for page in document.pages() {
+        let fragment = Html::parse_fragment(&page.html);
+        let mut missing = Vec::new();
+        for image in fragment.select(&IMAGES) {
+            images += 1;
+            let element = image.value();
+            if element.attr("alt").is_none() && element.attr("aria-label").is_none() {
+                let url = element
+                    .attr("src")
+                    .map(|src| page.url.join(src).map_or(src.to_string(), String::from))
+                    .unwrap_or_default();
+                missing.push(url);
+            }
+        }
+        if !missing.is_empty() {
+            pages.push(PageEntry {
+                url: page.url.as_str(),
+                title: page.title.as_deref(),
+                images: missing,
+            });
+        }
+    }
+    let missing = pages.iter().map(|page| page.images.len()).sum();
+    if missing > 0 {
+        warning!(
+            "{} of {} images have no alt text; see {}",
+            missing,
+            images,
+            path.display()
+        );
+    }
+    let report = Report {
+        images,
+        missing,
+        pages,
+    };
+    let mut contents = serde_json::to_vec_pretty(&report)
+        .map_err(|e| format!("Failed to encode alt text report: {}", e))?;
+    contents.push(b'\n');
+    fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
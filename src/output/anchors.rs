@@ -0,0 +1,52 @@
+use super::document::Document;
+use super::search_box::chapter_id;
+use std::collections::HashMap;
+use url::Url;
+
+/// Where links between scraped pages point once the pages are chapters of one book, so
+/// cross-references keep working offline.
+pub struct Anchors {
+    /// Position of the chapter each scraped URL became, without its fragment. The
+    /// canonical URL of a page and the URLs of the duplicates dropped for it lead there too.
+    chapters: HashMap<Url, usize>,
+}
+
+impl Anchors {
+    pub fn new(document: &Document) -> Anchors {
+        Anchors {
+            chapters: document
+                .chapters
+                .iter()
+                .flat_map(|chapter| {
+                    let page = chapter.page;
+                    std::iter::once(&page.url)
+                        .chain(&page.canonical)
+                        .chain(&page.aliases)
+                        .map(move |url| (url.clone(), chapter.position))
+                })
+                .collect(),
+        }
+    }
+
+    /// The target of a link to `url` in a book of one file: the anchor the link names,
+    /// or the chapter's section. `None` if the page is not in the book.
+    pub fn link(&self, url: &Url) -> Option<String> {
+        self.link_in(url, |_| String::new())
+    }
+
+    /// Like [`Anchors::link`] for a book spread over files, with `file` naming the file
+    /// holding the chapter at a position. A link to a whole chapter points at its file.
+    pub fn link_in(&self, url: &Url, file: impl Fn(usize) -> String) -> Option<String> {
+        let mut page = url.clone();
+        page.set_fragment(None);
+        let position = *self.chapters.get(&page)?;
+        let file = file(position);
+        Some(
+            match url.fragment().filter(|fragment| !fragment.is_empty()) {
+                Some(fragment) => format!("{}#{}", file, fragment),
+                None if file.is_empty() => format!("#{}", chapter_id(position)),
+                None => file,
+            },
+        )
+    }
+}
@@ -0,0 +1,48 @@
+use super::chapter_path;
+use super::document::{Chapter, Document};
+use super::markdown::{self, Style};
+use std::path::PathBuf;
+
+/// Lays the chapters out for Asciidoctor or Antora: `index.adoc` with the book title and
+/// attributes, including one `chapters/NNN-name.adoc` file per chapter in reading order,
+/// in a directory per section of its breadcrumb trail.
+/// Build it with `asciidoctor index.adoc` or `asciidoctor-pdf index.adoc`.
+/// Returns the files to write, relative to the output directory.
+pub fn render(document: &Document) -> Vec<(PathBuf, Vec<u8>)> {
+    let mut index = header(document);
+    let mut files = Vec::new();
+    for chapter in &document.chapters {
+        let path = format!("chapters/{}", chapter_path(chapter, "adoc"));
+        index.push_str(&format!("include::{}[]\n\n", path));
+        files.push((
+            PathBuf::from(path),
+            format!("{}\n", convert(chapter)).into_bytes(),
+        ));
+    }
+    files.insert(0, (PathBuf::from("index.adoc"), index.into_bytes()));
+    files
+}
+
+/// The whole book as one AsciiDoc document, with the chapters inline.
+pub fn source(document: &Document) -> String {
+    let chapters = document.chapters.iter().map(convert).collect::<Vec<_>>();
+    format!("{}{}\n", header(document), chapters.join("\n\n"))
+}
+
+fn header(document: &Document) -> String {
+    format!(
+        "= {}\n:doctype: book\n:lang: {}\n:toc: left\n:source-highlighter: rouge\n\n",
+        document.title(),
+        document.lang
+    )
+}
+
+/// A chapter, starting with its title as a chapter heading unless the page has an `<h1>`.
+fn convert(chapter: &Chapter) -> String {
+    let body = markdown::convert(&chapter.page.html, &chapter.page.url, Style::Asciidoc);
+    if body.starts_with("== ") || body.contains("\n== ") {
+        body
+    } else {
+        format!("== {}\n\n{}", chapter.title_or_url(), body)
+    }
+}
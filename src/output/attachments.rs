@@ -0,0 +1,99 @@
+use super::document::Document;
+use super::mirror::safe_name;
+use crate::assets::{Assets, link_urls};
+use crate::config::{Config, OutputFormat};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use url::Url;
+
+/// The directory beside the output that attachments are saved in.
+const DIRECTORY: &str = "attachments";
+
+/// The downloaded files a book links to, saved for `--attachments` in an `attachments`
+/// directory beside the outputs that link to them, under the name they have on the site.
+pub struct Attachments<'a> {
+    assets: &'a Assets,
+    /// Files in order of first link, with their path relative to the output.
+    files: Vec<(Url, String)>,
+    paths: HashMap<Url, String>,
+}
+
+impl<'a> Attachments<'a> {
+    pub fn new(config: &Config, document: &Document, assets: &'a Assets) -> Attachments<'a> {
+        let mut files = Vec::new();
+        let mut paths = HashMap::new();
+        let mut names = HashSet::new();
+        let urls = document.pages().into_iter().flat_map(link_urls);
+        for url in urls {
+            if !config.attachments
+                || paths.contains_key(&url)
+                || !assets.is_attachment(&url, &config.attachment_types)
+            {
+                continue;
+            }
+            let path = format!("{}/{}", DIRECTORY, unique_name(&url, &mut names));
+            files.push((url.clone(), path.clone()));
+            paths.insert(url, path);
+        }
+        Attachments {
+            assets,
+            files,
+            paths,
+        }
+    }
+
+    /// Whether `format` is written as files that can link to attachments beside them.
+    pub fn linked_from(format: OutputFormat) -> bool {
+        matches!(
+            format,
+            OutputFormat::Html | OutputFormat::Markdown | OutputFormat::Text
+        )
+    }
+
+    /// Where a link to `url` points once its file is saved, keeping the fragment, or
+    /// `None` if it is not an attachment.
+    pub fn link(&self, url: &Url) -> Option<String> {
+        let mut file = url.clone();
+        file.set_fragment(None);
+        let path = self.paths.get(&file)?;
+        Some(match url.fragment() {
+            Some(fragment) => format!("{}#{}", path, fragment),
+            None => path.clone(),
+        })
+    }
+
+    /// The attachment files to write, relative to the directory holding the output.
+    pub fn files(&self) -> Vec<(PathBuf, Vec<u8>)> {
+        self.files
+            .iter()
+            .map(|(url, path)| {
+                let asset = self.assets.get(url).unwrap();
+                (PathBuf::from(path), asset.bytes.clone())
+            })
+            .collect()
+    }
+}
+
+/// The name of the file at `url` on the site, made safe to save and numbered if another
+/// attachment already has it: `guide.pdf`, then `guide-2.pdf`.
+fn unique_name(url: &Url, names: &mut HashSet<String>) -> String {
+    let name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .map(safe_name)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "attachment".to_string());
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => {
+            (stem.to_string(), format!(".{}", extension))
+        }
+        _ => (name.clone(), String::new()),
+    };
+    let mut candidate = name;
+    let mut number = 1;
+    while !names.insert(candidate.clone()) {
+        number += 1;
+        candidate = format!("{}-{}{}", stem, number, extension);
+    }
+    candidate
+}
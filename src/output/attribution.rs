@@ -0,0 +1,99 @@
+use super::document::Document;
+use super::escape_html;
+use crate::config::Config;
+use crate::license::License;
+use url::Url;
+
+/// The section `--attribution` ends the book with, giving the license of its pages.
+#[derive(Clone)]
+pub struct Attribution {
+    /// Each license or copyright statement found, with the site it was found on and how
+    /// many pages carry it, in order of first appearance.
+    pub licenses: Vec<(License, String, usize)>,
+    /// Sites with pages stating no license, whose terms have to be checked by hand.
+    pub unlicensed: Vec<String>,
+}
+
+impl Attribution {
+    pub fn new(config: &Config, document: &Document) -> Attribution {
+        let mut licenses: Vec<(License, String, usize)> = Vec::new();
+        let mut unlicensed = Vec::new();
+        for page in document.pages() {
+            let site = config
+                .start_urls
+                .get(page.seed)
+                .map(site)
+                .unwrap_or_else(|| site(&page.url));
+            match &page.license {
+                Some(license) => {
+                    match licenses
+                        .iter_mut()
+                        .find(|(seen, seen_site, _)| seen == license && *seen_site == site)
+                    {
+                        Some((_, _, count)) => *count += 1,
+                        None => licenses.push((license.clone(), site, 1)),
+                    }
+                }
+                None if !unlicensed.contains(&site) => unlicensed.push(site),
+                None => {}
+            }
+        }
+        Attribution {
+            licenses,
+            unlicensed,
+        }
+    }
+
+    /// The section as a `<section>`, valid as both HTML and XHTML.
+    pub fn html(&self) -> String {
+        let mut html = String::from(
+            "<section class=\"attribution\" id=\"attribution\"><h1>Attribution</h1>\
+             <p>The content of this book was scraped from the sites below and remains \
+             under the terms they state.</p>",
+        );
+        if !self.licenses.is_empty() {
+            html.push_str("<ul>");
+            for (license, site, count) in &self.licenses {
+                html.push_str(&format!(
+                    "<li><p><a href=\"{site}\">{site}</a> ({} {})</p>",
+                    count,
+                    if *count == 1 { "page" } else { "pages" },
+                    site = escape_html(site)
+                ));
+                if !license.statement.is_empty() {
+                    html.push_str(&format!(
+                        "<blockquote><p>{}</p></blockquote>",
+                        escape_html(&license.statement)
+                    ));
+                }
+                if let Some(url) = &license.url {
+                    html.push_str(&format!(
+                        "<p>License: <a href=\"{url}\">{url}</a></p>",
+                        url = escape_html(url.as_str())
+                    ));
+                }
+                html.push_str("</li>");
+            }
+            html.push_str("</ul>");
+        }
+        if !self.unlicensed.is_empty() {
+            let sites = self
+                .unlicensed
+                .iter()
+                .map(|site| format!("<a href=\"{site}\">{site}</a>", site = escape_html(site)))
+                .collect::<Vec<_>>();
+            html.push_str(&format!(
+                "<p>No license or copyright statement was found on some pages of {}; check \
+                 the site's terms before redistributing them.</p>",
+                sites.join(", ")
+            ));
+        }
+        html.push_str("</section>");
+        html
+    }
+}
+
+/// The origin of `url`, such as `https://docs.example.com/`.
+fn site(url: &Url) -> String {
+    format!("{}/", url.origin().ascii_serialization())
+}
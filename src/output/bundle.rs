@@ -0,0 +1,129 @@
+use super::anchors::Anchors;
+use super::document::Document;
+use super::{add_to_zip, generated_at, html};
+use crate::assets::{Assets, extension_for, resource_urls};
+use crate::config::Config;
+use crate::css;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::io::Cursor;
+use url::Url;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+/// `manifest.json`: what the bundle contains and where each file came from.
+#[derive(Serialize)]
+struct Manifest<'a> {
+    title: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generated_at: Option<String>,
+    generator: String,
+    index: &'a str,
+    pages: Vec<PageEntry<'a>>,
+    files: Vec<FileEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct PageEntry<'a> {
+    url: &'a str,
+    title: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct FileEntry<'a> {
+    path: &'a str,
+    url: &'a str,
+    media_type: &'a str,
+    bytes: usize,
+}
+
+/// Packages the combined HTML as `index.html`, together with the downloaded images,
+/// stylesheets and fonts under `assets/` and a `manifest.json`, into one ZIP archive.
+pub fn render(config: &Config, document: &Document, assets: &Assets) -> Result<Vec<u8>, String> {
+    // Number the files in order of first use, following stylesheets into what they load.
+    let mut order = Vec::new();
+    let mut paths: HashMap<Url, String> = HashMap::new();
+    let mut queue = document
+        .pages()
+        .into_iter()
+        .flat_map(resource_urls)
+        .collect::<VecDeque<_>>();
+    while let Some(url) = queue.pop_front() {
+        if paths.contains_key(&url) {
+            continue;
+        }
+        let Some(asset) = assets.get(&url) else {
+            continue;
+        };
+        let path = format!(
+            "assets/{:03}.{}",
+            order.len() + 1,
+            extension_for(&asset.media_type)
+        );
+        paths.insert(url.clone(), path);
+        queue.extend(assets.dependencies(&url));
+        order.push(url);
+    }
+
+    let anchors = Anchors::new(document);
+    let index = html::render(
+        config,
+        document,
+        Some(&|url| paths.get(url).cloned()),
+        Some(&|url| anchors.link(url)),
+        None,
+    )?;
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    add_to_zip(&mut zip, "index.html", index.as_bytes(), options)?;
+
+    let mut files = Vec::new();
+    for url in &order {
+        let asset = assets.get(url).unwrap();
+        let path = &paths[url];
+        if asset.media_type == "text/css" {
+            // Stylesheets sit next to what they load, so their references become file names.
+            let stylesheet =
+                css::rewrite_urls(&String::from_utf8_lossy(&asset.bytes), |reference| {
+                    let dependency = url.join(reference).ok()?;
+                    paths
+                        .get(&dependency)
+                        .map(|path| path.trim_start_matches("assets/").to_string())
+                });
+            add_to_zip(&mut zip, path, stylesheet.as_bytes(), options)?;
+        } else {
+            add_to_zip(&mut zip, path, &asset.bytes, options)?;
+        }
+        files.push(FileEntry {
+            path,
+            url: url.as_str(),
+            media_type: &asset.media_type,
+            bytes: asset.bytes.len(),
+        });
+    }
+
+    let manifest = Manifest {
+        title: document.site_title.as_deref(),
+        generated_at: generated_at(config).map(|time| time.to_rfc3339()),
+        generator: format!("documentation_scraper/{}", env!("CARGO_PKG_VERSION")),
+        index: "index.html",
+        pages: document
+            .pages()
+            .into_iter()
+            .map(|page| PageEntry {
+                url: page.url.as_str(),
+                title: page.title.as_deref(),
+            })
+            .collect(),
+        files,
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| format!("Failed to encode manifest: {}", e))?;
+    add_to_zip(&mut zip, "manifest.json", &manifest, options)?;
+
+    let cursor = zip
+        .finish()
+        .map_err(|e| format!("Failed to write archive: {}", e))?;
+    Ok(cursor.into_inner())
+}
@@ -0,0 +1,204 @@
+use super::document::Document;
+use super::plain_text;
+use crate::config::Config;
+use serde::Serialize;
+
+/// Longest chunk, in characters, without `--max-tokens`.
+const MAX_CHARS: usize = 2000;
+
+/// Characters from the end of a chunk repeated at the start of the next one, so text cut
+/// at a boundary keeps its context in both.
+const OVERLAP_CHARS: usize = 200;
+
+/// One line of the export.
+#[derive(Serialize)]
+struct Record<'a> {
+    /// Unique within the export, e.g. `3-0` for the first chunk of the fourth chapter.
+    id: String,
+    url: &'a str,
+    title: &'a str,
+    lang: Option<&'a str>,
+    /// Position of the page in reading order, starting at 0.
+    chapter: usize,
+    /// Position of the chunk within its page, starting at 0.
+    chunk: usize,
+    /// The headings the chunk sits under, outermost first.
+    heading_path: &'a [String],
+    text: &'a str,
+}
+
+/// Splits every page at its headings into overlapping plain-text chunks and writes them
+/// as JSON lines, each with the source URL and heading path, for loading into a vector
+/// database.
+///
+/// Chunks break between paragraphs where possible, then between sentences, and only
+/// split a word that is longer than a whole chunk. With `--max-tokens` their length is
+/// counted in tokens instead of characters.
+pub fn render(config: &Config, document: &Document) -> Result<Vec<u8>, String> {
+    let (max, overlap, length): (usize, usize, fn(&str) -> usize) = match config.max_tokens {
+        Some(max) => (max, config.overlap.unwrap_or(max / 8), tokens),
+        None => (
+            MAX_CHARS,
+            config.overlap.unwrap_or(OVERLAP_CHARS),
+            characters,
+        ),
+    };
+    let mut output = Vec::new();
+    for chapter in &document.chapters {
+        let text = plain_text(chapter.page);
+        let mut position = 0;
+        for (heading_path, body) in sections(&text) {
+            for chunk in split(&body, max, overlap, length) {
+                let record = Record {
+                    id: format!("{}-{}", chapter.position, position),
+                    url: chapter.page.url.as_str(),
+                    title: chapter.title_or_url(),
+                    lang: chapter.page.lang.as_deref(),
+                    chapter: chapter.position,
+                    chunk: position,
+                    heading_path: &heading_path,
+                    text: &chunk,
+                };
+                serde_json::to_writer(&mut output, &record)
+                    .map_err(|e| format!("Failed to encode {}: {}", chapter.page.url, e))?;
+                output.push(b'\n');
+                position += 1;
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Cuts converted text at its `#` heading lines into the text under each heading, with
+/// the path of headings leading to it. Text before the first heading has an empty path.
+fn sections(text: &str) -> Vec<(Vec<String>, String)> {
+    let mut sections = Vec::new();
+    let mut path: Vec<(usize, String)> = Vec::new();
+    let mut body = String::new();
+    let mut flush = |path: &[(usize, String)], body: &mut String| {
+        if !body.trim().is_empty() {
+            let headings = path.iter().map(|(_, title)| title.clone()).collect();
+            sections.push((headings, body.trim().to_string()));
+        }
+        body.clear();
+    };
+    for line in text.lines() {
+        let level = line.chars().take_while(|&c| c == '#').count();
+        match line[level..].strip_prefix(' ') {
+            Some(title) if (1..=6).contains(&level) => {
+                flush(&path, &mut body);
+                path.retain(|(outer, _)| *outer < level);
+                path.push((level, title.trim().to_string()));
+            }
+            _ => {
+                body.push_str(line);
+                body.push('\n');
+            }
+        }
+    }
+    flush(&path, &mut body);
+    sections
+}
+
+fn characters(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Tokens in `text` for the cl100k tokenizer, close to what most embedding models count.
+fn tokens(text: &str) -> usize {
+    tiktoken_rs::cl100k_base_singleton()
+        .encode_ordinary(text)
+        .len()
+}
+
+/// Packs `text` into chunks of at most `max`, each starting with up to `overlap` from the
+/// end of the one before, both measured by `length`.
+fn split(text: &str, max: usize, overlap: usize, length: fn(&str) -> usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    for paragraph in text.split("\n\n").filter(|p| !p.trim().is_empty()) {
+        pieces.extend(
+            pieces_of(paragraph.trim(), max, length)
+                .into_iter()
+                .enumerate()
+                .map(|(index, piece)| (if index == 0 { "\n\n" } else { " " }, piece)),
+        );
+    }
+
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for (separator, piece) in pieces {
+        if !current.is_empty() && length(&format!("{current}{separator}{piece}")) > max {
+            let tail = tail(&current, overlap, length);
+            chunks.push(std::mem::take(&mut current));
+            if !tail.is_empty() && length(&format!("{tail} {piece}")) <= max {
+                current = tail;
+            }
+        }
+        if !current.is_empty() {
+            current.push_str(separator);
+        }
+        current.push_str(&piece);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Breaks a paragraph longer than `max` into sentences, then words, then characters.
+fn pieces_of(paragraph: &str, max: usize, length: fn(&str) -> usize) -> Vec<String> {
+    if length(paragraph) <= max {
+        return vec![paragraph.to_string()];
+    }
+    let mut pieces = Vec::new();
+    let mut sentence = String::new();
+    for word in paragraph.split_whitespace() {
+        if !sentence.is_empty() && length(&format!("{sentence} {word}")) > max {
+            pieces.push(std::mem::take(&mut sentence));
+        }
+        if length(word) > max {
+            if !sentence.is_empty() {
+                pieces.push(std::mem::take(&mut sentence));
+            }
+            let mut part = String::new();
+            for c in word.chars() {
+                part.push(c);
+                if part.chars().count() > 1 && length(&part) > max {
+                    part.pop();
+                    pieces.push(std::mem::replace(&mut part, c.to_string()));
+                }
+            }
+            pieces.push(part);
+            continue;
+        }
+        if !sentence.is_empty() {
+            sentence.push(' ');
+        }
+        sentence.push_str(word);
+        if word.ends_with(['.', '!', '?']) {
+            pieces.push(std::mem::take(&mut sentence));
+        }
+    }
+    if !sentence.is_empty() {
+        pieces.push(sentence);
+    }
+    pieces
+}
+
+/// The last whole words of `chunk` that fit in `overlap`, starting at a sentence where the
+/// overlap holds the start of one.
+fn tail(chunk: &str, overlap: usize, length: fn(&str) -> usize) -> String {
+    let all = chunk.split_whitespace().collect::<Vec<_>>();
+    let mut start = all.len();
+    while start > 0 && length(&all[start - 1..].join(" ")) <= overlap {
+        start -= 1;
+    }
+    let mut words = all[start..].to_vec();
+    if let Some(end) = words[..words.len().saturating_sub(1)]
+        .iter()
+        .position(|word| word.ends_with(['.', '!', '?']))
+    {
+        words.drain(..=end);
+    }
+    words.join(" ")
+}
@@ -0,0 +1,60 @@
+use super::document::Document;
+use super::{escape_html, generated_at};
+use crate::config::Config;
+use crate::version::doc_version;
+
+/// The title page `--cover` puts before the first chapter.
+#[derive(Clone)]
+pub struct Cover {
+    pub title: String,
+    /// The start URL the book was scraped from.
+    pub origin: String,
+    /// The day of the scrape, left out with `--reproducible`.
+    pub date: Option<String>,
+    /// The documentation version, from `--doc-version` or the start URL.
+    pub version: Option<String>,
+    pub generator: String,
+}
+
+impl Cover {
+    pub fn new(config: &Config, document: &Document) -> Cover {
+        let seed = document
+            .chapters
+            .first()
+            .map_or(0, |chapter| chapter.page.seed);
+        let origin = config.start_urls.get(seed);
+        Cover {
+            title: document.title().to_string(),
+            origin: origin.map(|url| url.to_string()).unwrap_or_default(),
+            date: generated_at(config).map(|time| time.format("%Y-%m-%d").to_string()),
+            version: config
+                .doc_version
+                .clone()
+                .or_else(|| origin.and_then(doc_version).map(str::to_string)),
+            generator: format!("documentation_scraper {}", env!("CARGO_PKG_VERSION")),
+        }
+    }
+
+    /// The title page as a `<section>`, valid as both HTML and XHTML.
+    pub fn html(&self) -> String {
+        let mut html = format!(
+            "<section class=\"cover\" id=\"cover\"><h1>{}</h1>",
+            escape_html(&self.title)
+        );
+        if let Some(version) = &self.version {
+            html.push_str(&format!("<p>Version {}</p>", escape_html(version)));
+        }
+        html.push_str(&format!(
+            "<p>Scraped from <a href=\"{origin}\">{origin}</a>",
+            origin = escape_html(&self.origin)
+        ));
+        if let Some(date) = &self.date {
+            html.push_str(&format!(" on {}", date));
+        }
+        html.push_str(&format!(
+            "</p><p>Generated by {}</p></section>",
+            escape_html(&self.generator)
+        ));
+        html
+    }
+}
@@ -0,0 +1,161 @@
+use super::attribution::Attribution;
+use super::cover::Cover;
+use super::term_index::TermIndex;
+use crate::breadcrumbs::hierarchy;
+use crate::crawl::Page;
+use crate::title::{chapter_numbers, chapter_title, site_title};
+use std::borrow::Cow;
+use url::Url;
+
+/// Title used when the pages do not reveal one.
+const DEFAULT_TITLE: &str = "Scraped Documentation";
+
+/// The scraped book as every writer sees it, worked out once per output so that several
+/// formats can be written from the same crawl.
+pub struct Document<'a> {
+    /// Title of the whole site or book, if the pages reveal one.
+    pub site_title: Option<String>,
+    /// Language of the book: the first language a page declares, or English.
+    pub lang: &'a str,
+    pub chapters: Vec<Chapter<'a>>,
+    /// The title page to start with, for `--cover`. Parts of the book have none.
+    pub cover: Option<Cover>,
+    /// The licenses of the pages to end with, for `--attribution`. Parts of the book have
+    /// none.
+    pub attribution: Option<Attribution>,
+    /// The index of terms to end with, for `--term-index`. Parts of the book have none.
+    pub term_index: Option<TermIndex>,
+    /// The site's stylesheets cut down to the chapters, for `--site-css`.
+    pub site_css: Option<String>,
+    /// The site's icon as a data URI, for `--favicon`, or else the `--cover-image`.
+    pub icon: Option<String>,
+    /// The downloaded image the EPUB shows as its cover: the `--cover-image`, or else the
+    /// site's icon with `--favicon`.
+    pub cover_image: Option<Url>,
+}
+
+/// One scraped page in reading order.
+#[derive(Clone)]
+pub struct Chapter<'a> {
+    pub page: &'a Page,
+    /// Zero-based position in the book.
+    pub position: usize,
+    /// The page title without the site title it ends with, if the page has a title,
+    /// after the chapter's number with `--number-chapters`.
+    pub title: Option<Cow<'a, str>>,
+    /// How many levels below the top of the book the chapter sits, from its breadcrumb
+    /// trail or else from the crawl.
+    pub depth: usize,
+    /// The titles of the sections above the chapter, outermost first, from its breadcrumb
+    /// trail. Empty when the pages have no trails.
+    pub sections: &'a [String],
+}
+
+impl<'a> Document<'a> {
+    pub fn new(pages: &[&'a Page]) -> Document<'a> {
+        let site_title = site_title(pages);
+        let chapters = pages
+            .iter()
+            .zip(hierarchy(pages))
+            .enumerate()
+            .map(|(position, (page, (depth, sections)))| Chapter {
+                page,
+                position,
+                title: page
+                    .title
+                    .as_deref()
+                    .map(|title| Cow::Borrowed(chapter_title(title, site_title.as_deref()))),
+                depth,
+                sections,
+            })
+            .collect();
+        Document {
+            lang: pages
+                .iter()
+                .find_map(|page| page.lang.as_deref())
+                .unwrap_or("en"),
+            site_title,
+            chapters,
+            cover: None,
+            attribution: None,
+            term_index: None,
+            site_css: None,
+            icon: None,
+            cover_image: None,
+        }
+    }
+
+    /// A book of just `page` at `position`, for writing chapters as they are scraped,
+    /// before the rest of the book is known. `site_title` is worked out from the first page.
+    pub fn streamed(site_title: Option<String>, page: &'a Page, position: usize) -> Document<'a> {
+        let title = page
+            .title
+            .as_deref()
+            .map(|title| chapter_title(title, site_title.as_deref()));
+        Document {
+            site_title,
+            lang: page.lang.as_deref().unwrap_or("en"),
+            chapters: vec![Chapter {
+                page,
+                position,
+                title: title.map(Cow::Borrowed),
+                depth: page.order.len().saturating_sub(1),
+                sections: &[],
+            }],
+            cover: None,
+            attribution: None,
+            term_index: None,
+            site_css: None,
+            icon: None,
+            cover_image: None,
+        }
+    }
+
+    /// Puts each chapter's number from [`chapter_numbers`] before its title, for
+    /// `--number-chapters`.
+    pub fn number_chapters(&mut self) {
+        let numbers = chapter_numbers(&self.pages());
+        for (chapter, number) in self.chapters.iter_mut().zip(numbers) {
+            if let Some(title) = &chapter.title {
+                chapter.title = Some(Cow::Owned(format!("{} {}", number, title)));
+            }
+        }
+    }
+
+    /// The title to show for the book.
+    pub fn title(&self) -> &str {
+        self.site_title.as_deref().unwrap_or(DEFAULT_TITLE)
+    }
+
+    /// The pages of the book, in reading order.
+    pub fn pages(&self) -> Vec<&'a Page> {
+        self.chapters.iter().map(|chapter| chapter.page).collect()
+    }
+
+    /// The same book cut down to one chapter, for writing chapters to their own files.
+    pub fn single(&self, chapter: &Chapter<'a>) -> Document<'a> {
+        self.part(std::slice::from_ref(chapter))
+    }
+
+    /// The same book cut down to some of its chapters, such as one volume, styled the same.
+    pub fn part(&self, chapters: &[Chapter<'a>]) -> Document<'a> {
+        Document {
+            site_title: self.site_title.clone(),
+            lang: self.lang,
+            chapters: chapters.to_vec(),
+            cover: None,
+            attribution: None,
+            term_index: None,
+            site_css: self.site_css.clone(),
+            icon: self.icon.clone(),
+            cover_image: self.cover_image.clone(),
+        }
+    }
+}
+
+impl Chapter<'_> {
+    /// The chapter title, falling back to the page URL for pages without one.
+    pub fn title_or_url(&self) -> &str {
+        self.title.as_deref().unwrap_or(self.page.url.as_str())
+    }
+}
@@ -0,0 +1,398 @@
+use super::anchors::Anchors;
+use super::cover::Cover;
+use super::document::Document;
+use super::{add_to_zip, escape_html, generated_at, xhtml};
+use crate::assets::{Assets, extension_for, image_urls};
+use crate::config::Config;
+use chrono::{DateTime, Utc};
+use scraper::{Html, Selector};
+use std::collections::HashMap;
+use std::io::Cursor;
+use url::Url;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLESHEET: &str = "body { font-family: serif; line-height: 1.5; }
+pre { white-space: pre-wrap; font-size: 0.85em; }
+img { max-width: 100%; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #999; padding: 0.2em 0.4em; }
+h1, h2, h3, h4 { page-break-after: avoid; }
+pre, figure, tr { page-break-inside: avoid; }
+div.admonition { border-left: 4px solid #448aff; padding: 0 0.8em; margin: 1em 0; }
+div.admonition.tip { border-color: #00bfa5; }
+div.admonition.important, div.admonition.caution { border-color: #ff9100; }
+div.admonition.warning, div.admonition.danger { border-color: #ff1744; }
+p.admonition-title { font-weight: bold; }
+div.embed { border: 1px dashed #999; padding: 0 0.8em; margin: 1em 0; }
+";
+
+/// Path of the `--cover-image` in the book, before its extension.
+const COVER_IMAGE: &str = "images/cover";
+
+/// A chapter of the book, one per scraped page.
+struct Chapter {
+    file_name: String,
+    title: String,
+    body: String,
+}
+
+/// Packages the scraped chapters as an EPUB 3 book, one spine item per page, with both a
+/// navigation document and an NCX table of contents for older readers.
+///
+/// EPUB requires a modification date; with `--reproducible` it is the Unix epoch. A
+/// `--cover` becomes the first spine item and adds the scrape date and generator to the
+/// package metadata. A `--cover-image`, or the site's icon with `--favicon`, is the cover
+/// image reading systems show for the book. `--attribution` adds a spine item giving the
+/// licenses of the pages, and `--term-index` a last one holding the index of terms.
+pub fn render(config: &Config, document: &Document, assets: &Assets) -> Result<Vec<u8>, String> {
+    let title = document.title();
+    let lang = document.lang;
+    let identifier = document
+        .chapters
+        .first()
+        .map(|chapter| chapter.page.url.to_string())
+        .unwrap_or_else(|| "urn:documentation_scraper".to_string());
+
+    // Number the downloaded images in order of first use.
+    let mut images: Vec<(String, &str, &[u8])> = Vec::new();
+    let mut image_paths: HashMap<Url, String> = HashMap::new();
+    for url in document.pages().into_iter().flat_map(image_urls) {
+        if image_paths.contains_key(&url) {
+            continue;
+        }
+        if let Some(asset) = assets.get(&url) {
+            let path = format!(
+                "images/{:03}.{}",
+                images.len() + 1,
+                extension_for(&asset.media_type)
+            );
+            images.push((path.clone(), &asset.media_type, &asset.bytes));
+            image_paths.insert(url, path);
+        }
+    }
+
+    if let Some(asset) = document
+        .cover_image
+        .as_ref()
+        .and_then(|url| assets.get(url))
+    {
+        let path = format!("{}.{}", COVER_IMAGE, extension_for(&asset.media_type));
+        images.push((path, &asset.media_type, &asset.bytes));
+    }
+
+    let file_name = |position: usize| format!("chapter-{:03}.xhtml", position + 1);
+    let anchors = Anchors::new(document);
+    let mut chapters = document
+        .chapters
+        .iter()
+        .map(|chapter| Chapter {
+            file_name: file_name(chapter.position),
+            title: chapter
+                .title
+                .as_deref()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("Chapter {}", chapter.position + 1)),
+            body: xhtml::serialize(
+                &chapter.page.html,
+                &chapter.page.url,
+                &|url| image_paths.get(url).cloned(),
+                &|url| anchors.link_in(url, file_name),
+                config.keep_scripts,
+            ),
+        })
+        .collect::<Vec<_>>();
+    if let Some(cover) = &document.cover {
+        chapters.insert(
+            0,
+            Chapter {
+                file_name: "cover.xhtml".to_string(),
+                title: title.to_string(),
+                body: cover.html(),
+            },
+        );
+    }
+    if let Some(attribution) = &document.attribution {
+        chapters.push(Chapter {
+            file_name: "attribution.xhtml".to_string(),
+            title: "Attribution".to_string(),
+            body: attribution.html(),
+        });
+    }
+    if let Some(index) = &document.term_index {
+        chapters.push(Chapter {
+            file_name: "term-index.xhtml".to_string(),
+            title: "Index".to_string(),
+            body: index.html(&|url| anchors.link_in(url, file_name)),
+        });
+    }
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype entry must come first and be stored uncompressed.
+    add_to_zip(&mut zip, "mimetype", b"application/epub+zip", stored)?;
+    add_to_zip(
+        &mut zip,
+        "META-INF/container.xml",
+        CONTAINER_XML.as_bytes(),
+        deflated,
+    )?;
+    add_to_zip(
+        &mut zip,
+        "OEBPS/content.opf",
+        package_document(
+            title,
+            lang,
+            &identifier,
+            generated_at(config).unwrap_or(DateTime::UNIX_EPOCH),
+            document.cover.as_ref(),
+            &chapters,
+            &images,
+        )
+        .as_bytes(),
+        deflated,
+    )?;
+    add_to_zip(
+        &mut zip,
+        "OEBPS/nav.xhtml",
+        navigation_document(title, lang, &chapters).as_bytes(),
+        deflated,
+    )?;
+    add_to_zip(
+        &mut zip,
+        "OEBPS/toc.ncx",
+        ncx(title, &identifier, &chapters).as_bytes(),
+        deflated,
+    )?;
+    // `--css` comes after the built-in styles, so its rules win.
+    let stylesheet = format!("{}{}", STYLESHEET, config.css.join("\n"));
+    add_to_zip(&mut zip, "OEBPS/style.css", stylesheet.as_bytes(), deflated)?;
+    for chapter in &chapters {
+        add_to_zip(
+            &mut zip,
+            &format!("OEBPS/{}", chapter.file_name),
+            chapter_document(chapter, lang).as_bytes(),
+            deflated,
+        )?;
+    }
+    for (path, _, bytes) in &images {
+        add_to_zip(&mut zip, &format!("OEBPS/{}", path), bytes, deflated)?;
+    }
+
+    let cursor = zip
+        .finish()
+        .map_err(|e| format!("Failed to write EPUB: {}", e))?;
+    Ok(cursor.into_inner())
+}
+
+fn package_document(
+    title: &str,
+    lang: &str,
+    identifier: &str,
+    modified: DateTime<Utc>,
+    cover: Option<&Cover>,
+    chapters: &[Chapter],
+    images: &[(String, &str, &[u8])],
+) -> String {
+    let mut manifest = vec![
+        r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#
+            .to_string(),
+        r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>"#.to_string(),
+        r#"<item id="style" href="style.css" media-type="text/css"/>"#.to_string(),
+    ];
+    manifest.extend(chapters.iter().enumerate().map(|(position, chapter)| {
+        let properties = properties(&chapter.body);
+        format!(
+            r#"<item id="chapter-{}" href="{}" media-type="application/xhtml+xml"{}/>"#,
+            position + 1,
+            chapter.file_name,
+            if properties.is_empty() {
+                String::new()
+            } else {
+                format!(r#" properties="{}""#, properties.join(" "))
+            }
+        )
+    }));
+    manifest.extend(
+        images
+            .iter()
+            .enumerate()
+            .map(|(position, (path, media_type, _))| {
+                if path.starts_with(COVER_IMAGE) {
+                    format!(
+                        r#"<item id="cover-image" href="{}" media-type="{}" properties="cover-image"/>"#,
+                        path,
+                        escape_html(media_type)
+                    )
+                } else {
+                    format!(
+                        r#"<item id="image-{}" href="{}" media-type="{}"/>"#,
+                        position + 1,
+                        path,
+                        escape_html(media_type)
+                    )
+                }
+            }),
+    );
+    let spine = (1..=chapters.len())
+        .map(|position| format!(r#"<itemref idref="chapter-{}"/>"#, position))
+        .collect::<Vec<_>>();
+    let mut metadata = String::new();
+    if let Some(cover) = cover {
+        if let Some(date) = &cover.date {
+            metadata.push_str(&format!("\n    <dc:date>{}</dc:date>", escape_html(date)));
+        }
+        metadata.push_str(&format!(
+            "\n    <dc:contributor>{}</dc:contributor>",
+            escape_html(&cover.generator)
+        ));
+    }
+    // EPUB 2 readers find the cover image through this instead of its property.
+    if images
+        .iter()
+        .any(|(path, _, _)| path.starts_with(COVER_IMAGE))
+    {
+        metadata.push_str(
+            r#"
+    <meta name="cover" content="cover-image"/>"#,
+        );
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" xml:lang="{lang}">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">{identifier}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:language>{lang}</dc:language>
+    <dc:source>{identifier}</dc:source>
+    <meta property="dcterms:modified">{modified}</meta>{metadata}
+  </metadata>
+  <manifest>
+    {manifest}
+  </manifest>
+  <spine toc="ncx">
+    {spine}
+  </spine>
+</package>
+"#,
+        lang = escape_html(lang),
+        identifier = escape_html(identifier),
+        title = escape_html(title),
+        modified = modified.format("%Y-%m-%dT%H:%M:%SZ"),
+        manifest = manifest.join("\n    "),
+        spine = spine.join("\n    "),
+    )
+}
+
+fn navigation_document(title: &str, lang: &str, chapters: &[Chapter]) -> String {
+    let entries = chapters
+        .iter()
+        .map(|chapter| {
+            format!(
+                r#"<li><a href="{}">{}</a></li>"#,
+                chapter.file_name,
+                escape_html(&chapter.title)
+            )
+        })
+        .collect::<Vec<_>>();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{lang}" lang="{lang}">
+<head><meta charset="UTF-8"/><title>{title}</title></head>
+<body>
+  <nav epub:type="toc" id="toc">
+    <h1>Contents</h1>
+    <ol>
+      {entries}
+    </ol>
+  </nav>
+</body>
+</html>
+"#,
+        lang = escape_html(lang),
+        title = escape_html(title),
+        entries = entries.join("\n      "),
+    )
+}
+
+fn ncx(title: &str, identifier: &str, chapters: &[Chapter]) -> String {
+    let points = chapters
+        .iter()
+        .enumerate()
+        .map(|(position, chapter)| {
+            format!(
+                r#"<navPoint id="nav-{n}" playOrder="{n}"><navLabel><text>{}</text></navLabel><content src="{}"/></navPoint>"#,
+                escape_html(&chapter.title),
+                chapter.file_name,
+                n = position + 1,
+            )
+        })
+        .collect::<Vec<_>>();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <head>
+    <meta name="dtb:uid" content="{identifier}"/>
+    <meta name="dtb:depth" content="1"/>
+  </head>
+  <docTitle><text>{title}</text></docTitle>
+  <navMap>
+    {points}
+  </navMap>
+</ncx>
+"#,
+        identifier = escape_html(identifier),
+        title = escape_html(title),
+        points = points.join("\n    "),
+    )
+}
+
+/// The manifest properties EPUB 3 requires of a chapter holding inline SVG (`svg`), MathML
+/// (`mathml`) or scripts (`scripted`). The `math/tex` scripts kept for formulas hold no code
+/// and do not count.
+fn properties(body: &str) -> Vec<&'static str> {
+    let fragment = Html::parse_fragment(body);
+    let has = |selector: &str| {
+        fragment
+            .select(&Selector::parse(selector).unwrap())
+            .next()
+            .is_some()
+    };
+    [
+        (has("svg"), "svg"),
+        (has("math"), "mathml"),
+        (has("script:not([type^='math/'])"), "scripted"),
+    ]
+    .into_iter()
+    .filter_map(|(present, property)| present.then_some(property))
+    .collect()
+}
+
+fn chapter_document(chapter: &Chapter, lang: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xml:lang="{lang}" lang="{lang}">
+<head><meta charset="UTF-8"/><title>{title}</title><link rel="stylesheet" type="text/css" href="style.css"/></head>
+<body>
+{body}
+</body>
+</html>
+"#,
+        lang = escape_html(lang),
+        title = escape_html(&chapter.title),
+        body = chapter.body,
+    )
+}
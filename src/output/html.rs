@@ -0,0 +1,235 @@
+use super::anchors::Anchors;
+use super::document::Document;
+use super::search_box::{self, chapter_id};
+use super::xhtml::{self, Resource};
+use super::{generated_at, minify, reading_minutes, word_count};
+use crate::config::Config;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use std::error::Error;
+use tera::{Context, Tera};
+use url::Url;
+
+/// Layout used unless `--template` names another.
+pub const DEFAULT_TEMPLATE: &str = include_str!("../../templates/book.html");
+
+/// Template name; the `.html` extension turns on Tera's HTML autoescaping.
+const TEMPLATE_NAME: &str = "book.html";
+
+/// A chapter as seen by the template.
+#[derive(Serialize)]
+struct Chapter<'a> {
+    /// Id of the chapter's `<section>`, for linking to it.
+    id: String,
+    title: &'a str,
+    url: &'a str,
+    lang: Option<&'a str>,
+    /// Depth in the crawl: 0 for chapters reached by next links, one more per followed link.
+    depth: usize,
+    html: String,
+}
+
+/// A heading of a chapter that the table of contents links to.
+#[derive(Serialize)]
+struct Section {
+    id: String,
+    title: String,
+}
+
+/// Where a volume of a split book sits among the others; see `--split-size`.
+#[derive(Serialize)]
+pub struct Volume {
+    /// Starting at 1.
+    pub number: usize,
+    pub count: usize,
+    /// File names of the neighbouring volumes and the index page.
+    pub previous: Option<String>,
+    pub next: Option<String>,
+    pub index: String,
+}
+
+/// Facts about the scrape itself.
+#[derive(Serialize)]
+struct Metadata<'a> {
+    generator: String,
+    /// Null with `--reproducible`.
+    generated_at: Option<String>,
+    start_urls: Vec<&'a str>,
+    page_count: usize,
+}
+
+/// Checks that a `--template` file parses, so mistakes surface before the crawl.
+pub fn validate_template(source: &str) -> Result<(), String> {
+    Tera::default()
+        .add_raw_template(TEMPLATE_NAME, source)
+        .map_err(|e| format!("Invalid template: {}", error_chain(&e)))
+}
+
+/// Renders the scraped chapters into one standalone HTML document with the configured
+/// Tera template, or the default one.
+///
+/// The template gets `title`, `lang`, `chapters` (each with `id`, `title`, `url`, `lang`,
+/// `depth` and `html`), `toc` (the chapters without their HTML, each with the `sections`
+/// its `<h2 id>` headings start, its `words` and its reading time in `minutes`), `search`
+/// (the search box, empty without `--search`), `cover` (the title page, empty without
+/// `--cover`), `attribution` (the licenses of the pages, empty without `--attribution`),
+/// `term_index` (the index of terms, empty without `--term-index`), `site_css` (the site's
+/// stylesheets, empty without `--site-css`), `css` (the `--css` files), `icon` (the site's
+/// icon as a data URI, empty without `--favicon` or `--cover-image`), `volume` (`number`,
+/// `count`, `previous`, `next` and `index`, or null when the book is not split) and
+/// `metadata` (`generator`, `generated_at`, `start_urls`, `page_count`).
+///
+/// With `resource`, every image, stylesheet and font is loaded from the URL it returns,
+/// such as a data URI or a path inside a bundle, and the remaining links are made absolute.
+/// With `link`, hyperlinks point where it says, such as another file of a mirror.
+/// `--minify` shrinks the rendered document.
+pub fn render(
+    config: &Config,
+    document: &Document,
+    resource: Option<Resource>,
+    link: Option<Resource>,
+    volume: Option<&Volume>,
+) -> Result<String, String> {
+    let chapters = document
+        .chapters
+        .iter()
+        .map(|chapter| {
+            let page = chapter.page;
+            Chapter {
+                id: chapter_id(chapter.position),
+                title: chapter.title_or_url(),
+                url: page.url.as_str(),
+                lang: page.lang.as_deref(),
+                depth: chapter.depth,
+                html: match (resource, link) {
+                    (None, None) => page.html.clone(),
+                    (resource, link) => xhtml::serialize(
+                        &page.html,
+                        &page.url,
+                        resource.unwrap_or(&|_| None),
+                        link.unwrap_or(&|_| None),
+                        config.keep_scripts,
+                    ),
+                },
+            }
+        })
+        .collect::<Vec<_>>();
+    let toc = chapters
+        .iter()
+        .zip(&document.chapters)
+        .map(|(chapter, source)| {
+            let words = word_count(source.page);
+            serde_json::json!({
+                "id": chapter.id,
+                "title": chapter.title,
+                "url": chapter.url,
+                "depth": chapter.depth,
+                "words": words,
+                "minutes": reading_minutes(words),
+                "sections": sections(&source.page.html, chapter.title),
+            })
+        })
+        .collect::<Vec<_>>();
+    let metadata = Metadata {
+        generator: format!("documentation_scraper/{}", env!("CARGO_PKG_VERSION")),
+        generated_at: generated_at(config).map(|time| time.to_rfc3339()),
+        start_urls: config.start_urls.iter().map(|url| url.as_str()).collect(),
+        page_count: document.chapters.len(),
+    };
+
+    let mut context = Context::new();
+    context.insert("title", document.title());
+    context.insert("lang", document.lang);
+    context.insert("chapters", &chapters);
+    context.insert("toc", &toc);
+    context.insert(
+        "search",
+        &if config.search {
+            search_box::render(document)
+        } else {
+            String::new()
+        },
+    );
+    context.insert(
+        "cover",
+        &document
+            .cover
+            .as_ref()
+            .map(|cover| cover.html())
+            .unwrap_or_default(),
+    );
+    context.insert(
+        "attribution",
+        &document
+            .attribution
+            .as_ref()
+            .map(|attribution| attribution.html())
+            .unwrap_or_default(),
+    );
+    let anchors = Anchors::new(document);
+    let anchor = |url: &Url| anchors.link(url);
+    context.insert(
+        "term_index",
+        &document
+            .term_index
+            .as_ref()
+            .map(|index| index.html(link.unwrap_or(&anchor)))
+            .unwrap_or_default(),
+    );
+    // Nothing in a stylesheet may end the <style> element it is put in.
+    context.insert(
+        "site_css",
+        &document
+            .site_css
+            .as_deref()
+            .unwrap_or_default()
+            .replace("</", "<\\/"),
+    );
+    context.insert("css", &config.css.join("\n").replace("</", "<\\/"));
+    context.insert("icon", &document.icon.as_deref().unwrap_or_default());
+    context.insert("volume", &volume);
+    context.insert("metadata", &metadata);
+
+    let mut tera = Tera::default();
+    let source = config.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+    let html = tera
+        .add_raw_template(TEMPLATE_NAME, source)
+        .and_then(|_| tera.render(TEMPLATE_NAME, &context))
+        .map_err(|e| format!("Failed to render template: {}", error_chain(&e)))?;
+    Ok(if config.minify {
+        minify::html(&html)
+    } else {
+        html
+    })
+}
+
+/// The `<h2>` headings of a chapter that have an id to link to, other than one repeating
+/// the chapter's `title`.
+fn sections(html: &str, title: &str) -> Vec<Section> {
+    let selector = Selector::parse("h2[id]").unwrap();
+    Html::parse_fragment(html)
+        .select(&selector)
+        .filter_map(|heading| {
+            // Sphinx and others end headings with a `¶` permalink.
+            let text = heading.text().collect::<String>();
+            let text = text.trim().trim_end_matches('¶').trim_end();
+            let id = heading.value().attr("id")?.to_string();
+            (!text.is_empty() && text != title && !id.is_empty()).then(|| Section {
+                id,
+                title: text.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Tera puts the useful detail (line, missing variable) in the error's sources.
+fn error_chain(error: &tera::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
@@ -0,0 +1,81 @@
+use super::document::Document;
+use crate::assets::{Assets, extension_for, image_urls};
+use crate::config::Config;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use url::Url;
+
+/// The downloaded images of a book, saved as files next to its HTML for
+/// `--localize-images`.
+pub struct LocalImages<'a> {
+    assets: &'a Assets,
+    /// Images saved as files, in order of first use, with their path relative to the HTML.
+    files: Vec<(Url, String)>,
+    paths: HashMap<Url, String>,
+    /// Images smaller than this are embedded as data URIs rather than saved.
+    inline_under: usize,
+}
+
+impl<'a> LocalImages<'a> {
+    /// Numbers the downloaded images of `document` in order of first use, to be saved
+    /// under `directory`.
+    pub fn new(
+        config: &Config,
+        document: &Document,
+        assets: &'a Assets,
+        directory: &str,
+    ) -> LocalImages<'a> {
+        let inline_under = config.inline_images_under.unwrap_or(0);
+        let mut files = Vec::new();
+        let mut paths = HashMap::new();
+        for url in document.pages().into_iter().flat_map(image_urls) {
+            if paths.contains_key(&url) {
+                continue;
+            }
+            let Some(asset) = assets.get(&url) else {
+                continue;
+            };
+            if asset.bytes.len() < inline_under {
+                continue;
+            }
+            let path = format!(
+                "{}/{:03}.{}",
+                directory,
+                files.len() + 1,
+                extension_for(&asset.media_type)
+            );
+            files.push((url.clone(), path.clone()));
+            paths.insert(url, path);
+        }
+        LocalImages {
+            assets,
+            files,
+            paths,
+            inline_under,
+        }
+    }
+
+    /// Where the HTML loads `url` from: the saved file, a data URI for a small image, or
+    /// `None` to keep loading it from the site.
+    pub fn resource(&self, url: &Url) -> Option<String> {
+        match self.paths.get(url) {
+            Some(path) => Some(path.clone()),
+            None => self
+                .assets
+                .get(url)
+                .filter(|asset| asset.bytes.len() < self.inline_under)
+                .and_then(|_| self.assets.data_uri(url)),
+        }
+    }
+
+    /// The image files to write, relative to the directory holding the HTML.
+    pub fn files(&self) -> Vec<(PathBuf, Vec<u8>)> {
+        self.files
+            .iter()
+            .map(|(url, path)| {
+                let asset = self.assets.get(url).unwrap();
+                (PathBuf::from(path), asset.bytes.clone())
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,81 @@
+use super::document::Document;
+use super::plain_text;
+use crate::config::Config;
+use serde::Serialize;
+use url::Url;
+
+/// One line of the export.
+#[derive(Serialize)]
+struct Record<'a> {
+    /// Position of the page in reading order, starting at 0.
+    index: usize,
+    /// Index of the start URL the page was reached from.
+    seed: usize,
+    /// Position within that start URL's crawl, see `Page::order`.
+    order: &'a [usize],
+    url: &'a str,
+    title: Option<&'a str>,
+    lang: Option<&'a str>,
+    html: &'a str,
+    text: String,
+    /// The license found with `--attribution`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<LicenseRecord<'a>>,
+    /// The sections above the page, from its breadcrumb trail.
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    breadcrumbs: &'a [String],
+    fetch: Fetch<'a>,
+}
+
+#[derive(Serialize)]
+struct LicenseRecord<'a> {
+    statement: &'a str,
+    url: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Fetch<'a> {
+    status: u16,
+    content_type: Option<&'a str>,
+    bytes: usize,
+    /// Left out with `--reproducible`, like `fetched_at`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fetched_at: Option<String>,
+}
+
+/// Writes one JSON object per page, one per line.
+pub fn render(config: &Config, document: &Document) -> Result<Vec<u8>, String> {
+    let timings = !config.reproducible;
+    let mut output = Vec::new();
+    for chapter in &document.chapters {
+        let page = chapter.page;
+        let record = Record {
+            index: chapter.position,
+            seed: page.seed,
+            order: &page.order,
+            url: page.url.as_str(),
+            title: page.title.as_deref(),
+            lang: page.lang.as_deref(),
+            html: &page.html,
+            text: plain_text(page),
+            license: page.license.as_ref().map(|license| LicenseRecord {
+                statement: &license.statement,
+                url: license.url.as_ref().map(Url::as_str),
+            }),
+            breadcrumbs: &page.breadcrumbs,
+            fetch: Fetch {
+                status: page.fetch.status,
+                content_type: page.fetch.content_type.as_deref(),
+                bytes: page.fetch.bytes,
+                elapsed_ms: timings.then_some(page.fetch.elapsed.as_millis()),
+                fetched_at: timings.then(|| page.fetch.fetched_at.to_rfc3339()),
+            },
+        };
+        serde_json::to_writer(&mut output, &record)
+            .map_err(|e| format!("Failed to encode {}: {}", page.url, e))?;
+        output.push(b'\n');
+    }
+    Ok(output)
+}
@@ -0,0 +1,102 @@
+use super::document::Document;
+use super::markdown::{self, Style, escape};
+use super::xhtml::Resource;
+use crate::assets::{Assets, extension_for, image_urls};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use url::Url;
+
+/// Packages that work with pdfLaTeX as well as XeLaTeX and LuaLaTeX, the latter two
+/// being needed for text outside Latin-1.
+const PREAMBLE: &str = r"\documentclass[11pt]{book}
+\usepackage{iftex}
+\ifPDFTeX
+  \usepackage[T1]{fontenc}
+  \usepackage[utf8]{inputenc}
+\else
+  \usepackage{fontspec}
+\fi
+\usepackage{graphicx}
+\usepackage[export]{adjustbox}
+\usepackage{listings}
+\usepackage{longtable}
+\usepackage{enumitem}
+\usepackage[normalem]{ulem}
+\usepackage{xcolor}
+\usepackage{hyperref}
+\lstset{basicstyle=\ttfamily\small, breaklines=true, columns=fullflexible, frame=single, backgroundcolor=\color{black!3}, showstringspaces=false}
+\hypersetup{colorlinks=true, linkcolor=blue, urlcolor=blue}
+";
+
+/// Image types every LaTeX engine can include.
+const SUPPORTED_IMAGES: &[&str] = &["image/png", "image/jpeg", "application/pdf"];
+
+/// Writes the chapters as a LaTeX book, `book.tex`, with the downloaded images next to it
+/// under `images/`, ready for `latexmk -pdf book.tex` or `lualatex book.tex`.
+///
+/// Every chapter starts with `\chapter`: its first `<h1>` when it has one, its title
+/// otherwise. Images LaTeX cannot include, such as SVG, are left out.
+/// Returns the files to write, relative to the output directory.
+pub fn render(
+    document: &Document,
+    assets: &Assets,
+    link_footnotes: bool,
+) -> Vec<(PathBuf, Vec<u8>)> {
+    let mut files = Vec::new();
+    let mut image_paths: HashMap<Url, String> = HashMap::new();
+    for url in document.pages().into_iter().flat_map(image_urls) {
+        if image_paths.contains_key(&url) {
+            continue;
+        }
+        if let Some(asset) = assets.get(&url)
+            && SUPPORTED_IMAGES.contains(&asset.media_type.as_str())
+        {
+            let path = format!(
+                "images/{:03}.{}",
+                image_paths.len() + 1,
+                extension_for(&asset.media_type)
+            );
+            files.push((PathBuf::from(&path), asset.bytes.clone()));
+            image_paths.insert(url, path);
+        }
+    }
+
+    let tex = source(
+        document,
+        &|url| image_paths.get(url).cloned(),
+        link_footnotes,
+    );
+    files.insert(0, (PathBuf::from("book.tex"), tex.into_bytes()));
+    files
+}
+
+/// The complete LaTeX source, with `images` naming the file to include for each image.
+/// With `link_footnotes`, links to other sites get a footnote giving their URL.
+pub fn source(document: &Document, images: Resource, link_footnotes: bool) -> String {
+    let chapters = document
+        .chapters
+        .iter()
+        .map(|chapter| {
+            let body = markdown::convert_with_images(
+                &chapter.page.html,
+                &chapter.page.url,
+                Style::Latex,
+                images,
+                &|_| None,
+                link_footnotes,
+            );
+            if body.contains("\\chapter{") {
+                body
+            } else {
+                let title = escape(chapter.title_or_url(), Style::Latex);
+                format!("\\chapter{{{}}}\n\n{}", title, body)
+            }
+        })
+        .collect::<Vec<_>>();
+    let title = escape(document.title(), Style::Latex);
+    format!(
+        "{PREAMBLE}\\hypersetup{{pdftitle={{{title}}}, pdflang={{{lang}}}}}\n\\title{{{title}}}\n\\date{{}}\n\n\\begin{{document}}\n\\maketitle\n\\tableofcontents\n\n{chapters}\n\n\\end{{document}}\n",
+        lang = escape(document.lang, Style::Latex),
+        chapters = chapters.join("\n\n"),
+    )
+}
@@ -0,0 +1,60 @@
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// A link in serialized XHTML, with its attributes and content.
+static LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<a\b([^>]*)>(.*?)</a>").unwrap());
+
+/// The `href` attribute of a link going to another site.
+static EXTERNAL_HREF: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\bhref="(https?://[^"]+)""#).unwrap());
+
+/// Any tag, to compare a link's text with its URL.
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+/// Numbers the links to other sites in a chapter, for `--link-footnotes`, and lists their
+/// URLs in an `<ol class="link-notes">` at its end, so a printed copy still tells where
+/// they lead. A URL linked several times keeps its first number, and links whose text is
+/// already the URL are left alone.
+///
+/// `html` is serialized XHTML, and `id` is the chapter's, which the notes' ids start with.
+pub fn add(html: &str, id: &str) -> String {
+    let mut numbers: HashMap<String, usize> = HashMap::new();
+    let mut notes = Vec::new();
+    let html = LINK.replace_all(html, |link: &Captures| {
+        let Some(href) = EXTERNAL_HREF
+            .captures(&link[1])
+            .map(|href| href[1].to_string())
+        else {
+            return link[0].to_string();
+        };
+        if TAG.replace_all(&link[2], "").trim() == href {
+            return link[0].to_string();
+        }
+        let number = *numbers.entry(href.clone()).or_insert_with(|| {
+            notes.push(href);
+            notes.len()
+        });
+        format!(
+            "{}<sup class=\"link-note\"><a href=\"#{}-link-{}\">{}</a></sup>",
+            &link[0], id, number, number
+        )
+    });
+    if notes.is_empty() {
+        return html.into_owned();
+    }
+    let items = notes
+        .iter()
+        .enumerate()
+        .map(|(index, href)| {
+            format!(
+                "<li id=\"{}-link-{}\"><a href=\"{}\">{}</a></li>",
+                id,
+                index + 1,
+                href,
+                href
+            )
+        })
+        .collect::<String>();
+    format!("{}<ol class=\"link-notes\">{}</ol>", html, items)
+}
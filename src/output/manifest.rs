@@ -0,0 +1,115 @@
+use super::{generated_at, reading_minutes, word_count};
+use crate::config::Config;
+use crate::crawl::{Failure, Page};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// `<output>.manifest.json`: what went into the output, for `--manifest`.
+#[derive(Serialize)]
+struct Manifest<'a> {
+    generator: String,
+    /// Left out with `--reproducible`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generated_at: Option<String>,
+    start_urls: Vec<&'a str>,
+    /// The files and directories written, one per format.
+    outputs: Vec<String>,
+    /// Words in all the pages, and their reading time in minutes.
+    words: usize,
+    reading_minutes: usize,
+    pages: &'a [Entry],
+    failures: Vec<FailureEntry<'a>>,
+}
+
+/// A fetched page, in the order it appears in the output.
+#[derive(Serialize)]
+pub struct Entry {
+    url: String,
+    title: Option<String>,
+    status: u16,
+    content_type: Option<String>,
+    /// Size of the response body as received.
+    bytes: usize,
+    /// SHA-256 of the response body, in hex.
+    sha256: String,
+    /// Words in the text of the chapter, and its reading time in minutes.
+    words: usize,
+    reading_minutes: usize,
+    /// Left out with `--reproducible`, like `fetched_at`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fetched_at: Option<String>,
+    /// Other URLs that served the same content, left out of the output.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<String>,
+}
+
+impl Entry {
+    /// Records another URL the page's content was found at.
+    pub fn add_alias(&mut self, url: &Url) {
+        self.aliases.push(url.to_string());
+    }
+}
+
+#[derive(Serialize)]
+struct FailureEntry<'a> {
+    url: &'a str,
+    error: &'a str,
+}
+
+/// What the manifest records about `page`.
+pub fn entry(config: &Config, page: &Page) -> Entry {
+    let timings = !config.reproducible;
+    let words = word_count(page);
+    Entry {
+        url: page.url.to_string(),
+        title: page.title.clone(),
+        status: page.fetch.status,
+        content_type: page.fetch.content_type.clone(),
+        bytes: page.fetch.bytes,
+        sha256: page.fetch.sha256.clone(),
+        words,
+        reading_minutes: reading_minutes(words),
+        elapsed_ms: timings.then_some(page.fetch.elapsed.as_millis()),
+        fetched_at: timings.then(|| page.fetch.fetched_at.to_rfc3339()),
+        aliases: page.aliases.iter().map(Url::to_string).collect(),
+    }
+}
+
+/// Writes the manifest to `path`. `failures` are those of the start URLs that went into
+/// the outputs.
+pub fn write(
+    config: &Config,
+    path: &Path,
+    outputs: &[PathBuf],
+    pages: &[Entry],
+    failures: &[&Failure],
+) -> Result<(), String> {
+    let words = pages.iter().map(|page| page.words).sum();
+    let manifest = Manifest {
+        generator: format!("documentation_scraper/{}", env!("CARGO_PKG_VERSION")),
+        generated_at: generated_at(config).map(|time| time.to_rfc3339()),
+        start_urls: config.start_urls.iter().map(|url| url.as_str()).collect(),
+        outputs: outputs
+            .iter()
+            .map(|output| output.display().to_string())
+            .collect(),
+        words,
+        reading_minutes: reading_minutes(words),
+        pages,
+        failures: failures
+            .iter()
+            .map(|failure| FailureEntry {
+                url: failure.url.as_str(),
+                error: &failure.error,
+            })
+            .collect(),
+    };
+    let mut contents = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| format!("Failed to encode manifest: {}", e))?;
+    contents.push(b'\n');
+    fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
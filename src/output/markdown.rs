@@ -0,0 +1,1187 @@
+use super::document::{Chapter, Document};
+use super::table;
+use super::xhtml::{self, Resource};
+use chrono::{DateTime, SecondsFormat, Utc};
+use scraper::node::Node;
+use scraper::{CaseSensitivity, ElementRef, Html};
+use url::Url;
+
+/// Elements whose content never belongs in the document.
+const SKIPPED: &[&str] = &["button", "noscript", "script", "style", "template"];
+
+/// Elements that start a new Markdown block instead of continuing the current paragraph.
+const BLOCKS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "ul",
+];
+
+/// Flavour of the converted text.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Markdown,
+    /// Plain text: no markup except `#` heading markers, code blocks indented by four spaces,
+    /// and tables as tab-separated rows, or as a grid if their cells span rows or columns.
+    Text,
+    /// LaTeX: sectioning commands from `\chapter` for `<h1>` down, `lstlisting` code
+    /// blocks, and list, quote and `longtable` environments.
+    Latex,
+    /// AsciiDoc for Asciidoctor: `<h1>` becomes a `==` chapter heading below the book title,
+    /// code becomes `[source]` blocks and lists nest by repeating their marker.
+    Asciidoc,
+    /// Org mode: `*` headings, `#+begin_src` blocks with the language, `[[url][text]]` links.
+    Org,
+}
+
+/// Converts the scraped chapters into one document, separated by horizontal rules, then
+/// its `--attribution`. `links` points links at local copies of the files they go to.
+pub fn render(document: &Document, style: Style, links: Resource) -> String {
+    let pages = document.pages();
+    let mut chapters = pages
+        .iter()
+        .map(|page| convert_with_images(&page.html, &page.url, style, &|_| None, links, false))
+        .collect::<Vec<_>>();
+    if let (Some(attribution), Some(page)) = (&document.attribution, pages.first()) {
+        chapters.push(convert(&attribution.html(), &page.url, style));
+    }
+    format!("{}\n", chapters.join(&separator(style)))
+}
+
+/// YAML front matter for a chapter written to its own Markdown file, as Hugo, Jekyll and
+/// Obsidian read it: the title, the URL it was scraped from, its position in the book, the
+/// sections of its breadcrumb trail and, unless `fetched_at` is `None`, when it was scraped.
+pub fn front_matter(chapter: &Chapter, fetched_at: Option<&DateTime<Utc>>) -> String {
+    // JSON strings are valid YAML scalars, so they need no further quoting.
+    let quote = |value: &str| serde_json::to_string(value).unwrap_or_default();
+    let mut lines = vec!["---".to_string()];
+    if let Some(title) = &chapter.title {
+        lines.push(format!("title: {}", quote(title)));
+    }
+    lines.push(format!("source: {}", quote(chapter.page.url.as_str())));
+    lines.push(format!("order: {}", chapter.position + 1));
+    if chapter.sections.is_empty() {
+        lines.push("sections: []".to_string());
+    } else {
+        lines.push("sections:".to_string());
+        lines.extend(
+            chapter
+                .sections
+                .iter()
+                .map(|section| format!("  - {}", quote(section))),
+        );
+    }
+    if let Some(time) = fetched_at {
+        lines.push(format!(
+            "date: {}",
+            quote(&time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        ));
+    }
+    lines.push("---".to_string());
+    format!("{}\n\n", lines.join("\n"))
+}
+
+/// What goes between two chapters in [`render`].
+pub fn separator(style: Style) -> String {
+    match style {
+        Style::Markdown => "\n\n---\n\n".to_string(),
+        Style::Text => format!("\n\n{}\n\n", "=".repeat(72)),
+        Style::Latex => "\n\n\\clearpage\n\n".to_string(),
+        Style::Asciidoc | Style::Org => "\n\n".to_string(),
+    }
+}
+
+/// Converts an HTML fragment, resolving relative links against `base`.
+pub fn convert(html: &str, base: &Url, style: Style) -> String {
+    convert_with_images(html, base, style, &|_| None, &|_| None, false)
+}
+
+/// Like [`convert`], with `images` picking the file to show for each image, given its
+/// absolute URL, and `links` the target of each link that has a local copy. LaTeX shows
+/// only images it gets a file for, and with `link_footnotes` gives the URL of each link to
+/// another site in a footnote.
+pub fn convert_with_images(
+    html: &str,
+    base: &Url,
+    style: Style,
+    images: Resource,
+    links: Resource,
+    link_footnotes: bool,
+) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut converter = Converter {
+        base,
+        style,
+        images,
+        links,
+        link_footnotes,
+        in_heading: false,
+        list_depth: 0,
+    };
+    converter.blocks(fragment.root_element(), "\n\n")
+}
+
+struct Converter<'a> {
+    base: &'a Url,
+    style: Style,
+    images: Resource<'a>,
+    links: Resource<'a>,
+    /// Whether LaTeX links to other sites get a footnote with their URL.
+    link_footnotes: bool,
+    /// Heading permalinks (`<a href="#id">`) are dropped while this is set.
+    in_heading: bool,
+    /// Number of lists around the current element, for AsciiDoc's list markers.
+    list_depth: usize,
+}
+
+impl Converter<'_> {
+    /// Renders the children of `element` as blocks joined by `separator`.
+    ///
+    /// Runs of inline content between block elements become paragraphs.
+    fn blocks(&mut self, element: ElementRef, separator: &str) -> String {
+        self.blocks_except(element, separator, None)
+    }
+
+    /// Like [`Converter::blocks`], leaving out the child `skipped`.
+    fn blocks_except(
+        &mut self,
+        element: ElementRef,
+        separator: &str,
+        skipped: Option<ego_tree::NodeId>,
+    ) -> String {
+        let mut blocks = Vec::new();
+        let mut paragraph = String::new();
+        for child in element
+            .children()
+            .filter(|child| Some(child.id()) != skipped)
+        {
+            match ElementRef::wrap(child) {
+                Some(child) if BLOCKS.contains(&child.value().name()) => {
+                    push_block(&mut blocks, &paragraph);
+                    paragraph.clear();
+                    let block = self.block(child);
+                    push_block(&mut blocks, &block);
+                }
+                _ => self.inline(child, &mut paragraph),
+            }
+        }
+        push_block(&mut blocks, &paragraph);
+        blocks.join(separator)
+    }
+
+    fn block(&mut self, element: ElementRef) -> String {
+        match element.value().name() {
+            name @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                self.in_heading = true;
+                let text = self.inline_text(element);
+                self.in_heading = false;
+                match self.style {
+                    Style::Latex => format!("\\{}{{{}}}", LATEX_SECTIONS[level - 1], text),
+                    // AsciiDoc has six levels, the first being the book title.
+                    Style::Asciidoc => format!("{} {}", "=".repeat((level + 1).min(6)), text),
+                    Style::Org => format!("{} {}", "*".repeat(level), text),
+                    _ => format!("{} {}", "#".repeat(level), text),
+                }
+            }
+            "p" => self.inline_text(element),
+            "pre" => self.code_block(element),
+            "ul" => self.list(element, false),
+            "ol" => self.list(element, true),
+            "dl" => self.definitions(element),
+            "div" | "aside" if element.value().classes().any(|class| class == "admonition") => {
+                self.admonition(element)
+            }
+            "blockquote" if self.style == Style::Latex => format!(
+                "\\begin{{quote}}\n{}\n\\end{{quote}}",
+                self.blocks(element, "\n\n")
+            ),
+            "blockquote" if self.style == Style::Asciidoc => {
+                format!("____\n{}\n____", self.blocks(element, "\n\n"))
+            }
+            "blockquote" if self.style == Style::Org => format!(
+                "#+begin_quote\n{}\n#+end_quote",
+                self.blocks(element, "\n\n")
+            ),
+            "blockquote" => prefix_lines(&self.blocks(element, "\n\n"), "> ", ">"),
+            "table" => self.table(element),
+            "hr" if self.style == Style::Latex => {
+                "\\noindent\\rule{\\linewidth}{0.4pt}".to_string()
+            }
+            "hr" if self.style == Style::Asciidoc => "'''".to_string(),
+            "hr" if self.style == Style::Org => "-----".to_string(),
+            "hr" => "---".to_string(),
+            _ => self.blocks(element, "\n\n"),
+        }
+    }
+
+    /// A note, tip or warning in the markup admonitions are normalized to, as a quote
+    /// starting with its title, or AsciiDoc's own admonition block.
+    fn admonition(&mut self, element: ElementRef) -> String {
+        let kind = element
+            .value()
+            .classes()
+            .find(|class| *class != "admonition")
+            .unwrap_or("note");
+        let title_element = element
+            .children()
+            .filter_map(ElementRef::wrap)
+            .find(|child| {
+                child
+                    .value()
+                    .classes()
+                    .any(|class| class == "admonition-title")
+            });
+        let title = title_element
+            .map(|title| self.inline_text(title))
+            .filter(|title| !title.is_empty());
+        let body = self.blocks_except(element, "\n\n", title_element.map(|title| title.id()));
+        let title = title.unwrap_or_else(|| {
+            let mut label = kind.to_string();
+            label[..1].make_ascii_uppercase();
+            label
+        });
+        match self.style {
+            Style::Markdown => prefix_lines(&format!("**{}**\n\n{}", title, body), "> ", ">"),
+            Style::Text => format!("{}:\n\n{}", title, body),
+            Style::Latex => format!(
+                "\\begin{{quote}}\n\\textbf{{{}}}\n\n{}\n\\end{{quote}}",
+                title, body
+            ),
+            Style::Org => format!("#+begin_quote\n*{}*\n\n{}\n#+end_quote", title, body),
+            Style::Asciidoc => {
+                // AsciiDoc knows five kinds; danger is the strongest of them, a warning.
+                let label = match kind {
+                    "tip" => "TIP",
+                    "important" => "IMPORTANT",
+                    "caution" => "CAUTION",
+                    "warning" | "danger" => "WARNING",
+                    _ => "NOTE",
+                };
+                let title = if title.eq_ignore_ascii_case(label) {
+                    String::new()
+                } else {
+                    format!(".{}\n", title)
+                };
+                format!("{}[{}]\n====\n{}\n====", title, label, body)
+            }
+        }
+    }
+
+    fn inline_text(&mut self, element: ElementRef) -> String {
+        let mut text = String::new();
+        for child in element.children() {
+            self.inline(child, &mut text);
+        }
+        text.trim().to_string()
+    }
+
+    fn inline(&mut self, node: ego_tree::NodeRef<Node>, out: &mut String) {
+        match node.value() {
+            Node::Text(text) => push_text(out, &text.text, self.style),
+            Node::Element(_) => {
+                if let Some(element) = ElementRef::wrap(node) {
+                    self.inline_element(element, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn inline_element(&mut self, element: ElementRef, out: &mut String) {
+        let name = element.value().name();
+        if SKIPPED.contains(&name) {
+            return;
+        }
+        if name == "math"
+            && let Some(tex) = tex_annotation(element)
+        {
+            let display = element.value().attr("display") == Some("block");
+            out.push_str(&match (self.style, display) {
+                (Style::Text, _) => tex,
+                (Style::Markdown, false) => format!("${}$", tex),
+                (Style::Markdown, true) => format!("$${}$$", tex),
+                (Style::Asciidoc, _) => format!("latexmath:[{}]", tex.replace(']', "\\]")),
+                (Style::Latex | Style::Org, false) => format!("\\({}\\)", tex),
+                (Style::Latex | Style::Org, true) => format!("\\[{}\\]", tex),
+            });
+            return;
+        }
+        if self.style == Style::Text {
+            match name {
+                "br" => out.push('\n'),
+                "a" if element.value().classes().any(|class| class == "headerlink") => {}
+                "code" | "kbd" | "samp" => out.push_str(&element.text().collect::<String>()),
+                "img" => {
+                    let alt = escape(element.value().attr("alt").unwrap_or_default(), Style::Text);
+                    if !alt.is_empty() {
+                        out.push_str(&format!("[image: {}]", alt));
+                    }
+                }
+                // An image inlined by `--inline-svg`, which keeps its alt text as a label.
+                "svg" => {
+                    let label = escape(
+                        element.value().attr("aria-label").unwrap_or_default(),
+                        Style::Text,
+                    );
+                    if !label.is_empty() {
+                        out.push_str(&format!("[image: {}]", label));
+                    }
+                }
+                _ => {
+                    for child in element.children() {
+                        self.inline(child, out);
+                    }
+                }
+            }
+            return;
+        }
+        if self.style == Style::Latex {
+            match name {
+                "br" => {
+                    if !out.trim().is_empty() && !out.ends_with('\n') {
+                        out.push_str("\\\\\n");
+                    }
+                }
+                "strong" | "b" => self.emphasis(element, "\\textbf{", "}", out),
+                "em" | "i" => self.emphasis(element, "\\emph{", "}", out),
+                "del" | "s" => self.emphasis(element, "\\sout{", "}", out),
+                "code" | "kbd" | "samp" => {
+                    let code = element.text().collect::<String>();
+                    let code = escape(&code, Style::Latex);
+                    if !code.is_empty() {
+                        out.push_str(&format!("\\texttt{{{}}}", code));
+                    }
+                }
+                "a" => self.link(element, out),
+                "img" => {
+                    let file = element
+                        .value()
+                        .attr("src")
+                        .and_then(|src| self.base.join(src).ok())
+                        .and_then(|url| (self.images)(&url));
+                    if let Some(file) = file {
+                        out.push_str(&format!(
+                            "\\includegraphics[max width=\\linewidth]{{{}}}",
+                            file
+                        ));
+                    }
+                }
+                _ => {
+                    for child in element.children() {
+                        self.inline(child, out);
+                    }
+                }
+            }
+            return;
+        }
+        if self.style == Style::Org {
+            match name {
+                "br" => out.push_str("\\\\\n"),
+                "strong" | "b" => self.emphasis(element, "*", "*", out),
+                "em" | "i" => self.emphasis(element, "/", "/", out),
+                "del" | "s" => self.emphasis(element, "+", "+", out),
+                "code" | "kbd" | "samp" => {
+                    let code = element.text().collect::<String>().replace('\n', " ");
+                    // Org has no escapes, so code holding `~` is marked verbatim instead.
+                    let marker = if code.contains('~') { '=' } else { '~' };
+                    if !code.trim().is_empty() {
+                        out.push_str(&format!("{marker}{}{marker}", code.trim()));
+                    }
+                }
+                "a" => self.link(element, out),
+                "img" => {
+                    if let Some(src) = element.value().attr("src") {
+                        out.push_str(&format!("[[{}]]", self.resolve(src)));
+                    }
+                }
+                _ => {
+                    for child in element.children() {
+                        self.inline(child, out);
+                    }
+                }
+            }
+            return;
+        }
+        if self.style == Style::Asciidoc {
+            match name {
+                "br" => out.push_str(" +\n"),
+                "strong" | "b" => self.emphasis(element, "**", "**", out),
+                "em" | "i" => self.emphasis(element, "__", "__", out),
+                "del" | "s" => self.emphasis(element, "[.line-through]##", "##", out),
+                "code" | "kbd" | "samp" => {
+                    let code = element.text().collect::<String>().replace('\n', " ");
+                    if !code.is_empty() {
+                        out.push_str(&format!("`+{}+`", code));
+                    }
+                }
+                "a" => self.link(element, out),
+                "img" => {
+                    if let Some(src) = element.value().attr("src") {
+                        let alt = escape(
+                            element.value().attr("alt").unwrap_or_default(),
+                            Style::Asciidoc,
+                        );
+                        // A comma would start the next attribute, so such alt text is quoted.
+                        let alt = alt.replace(']', "\\]");
+                        let alt = if alt.contains([',', '"', '=']) {
+                            format!("\"{}\"", alt.replace('"', "\\\""))
+                        } else {
+                            alt
+                        };
+                        out.push_str(&format!("image:{}[{}]", self.resolve(src), alt));
+                    }
+                }
+                _ => {
+                    for child in element.children() {
+                        self.inline(child, out);
+                    }
+                }
+            }
+            return;
+        }
+        match name {
+            "br" => out.push_str("\\\n"),
+            "strong" | "b" => self.emphasis(element, "**", "**", out),
+            "em" | "i" => self.emphasis(element, "*", "*", out),
+            "del" | "s" => self.emphasis(element, "~~", "~~", out),
+            "code" | "kbd" | "samp" => {
+                let code = element.text().collect::<String>().replace('\n', " ");
+                if !code.is_empty() {
+                    let fence = "`".repeat(longest_run(&code, '`') + 1);
+                    let padding = if code.starts_with('`') || code.ends_with('`') {
+                        " "
+                    } else {
+                        ""
+                    };
+                    out.push_str(&format!("{fence}{padding}{code}{padding}{fence}"));
+                }
+            }
+            "a" => self.link(element, out),
+            "img" => {
+                if let Some(src) = element.value().attr("src") {
+                    let alt = element.value().attr("alt").unwrap_or_default();
+                    out.push_str(&format!(
+                        "![{}]({})",
+                        escape(alt, Style::Markdown),
+                        self.resolve(src)
+                    ));
+                }
+            }
+            _ => {
+                for child in element.children() {
+                    self.inline(child, out);
+                }
+            }
+        }
+    }
+
+    fn emphasis(&mut self, element: ElementRef, open: &str, close: &str, out: &mut String) {
+        let text = self.inline_text(element);
+        if !text.is_empty() {
+            out.push_str(&format!("{open}{text}{close}"));
+        }
+    }
+
+    fn link(&mut self, element: ElementRef, out: &mut String) {
+        if element.value().classes().any(|class| class == "headerlink") {
+            return;
+        }
+        let text = self.inline_text(element);
+        let permalink = |href: &str| self.in_heading && href.starts_with('#');
+        match element.value().attr("href") {
+            // LaTeX has no anchors for fragment links to point at.
+            Some(href) if self.style == Style::Latex && href.starts_with('#') => {
+                out.push_str(&text)
+            }
+            Some(href) if self.style == Style::Latex && !text.is_empty() => {
+                let url = escape_latex_url(&self.resolve(href));
+                out.push_str(&format!("\\href{{{}}}{{{}}}", url, text));
+                if self.link_footnotes && url.starts_with("http") && !self.in_heading {
+                    out.push_str(&format!("\\footnote{{\\url{{{}}}}}", url));
+                }
+            }
+            Some(href) if self.style == Style::Asciidoc && !text.is_empty() && !permalink(href) => {
+                let text = text.replace(']', "\\]");
+                match href.strip_prefix('#') {
+                    Some(id) => out.push_str(&format!("<<{},{}>>", id, text)),
+                    None => out.push_str(&format!("link:{}[{}]", self.resolve(href), text)),
+                }
+            }
+            // Org has no anchors for fragment links to point at.
+            Some(href) if self.style == Style::Org && href.starts_with('#') => out.push_str(&text),
+            Some(href) if self.style == Style::Org && !text.is_empty() => {
+                let text = text.replace("[", "{").replace("]", "}");
+                out.push_str(&format!("[[{}][{}]]", self.resolve(href), text));
+            }
+            Some(href) if !text.is_empty() && !permalink(href) => {
+                out.push_str(&format!("[{}]({})", text, self.resolve(href)));
+            }
+            _ => out.push_str(&text),
+        }
+    }
+
+    fn list(&mut self, element: ElementRef, ordered: bool) -> String {
+        let start = element
+            .value()
+            .attr("start")
+            .and_then(|start| start.parse::<usize>().ok())
+            .unwrap_or(1);
+        if self.style == Style::Latex {
+            let items = element
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|child| child.value().name() == "li")
+                .map(|item| format!("\\item {}", self.blocks(item, "\n\n")))
+                .collect::<Vec<_>>();
+            if items.is_empty() {
+                return String::new();
+            }
+            let (environment, options) = match (ordered, start) {
+                (false, _) => ("itemize", String::new()),
+                (true, 1) => ("enumerate", String::new()),
+                (true, start) => ("enumerate", format!("[start={}]", start)),
+            };
+            return format!(
+                "\\begin{{{environment}}}{options}\n{}\n\\end{{{environment}}}",
+                items.join("\n")
+            );
+        }
+        if self.style == Style::Asciidoc {
+            self.list_depth += 1;
+            let marker = if ordered { "." } else { "*" }.repeat(self.list_depth);
+            // Blocks after an item's first are attached to it with `+` continuation lines.
+            let items = element
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|child| child.value().name() == "li")
+                .map(|item| format!("{} {}", marker, self.blocks(item, "\n+\n")))
+                .collect::<Vec<_>>();
+            self.list_depth -= 1;
+            let items = items.join("\n");
+            return match (ordered, start) {
+                (true, start) if start != 1 => format!("[start={}]\n{}", start, items),
+                _ => items,
+            };
+        }
+        element
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|child| child.value().name() == "li")
+            .enumerate()
+            .map(|(position, item)| {
+                let marker = if ordered {
+                    format!("{}. ", start + position)
+                } else {
+                    "- ".to_string()
+                };
+                // Org numbers lists itself unless the first item sets the counter.
+                let counter = match self.style {
+                    Style::Org if ordered && position == 0 && start != 1 => {
+                        format!("[@{}] ", start)
+                    }
+                    _ => String::new(),
+                };
+                // Items made of paragraphs stay loose; plain items stay tight around nested lists.
+                let loose = item
+                    .children()
+                    .filter_map(ElementRef::wrap)
+                    .any(|child| child.value().name() == "p");
+                let content = self.blocks(item, if loose { "\n\n" } else { "\n" });
+                let indent = " ".repeat(marker.len());
+                let body = prefix_lines(&content, &indent, "");
+                format!(
+                    "{}{}{}",
+                    marker,
+                    counter,
+                    &body[indent.len().min(body.len())..]
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// A definition list, as API references use for parameters and members: each term in
+    /// bold with its description indented below it after a `:`, as Pandoc reads them, or
+    /// the style's own definition lists.
+    fn definitions(&mut self, element: ElementRef) -> String {
+        // HTML allows wrapping each term and its description in a `<div>`.
+        let children = element
+            .children()
+            .filter_map(ElementRef::wrap)
+            .flat_map(|child| match child.value().name() {
+                "div" => child.children().filter_map(ElementRef::wrap).collect(),
+                _ => vec![child],
+            })
+            .collect::<Vec<_>>();
+        // AsciiDoc tells nested lists apart by their markers, `::` then `:::` and so on.
+        self.list_depth += 1;
+        let marker = ":".repeat(self.list_depth + 1);
+        let mut entries: Vec<(Vec<String>, Vec<String>)> = Vec::new();
+        for child in children {
+            match child.value().name() {
+                "dt" => {
+                    let term = self.inline_text(child);
+                    match entries.last_mut() {
+                        Some((_, descriptions)) if descriptions.is_empty() => {}
+                        _ => entries.push((Vec::new(), Vec::new())),
+                    }
+                    entries.last_mut().unwrap().0.push(term);
+                }
+                "dd" => {
+                    let separator = if self.style == Style::Asciidoc {
+                        "\n+\n"
+                    } else {
+                        "\n\n"
+                    };
+                    let description = self.blocks(child, separator);
+                    if entries.is_empty() {
+                        entries.push((Vec::new(), Vec::new()));
+                    }
+                    entries.last_mut().unwrap().1.push(description);
+                }
+                _ => {}
+            }
+        }
+        self.list_depth -= 1;
+
+        let entries = entries.into_iter().map(|(terms, descriptions)| {
+            let terms = terms.into_iter().filter(|term| !term.is_empty());
+            match self.style {
+                Style::Latex => {
+                    let mut items = terms
+                        .map(|term| format!("\\item[{{{}}}]", term))
+                        .collect::<Vec<_>>();
+                    if items.is_empty() {
+                        items.push("\\item[]".to_string());
+                    }
+                    let last = items.pop().unwrap();
+                    items.push(format!("{} {}", last, descriptions.join("\n\n")));
+                    items.join("\n")
+                }
+                Style::Asciidoc => {
+                    let mut lines = terms
+                        .map(|term| format!("{}{}", term, marker))
+                        .collect::<Vec<_>>();
+                    lines.extend(descriptions);
+                    lines.join("\n")
+                }
+                Style::Org => {
+                    let terms = terms.collect::<Vec<_>>().join(", ");
+                    let body = prefix_lines(&descriptions.join("\n\n"), "  ", "");
+                    format!("- {} :: {}", terms, body.trim_start())
+                }
+                Style::Markdown | Style::Text => {
+                    // Terms sharing a description are kept on lines of their own.
+                    let terms = match self.style {
+                        Style::Markdown => terms
+                            .map(|term| format!("**{}**", term))
+                            .collect::<Vec<_>>()
+                            .join("\\\n"),
+                        _ => terms.collect::<Vec<_>>().join("\n"),
+                    };
+                    let mut lines = vec![terms];
+                    for description in descriptions {
+                        let body = if self.style == Style::Markdown {
+                            let body = prefix_lines(&description, "    ", "");
+                            format!(":   {}", &body[4.min(body.len())..])
+                        } else {
+                            prefix_lines(&description, "  ", "")
+                        };
+                        lines.push(body);
+                    }
+                    lines.join("\n")
+                }
+            }
+        });
+        let entries = entries.collect::<Vec<_>>();
+        if entries.is_empty() {
+            return String::new();
+        }
+        match self.style {
+            Style::Latex => format!(
+                "\\begin{{description}}\n{}\n\\end{{description}}",
+                entries.join("\n")
+            ),
+            _ => entries.join("\n\n"),
+        }
+    }
+
+    /// A table in the style's table syntax. Markdown embeds the HTML of tables GFM cannot
+    /// express, with cells spanning rows or columns or holding blocks, and plain text
+    /// draws them as a grid.
+    fn table(&mut self, element: ElementRef) -> String {
+        let rows = table::rows(element);
+        if table::is_complex(&rows) {
+            match self.style {
+                // An HTML block ends at a blank line, so blank lines are left out.
+                Style::Markdown => {
+                    return xhtml::serialize(
+                        &element.html(),
+                        self.base,
+                        self.images,
+                        &|_| None,
+                        false,
+                    )
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                }
+                Style::Text => {
+                    let cells = table::place(&rows);
+                    let texts = cells
+                        .iter()
+                        .map(|cell| self.blocks(cell.element, "\n\n"))
+                        .collect::<Vec<_>>();
+                    return table::grid(&cells, &texts);
+                }
+                _ => {}
+            }
+        }
+        let rows = rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|cell| match self.style {
+                        Style::Markdown => self
+                            .inline_text(cell)
+                            .replace("\\\n", "<br>")
+                            .replace('\n', " ")
+                            .replace('|', "\\|"),
+                        Style::Text => self.inline_text(cell).replace(['\n', '\t'], " "),
+                        Style::Latex => self
+                            .inline_text(cell)
+                            .replace("\\\\\n", "\\newline ")
+                            .replace('\n', " "),
+                        Style::Asciidoc => self.inline_text(cell).replace('|', "\\|"),
+                        Style::Org => self
+                            .inline_text(cell)
+                            .replace("\\\\\n", " ")
+                            .replace('\n', " ")
+                            .replace('|', "\\vert{}"),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return String::new();
+        }
+        if self.style == Style::Text {
+            return rows
+                .iter()
+                .map(|row| row.join("\t"))
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        if self.style == Style::Latex {
+            // Columns share the line width so long cells wrap, and the header row repeats
+            // on every page the table spans.
+            let column = format!("p{{{:.3}\\linewidth}}", 0.9 / columns as f64);
+            let format_row = |cells: &[String]| {
+                let cells = (0..columns)
+                    .map(|column| cells.get(column).map(String::as_str).unwrap_or(""))
+                    .collect::<Vec<_>>();
+                format!("{} \\\\ \\hline", cells.join(" & "))
+            };
+            let mut lines = vec![
+                format!(
+                    "\\begin{{longtable}}{{|{}|}}\n\\hline",
+                    vec![column; columns].join("|")
+                ),
+                format_row(&rows[0]),
+                "\\endhead".to_string(),
+            ];
+            lines.extend(rows[1..].iter().map(|row| format_row(row)));
+            lines.push("\\end{longtable}".to_string());
+            return lines.join("\n");
+        }
+        if self.style == Style::Asciidoc {
+            let mut lines = vec!["[%header]".to_string(), "|===".to_string()];
+            for row in &rows {
+                let cells = (0..columns)
+                    .map(|column| row.get(column).map(String::as_str).unwrap_or(""))
+                    .map(|cell| format!("| {}", cell).trim_end().to_string())
+                    .collect::<Vec<_>>();
+                lines.push(cells.join(" "));
+            }
+            lines.push("|===".to_string());
+            return lines.join("\n");
+        }
+
+        let format_row = |cells: &[String]| {
+            let cells = (0..columns)
+                .map(|column| cells.get(column).map(String::as_str).unwrap_or(""))
+                .collect::<Vec<_>>();
+            format!("| {} |", cells.join(" | "))
+        };
+        let separator = match self.style {
+            Style::Org => format!("|{}|", vec!["-----"; columns].join("+")),
+            _ => format!("|{}", " --- |".repeat(columns)),
+        };
+        let mut lines = vec![format_row(&rows[0]), separator];
+        lines.extend(rows[1..].iter().map(|row| format_row(row)));
+        lines.join("\n")
+    }
+
+    fn resolve(&self, href: &str) -> String {
+        if href.starts_with('#') {
+            return href.to_string();
+        }
+        self.base
+            .join(href)
+            .map(|url| (self.links)(&url).unwrap_or_else(|| url.to_string()))
+            .unwrap_or_else(|_| href.to_string())
+    }
+
+    /// Renders a `<pre>` block as a fenced code block, keeping the language from its classes.
+    fn code_block(&self, pre: ElementRef) -> String {
+        let code = pre.text().collect::<String>();
+        let code = code.trim_end_matches('\n');
+        if self.style == Style::Text {
+            return prefix_lines(code, "    ", "");
+        }
+        let language = pre
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .chain(pre.ancestors().filter_map(ElementRef::wrap))
+            .find_map(code_language)
+            .unwrap_or_default();
+        if self.style == Style::Latex {
+            // listings fails on languages it does not know, so others are left plain.
+            let options = listings_language(&language)
+                .map(|language| format!("[language={{{}}}]", language))
+                .unwrap_or_default();
+            return format!("\\begin{{lstlisting}}{options}\n{code}\n\\end{{lstlisting}}");
+        }
+        if self.style == Style::Asciidoc {
+            // The delimiter must be longer than any line of dashes in the code.
+            let delimiter = "-".repeat(
+                code.lines()
+                    .filter(|line| !line.is_empty() && line.chars().all(|c| c == '-'))
+                    .map(str::len)
+                    .max()
+                    .unwrap_or(0)
+                    .max(3)
+                    + 1,
+            );
+            let attributes = if language.is_empty() {
+                "[source]".to_string()
+            } else {
+                format!("[source,{}]", language)
+            };
+            return format!("{attributes}\n{delimiter}\n{code}\n{delimiter}");
+        }
+        if self.style == Style::Org {
+            // Lines that would read as headings or keywords are escaped with a comma.
+            let code = code
+                .lines()
+                .map(|line| {
+                    if line.starts_with('*')
+                        || line.starts_with("#+")
+                        || line.starts_with(",*")
+                        || line.starts_with(",#+")
+                    {
+                        format!(",{}", line)
+                    } else {
+                        line.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            return if language.is_empty() {
+                format!("#+begin_example\n{code}\n#+end_example")
+            } else {
+                format!("#+begin_src {language}\n{code}\n#+end_src")
+            };
+        }
+        let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+        let info = if language.is_empty() {
+            language
+        } else {
+            fence_info(pre, &language)
+        };
+        format!("{fence}{info}\n{code}\n{fence}")
+    }
+}
+
+/// Doctest attributes mdBook and rustdoc render as extra classes of a code block, and
+/// write after the language in a fence, e.g. `rust,ignore`.
+const CODE_ANNOTATIONS: &[&str] = &[
+    "ignore",
+    "no_run",
+    "should_panic",
+    "compile_fail",
+    "editable",
+    "noplayground",
+    "edition2015",
+    "edition2018",
+    "edition2021",
+    "edition2024",
+];
+
+/// Classes of the elements wrapping a highlighted line: Sphinx's `hll`, Prism's and
+/// Docusaurus's.
+const HIGHLIGHTED_LINE_CLASSES: &[&str] = &[
+    "hll",
+    "highlight-line",
+    "highlighted",
+    "theme-code-block-highlighted-line",
+];
+
+/// The info string of a fenced code block: the language, its doctest attributes and the
+/// lines the site highlights, e.g. `rust,should_panic {2,4-5}`.
+fn fence_info(pre: ElementRef, language: &str) -> String {
+    let elements = pre
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .chain(pre.ancestors().filter_map(ElementRef::wrap).take(2))
+        .collect::<Vec<_>>();
+    let mut info = language.to_string();
+    for annotation in CODE_ANNOTATIONS {
+        if elements.iter().any(|element| {
+            element
+                .value()
+                .has_class(annotation, CaseSensitivity::CaseSensitive)
+        }) {
+            info.push(',');
+            info.push_str(annotation);
+        }
+    }
+
+    let lines = match pre.value().attr("data-line") {
+        Some(lines) => lines.split_whitespace().collect::<String>(),
+        None => line_ranges(&highlighted_lines(pre)),
+    };
+    if !lines.is_empty() {
+        info.push_str(&format!(" {{{}}}", lines));
+    }
+    info
+}
+
+/// The numbers of the lines of a code block inside an element marking them highlighted,
+/// starting at 1.
+fn highlighted_lines(pre: ElementRef) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut line = 1;
+    for node in pre.descendants() {
+        let Node::Text(text) = node.value() else {
+            continue;
+        };
+        let highlighted = node
+            .ancestors()
+            .take_while(|ancestor| ancestor.id() != pre.id())
+            .filter_map(ElementRef::wrap)
+            .any(|element| {
+                element
+                    .value()
+                    .classes()
+                    .any(|class| HIGHLIGHTED_LINE_CLASSES.contains(&class))
+            });
+        for c in text.chars() {
+            if c == '\n' {
+                line += 1;
+            } else if highlighted && lines.last() != Some(&line) {
+                lines.push(line);
+            }
+        }
+    }
+    lines
+}
+
+/// Line numbers in ascending order as ranges, e.g. `1,3-5` for 1, 3, 4 and 5.
+fn line_ranges(lines: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Reads the language from classes like `language-rust`, `lang-rust` or Sphinx's
+/// `highlight-rust`, or rustdoc's `rust-example-rendered` and the `item-decl` of its item
+/// declarations. Sphinx's `highlight-default`
+/// and its other names for Python, such as `highlight-python3`, are Python.
+pub fn code_language(element: ElementRef) -> Option<String> {
+    element.value().classes().find_map(|class| {
+        if class == "rust-example-rendered" || class == "item-decl" {
+            return Some("rust".to_string());
+        }
+        ["language-", "lang-", "highlight-"]
+            .iter()
+            .find_map(|prefix| class.strip_prefix(prefix))
+            .filter(|language| !language.is_empty() && *language != "none")
+            .map(|language| match language {
+                "default" | "python3" | "py3" | "ipython" | "ipython3" => "python".to_string(),
+                _ => language.to_string(),
+            })
+    })
+}
+
+/// Sectioning commands for `<h1>` to `<h6>`.
+const LATEX_SECTIONS: [&str; 6] = [
+    "chapter",
+    "section",
+    "subsection",
+    "subsubsection",
+    "paragraph",
+    "subparagraph",
+];
+
+/// The name the `listings` package knows a language by, for the languages it ships with.
+fn listings_language(language: &str) -> Option<&'static str> {
+    Some(match language.to_ascii_lowercase().as_str() {
+        "bash" | "sh" | "shell" | "zsh" => "bash",
+        "c" => "C",
+        "cpp" | "c++" | "cxx" => "C++",
+        "csharp" | "cs" => "[Sharp]C",
+        "fortran" => "Fortran",
+        "haskell" | "hs" => "Haskell",
+        "html" => "HTML",
+        "java" => "Java",
+        "lisp" | "elisp" => "Lisp",
+        "lua" => "Lua",
+        "make" | "makefile" => "make",
+        "matlab" => "Matlab",
+        "ocaml" | "ml" => "[Objective]Caml",
+        "perl" | "pl" => "Perl",
+        "php" => "PHP",
+        "python" | "py" | "python3" => "Python",
+        "r" => "R",
+        "ruby" | "rb" => "Ruby",
+        "scala" => "Scala",
+        "sql" => "SQL",
+        "tex" | "latex" => "[LaTeX]TeX",
+        "xml" => "XML",
+        _ => return None,
+    })
+}
+
+fn push_block(blocks: &mut Vec<String>, block: &str) {
+    let block = block.trim_matches('\n');
+    if !block.trim().is_empty() {
+        blocks.push(block.trim_end().to_string());
+    }
+}
+
+/// Appends text with whitespace collapsed and the characters that are syntax in `style`
+/// escaped.
+fn push_text(out: &mut String, text: &str, style: Style) {
+    for ch in text.chars() {
+        if ch.is_whitespace() {
+            if !out.is_empty() && !out.ends_with([' ', '\n']) {
+                out.push(' ');
+            }
+            continue;
+        }
+        match style {
+            Style::Markdown => {
+                let starts_word = !out.chars().next_back().is_some_and(char::is_alphanumeric);
+                if matches!(ch, '\\' | '*' | '`' | '[' | ']' | '<') || (ch == '_' && starts_word) {
+                    out.push('\\');
+                }
+                out.push(ch);
+            }
+            // Backslashes before lone marks show up in AsciiDoc and Org has no escapes, so
+            // text is taken as is.
+            Style::Text | Style::Asciidoc | Style::Org => out.push(ch),
+            Style::Latex => match ch {
+                '\\' => out.push_str("\\textbackslash{}"),
+                '^' => out.push_str("\\textasciicircum{}"),
+                '~' => out.push_str("\\textasciitilde{}"),
+                '{' | '}' | '$' | '&' | '#' | '%' | '_' => {
+                    out.push('\\');
+                    out.push(ch);
+                }
+                _ => out.push(ch),
+            },
+        }
+    }
+}
+
+pub fn escape(text: &str, style: Style) -> String {
+    let mut escaped = String::new();
+    push_text(&mut escaped, text, style);
+    escaped.trim().to_string()
+}
+
+/// The TeX source a `<math>` element was made from, kept as its annotation by KaTeX and
+/// `--math`.
+fn tex_annotation(math: ElementRef) -> Option<String> {
+    math.descendants()
+        .filter_map(ElementRef::wrap)
+        .find(|element| {
+            element.value().name() == "annotation"
+                && element.value().attr("encoding") == Some("application/x-tex")
+        })
+        .map(|annotation| annotation.text().collect::<String>().trim().to_string())
+        .filter(|tex| !tex.is_empty())
+}
+
+/// Escapes the characters `\href` does not take literally in its URL.
+fn escape_latex_url(url: &str) -> String {
+    let mut escaped = String::new();
+    for ch in url.chars() {
+        if matches!(ch, '\\' | '#' | '%' | '{' | '}') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+fn prefix_lines(text: &str, prefix: &str, empty_prefix: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                empty_prefix.to_string()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn longest_run(text: &str, ch: char) -> usize {
+    text.split(|c| c != ch).map(str::len).max().unwrap_or(0)
+}
@@ -0,0 +1,48 @@
+use super::chapter_path;
+use super::document::Document;
+use super::markdown::{self, Style};
+use std::path::PathBuf;
+
+/// Lays the chapters out as an mdBook project: `book.toml`, `src/SUMMARY.md` following
+/// the crawl order, and one Markdown file per chapter, in a directory per section of its
+/// breadcrumb trail. Returns the files to write,
+/// relative to the project directory.
+pub fn render(document: &Document) -> Result<Vec<(PathBuf, Vec<u8>)>, String> {
+    let mut book = toml::Table::new();
+    book.insert("title".to_string(), document.title().into());
+    book.insert("language".to_string(), document.lang.into());
+    book.insert("src".to_string(), "src".into());
+    let mut manifest = toml::Table::new();
+    manifest.insert("book".to_string(), book.into());
+    let book_toml =
+        toml::to_string(&manifest).map_err(|e| format!("Failed to write book.toml: {}", e))?;
+
+    let mut files = vec![(PathBuf::from("book.toml"), book_toml.into_bytes())];
+    let mut summary = String::from("# Summary\n\n");
+    // Nesting follows the hierarchy, but never skips a level when a parent page failed.
+    let mut previous_depth = None;
+    for chapter in &document.chapters {
+        let page = chapter.page;
+        let file_name = chapter_path(chapter, "md");
+        let name = chapter.title.as_deref().unwrap_or(&file_name);
+        let depth = match previous_depth {
+            Some(previous) => chapter.depth.min(previous + 1),
+            None => 0,
+        };
+        previous_depth = Some(depth);
+        summary.push_str(&format!(
+            "{}- [{}]({})\n",
+            "  ".repeat(depth),
+            name.replace('[', "\\[").replace(']', "\\]"),
+            file_name
+        ));
+
+        let chapter = markdown::convert(&page.html, &page.url, Style::Markdown);
+        files.push((
+            PathBuf::from("src").join(file_name),
+            format!("{}\n", chapter).into_bytes(),
+        ));
+    }
+    files.push((PathBuf::from("src/SUMMARY.md"), summary.into_bytes()));
+    Ok(files)
+}
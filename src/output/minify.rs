@@ -0,0 +1,160 @@
+use crate::css;
+
+/// Elements around which whitespace never shows, so it can be dropped entirely.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "!doctype",
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "br",
+    "caption",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "li",
+    "link",
+    "main",
+    "meta",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "script",
+    "section",
+    "style",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "ul",
+];
+
+/// Elements whose content is copied as it is, or minified as CSS for `<style>`.
+const RAW_ELEMENTS: &[&str] = &["pre", "textarea", "script", "style"];
+
+/// Shrinks an HTML document for `--minify`: drops comments, collapses runs of whitespace
+/// to one space and removes it next to block elements, and minifies `<style>` elements.
+/// The content of `<pre>`, `<textarea>` and `<script>` is kept as it is.
+pub fn html(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut rest = source;
+    // Whitespace was skipped since the last thing written.
+    let mut space = false;
+    // The last thing written was a block element's tag, or nothing.
+    let mut after_block = true;
+
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        if is_tag_start(rest) {
+            let tag = &rest[..tag_end(rest)];
+            rest = &rest[tag.len()..];
+            let name = tag_name(tag);
+            let block = BLOCK_ELEMENTS.contains(&name.as_str());
+            if space && !after_block && !block {
+                out.push(' ');
+            }
+            space = false;
+            after_block = block;
+            out.push_str(tag);
+
+            if !tag.starts_with("</") && RAW_ELEMENTS.contains(&name.as_str()) {
+                let content = &rest[..closing_tag(rest, &name)];
+                rest = &rest[content.len()..];
+                if name == "style" {
+                    out.push_str(&css::minify(content));
+                } else {
+                    out.push_str(content);
+                }
+            }
+            continue;
+        }
+
+        let end = rest[1..].find('<').map_or(rest.len(), |index| index + 1);
+        for c in rest[..end].chars() {
+            if c.is_whitespace() {
+                space = true;
+                continue;
+            }
+            if space && !after_block {
+                out.push(' ');
+            }
+            space = false;
+            after_block = false;
+            out.push(c);
+        }
+        rest = &rest[end..];
+    }
+    out
+}
+
+/// Whether `text` starts with a tag, rather than a `<` in running text.
+fn is_tag_start(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next() == Some('<')
+        && chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '/' || c == '!')
+}
+
+/// The length of the tag `text` starts with, up to the `>` that is not inside a quoted
+/// attribute value.
+fn tag_end(text: &str) -> usize {
+    let mut quote = None;
+    for (index, c) in text.char_indices().skip(1) {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return index + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// The lowercase element name of a start or end tag, e.g. `pre` for `</PRE>`.
+fn tag_name(tag: &str) -> String {
+    tag.trim_start_matches('<')
+        .trim_start_matches('/')
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '!')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Where the end tag of the raw element `name` starts in `text`, matched without regard
+/// to case.
+fn closing_tag(text: &str, name: &str) -> usize {
+    text.match_indices("</")
+        .map(|(index, _)| index)
+        .find(|&index| {
+            text.get(index + 2..index + 2 + name.len())
+                .is_some_and(|candidate| candidate.eq_ignore_ascii_case(name))
+        })
+        .unwrap_or(text.len())
+}
@@ -0,0 +1,152 @@
+use super::document::Document;
+use super::html;
+use super::suffixed_path;
+use crate::assets::Assets;
+use crate::config::Config;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use url::Url;
+
+/// Writes every chapter as its own HTML page, laid out like the site's URL paths
+/// (`/book/ch01.html` becomes `book/ch01.html`), for browsing offline.
+///
+/// Links between scraped pages become relative links between the files; other links
+/// point back to the site. Pages from several hosts are put under a directory per host.
+/// Returns the files to write, relative to the mirror directory.
+pub fn render(
+    config: &Config,
+    document: &Document,
+    assets: &Assets,
+) -> Result<Vec<(PathBuf, Vec<u8>)>, String> {
+    let hosts = document
+        .pages()
+        .iter()
+        .map(|page| page.url.host_str().unwrap_or_default())
+        .collect::<HashSet<_>>();
+    let mut used = HashSet::new();
+    let mut paths: HashMap<&Url, PathBuf> = HashMap::new();
+    for page in document.pages() {
+        let mut path = local_path(&page.url, hosts.len() > 1);
+        let mut counter = 1;
+        while !used.insert(path.clone()) {
+            counter += 1;
+            path = suffixed_path(
+                &local_path(&page.url, hosts.len() > 1),
+                &format!("-{}", counter),
+            );
+        }
+        paths.insert(&page.url, path);
+    }
+
+    let data_uri = |url: &Url| assets.data_uri(url);
+    let mut files = Vec::new();
+    for chapter in &document.chapters {
+        let path = &paths[&chapter.page.url];
+        let link = |url: &Url| {
+            let mut target = url.clone();
+            target.set_fragment(None);
+            let relative = relative_path(path, paths.get(&target)?);
+            Some(match url.fragment() {
+                Some(fragment) => format!("{}#{}", relative, fragment),
+                None => relative,
+            })
+        };
+        let page = html::render(
+            config,
+            &document.single(chapter),
+            config.self_contained.then_some(&data_uri as _),
+            Some(&link),
+            None,
+        )?;
+        files.push((path.clone(), page.into_bytes()));
+    }
+    Ok(files)
+}
+
+/// The file a page is saved to: its URL path with each segment reduced to safe characters,
+/// `index.html` for directories and `.html` added where the path has no HTML extension.
+fn local_path(url: &Url, with_host: bool) -> PathBuf {
+    let mut path = PathBuf::new();
+    if with_host {
+        path.push(safe_name(url.host_str().unwrap_or("site")));
+    }
+    let segments = url
+        .path_segments()
+        .map(|segments| segments.collect::<Vec<_>>())
+        .unwrap_or_default();
+    let (directories, file) = match segments.split_last() {
+        Some((file, directories)) => (directories, *file),
+        None => (&[][..], ""),
+    };
+    for directory in directories.iter().filter(|segment| !segment.is_empty()) {
+        path.push(safe_name(directory));
+    }
+    let file = safe_name(file);
+    if file.is_empty() {
+        path.push("index.html");
+    } else if file.ends_with(".html") || file.ends_with(".htm") {
+        path.push(file);
+    } else {
+        path.push(format!("{}.html", file));
+    }
+    path
+}
+
+/// A decoded URL path segment with anything but letters, digits, `.`, `-` and `_` replaced,
+/// so it is a valid file name that needs no escaping in links.
+pub fn safe_name(segment: &str) -> String {
+    let decoded = percent_decode(segment);
+    let name = decoded
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    // "." and ".." would leave the directory.
+    if name.chars().all(|c| c == '.') {
+        name.replace('.', "_")
+    } else {
+        name
+    }
+}
+
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%'
+            && let Some(byte) = segment
+                .get(index + 1..index + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The link from the file `from` to the file `to`, both relative to the mirror directory.
+fn relative_path(from: &Path, to: &Path) -> String {
+    let from = from
+        .parent()
+        .unwrap_or(Path::new(""))
+        .components()
+        .collect::<Vec<_>>();
+    let to = to.components().collect::<Vec<_>>();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts = vec![".."; from.len() - common];
+    parts.extend(to[common..].iter().filter_map(|component| match component {
+        Component::Normal(name) => name.to_str(),
+        _ => None,
+    }));
+    parts.join("/")
+}
@@ -0,0 +1,790 @@
+mod alt_text;
+mod anchors;
+mod asciidoc;
+mod attachments;
+mod attribution;
+mod bundle;
+mod chunks;
+mod cover;
+mod document;
+mod epub;
+mod html;
+mod images;
+mod jsonl;
+mod latex;
+mod link_notes;
+mod manifest;
+mod markdown;
+mod mdbook;
+mod minify;
+mod mirror;
+mod org;
+mod pandoc;
+mod pdf;
+mod search_box;
+mod site_css;
+mod sqlite;
+mod stream;
+mod table;
+mod term_index;
+mod volumes;
+mod warc;
+mod xhtml;
+
+pub use document::Document;
+pub use html::validate_template;
+pub use markdown::code_language;
+pub use stream::StreamWriter;
+
+use crate::assets::{Asset, Assets, Required};
+use crate::config::{Compression, Config, DEFAULT_OUTPUT, OutputFormat, OutputSuffix};
+use crate::crawl::{Failure, Page};
+use crate::log::info;
+use crate::search;
+use crate::title::slugify;
+use anchors::Anchors;
+use attachments::Attachments;
+use attribution::Attribution;
+use chrono::{DateTime, Local, Utc};
+use clap::ValueEnum;
+use cover::Cover;
+use document::Chapter;
+use images::LocalImages;
+use markdown::Style;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufWriter, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use term_index::TermIndex;
+use url::Url;
+use xhtml::Resource;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+/// Reading speed [`reading_minutes`] assumes.
+const WORDS_PER_MINUTE: usize = 200;
+
+/// Start time of the run, shared by every file written with `--output-suffix timestamp`.
+static RUN_TIMESTAMP: LazyLock<String> =
+    LazyLock::new(|| Local::now().format("%Y%m%d-%H%M%S").to_string());
+
+/// Fails early if a configured output path is already taken, before time is spent crawling.
+pub fn check_output_path(config: &Config) -> Result<(), String> {
+    match &config.output {
+        Some(output) if !config.separate_outputs => {
+            for &format in &config.formats {
+                final_output_path(config, format, output_path(config, format, output, true))?;
+            }
+            if config.manifest {
+                manifest_path(config, output)?;
+            }
+            if config.alt_text_report {
+                sidecar_path(config, output, "alt-text.json")?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Which files referenced by the pages must be downloaded to write the configured outputs.
+pub fn required_assets(config: &Config) -> Option<Required> {
+    if config.self_contained || config.formats.contains(&OutputFormat::Zip) {
+        Some(Required::Resources)
+    } else if config.formats.contains(&OutputFormat::Epub)
+        || config.formats.contains(&OutputFormat::Latex)
+        || (config.localize_images && config.formats.contains(&OutputFormat::Html))
+        || config.inline_svg
+    {
+        Some(Required::Images)
+    } else {
+        None
+    }
+}
+
+/// Writes the crawled pages in every configured format, to one combined output or one
+/// output per start URL, each with its manifest when `--manifest` is set and its report
+/// of images without alt text when `--alt-text-report` is.
+pub fn write_all(
+    config: &Config,
+    pages: &[Page],
+    failures: &[Failure],
+    assets: &Assets,
+) -> Result<(), String> {
+    if config.separate_outputs {
+        let mut used_paths = HashSet::new();
+        for seed in 0..config.start_urls.len() {
+            let seed_pages = pages
+                .iter()
+                .filter(|page| page.seed == seed)
+                .collect::<Vec<_>>();
+            let document = book(config, &seed_pages, assets);
+            let path = base_path(config, &document, seed, &mut used_paths);
+            let outputs = write_formats(config, &document, assets, &path, config.output.is_some())?;
+            let failures = failures
+                .iter()
+                .filter(|failure| failure.seed == seed)
+                .collect::<Vec<_>>();
+            write_manifest(config, &document, &path, &outputs, &failures)?;
+            write_alt_text_report(config, &document, &path)?;
+        }
+    } else {
+        let document = book(config, &pages.iter().collect::<Vec<_>>(), assets);
+        let path = base_path(config, &document, 0, &mut HashSet::new());
+        let outputs = write_formats(config, &document, assets, &path, config.output.is_some())?;
+        let failures = failures.iter().collect::<Vec<_>>();
+        write_manifest(config, &document, &path, &outputs, &failures)?;
+        write_alt_text_report(config, &document, &path)?;
+    }
+    Ok(())
+}
+
+/// The book made of `pages`, with its `--number-chapters`, `--cover`, `--site-css`,
+/// `--favicon`, `--cover-image`, `--attribution` and `--term-index`.
+fn book<'a>(config: &Config, pages: &[&'a Page], assets: &Assets) -> Document<'a> {
+    let mut document = Document::new(pages);
+    if config.number_chapters {
+        document.number_chapters();
+    }
+    if config.cover {
+        document.cover = Some(Cover::new(config, &document));
+    }
+    if config.site_css {
+        document.site_css = Some(site_css::render(&document, assets));
+    }
+    let icon = pages
+        .iter()
+        .filter(|_| config.favicon)
+        .filter_map(|page| page.icon.as_ref())
+        .find(|url| assets.get(url).is_some());
+    let cover_image = config
+        .cover_image
+        .as_ref()
+        .filter(|url| assets.get(url).is_some());
+    document.icon = icon
+        .or(cover_image)
+        .and_then(|url| assets.get(url))
+        .map(Asset::data_uri);
+    document.cover_image = cover_image.or(icon).cloned();
+    if config.attribution {
+        document.attribution = Some(Attribution::new(config, &document));
+    }
+    if config.term_index {
+        document.term_index = Some(TermIndex::new(&document));
+    }
+    document
+}
+
+/// Writes the `--manifest` of the outputs of `document`, if one was asked for.
+fn write_manifest(
+    config: &Config,
+    document: &Document,
+    path: &Path,
+    outputs: &[PathBuf],
+    failures: &[&Failure],
+) -> Result<(), String> {
+    if !config.manifest {
+        return Ok(());
+    }
+    let entries = document
+        .pages()
+        .into_iter()
+        .map(|page| manifest::entry(config, page))
+        .collect::<Vec<_>>();
+    manifest::write(
+        config,
+        &manifest_path(config, path)?,
+        outputs,
+        &entries,
+        failures,
+    )
+}
+
+/// Writes the `--alt-text-report` of `document`, if one was asked for.
+fn write_alt_text_report(config: &Config, document: &Document, path: &Path) -> Result<(), String> {
+    if !config.alt_text_report {
+        return Ok(());
+    }
+    alt_text::write(document, &sidecar_path(config, path, "alt-text.json")?)
+}
+
+/// The name the outputs of `document` are derived from: `--output`, numbered per start URL
+/// with `--separate-outputs`, or the site title. `used` holds the names already taken by
+/// other start URLs.
+fn base_path(
+    config: &Config,
+    document: &Document,
+    seed: usize,
+    used: &mut HashSet<PathBuf>,
+) -> PathBuf {
+    let mut path = match &config.output {
+        Some(output) if config.separate_outputs => suffixed_path(output, &format!("-{}", seed + 1)),
+        Some(output) => output.clone(),
+        None => derived_output_path(document),
+    };
+    if config.separate_outputs && !used.insert(path.clone()) {
+        path = suffixed_path(&path, &format!("-{}", seed + 1));
+    }
+    path
+}
+
+/// Writes `document` once per configured format, naming each output after `path`.
+/// Returns the paths written.
+fn write_formats(
+    config: &Config,
+    document: &Document,
+    assets: &Assets,
+    path: &Path,
+    explicit: bool,
+) -> Result<Vec<PathBuf>, String> {
+    let mut outputs = Vec::new();
+    for &format in &config.formats {
+        let path = final_output_path(config, format, output_path(config, format, path, explicit))?;
+        write_output(config, format, document, assets, &path)?;
+        outputs.push(path);
+    }
+    Ok(outputs)
+}
+
+fn write_output(
+    config: &Config,
+    format: OutputFormat,
+    document: &Document,
+    assets: &Assets,
+    path: &Path,
+) -> Result<(), String> {
+    if format == OutputFormat::Tantivy {
+        search::build_index(document, path, config.force)?;
+        info!(
+            "Successfully saved a search index of {} pages to {}",
+            document.chapters.len(),
+            path.display()
+        );
+        return Ok(());
+    }
+    if format == OutputFormat::Sqlite {
+        sqlite::write(document, &config.start_urls, path)?;
+        info!(
+            "Successfully saved {} pages to {}",
+            document.chapters.len(),
+            path.display()
+        );
+        return Ok(());
+    }
+
+    let files = if format == OutputFormat::Mdbook {
+        mdbook::render(document)?
+    } else if format == OutputFormat::Asciidoc {
+        asciidoc::render(document)
+    } else if format == OutputFormat::Latex {
+        latex::render(document, assets, config.link_footnotes)
+    } else if format == OutputFormat::Mirror {
+        mirror::render(config, document, assets)?
+    } else if format == OutputFormat::Html && splits_volumes(config) {
+        volumes::render(config, document, assets)?
+    } else if format == OutputFormat::Html && config.split_chapters {
+        let images = config
+            .localize_images
+            .then(|| LocalImages::new(config, document, assets, "images"));
+        let data_uri = |url: &Url| assets.data_uri(url);
+        let local = |url: &Url| images.as_ref()?.resource(url);
+        let resource: Option<Resource> = if config.self_contained {
+            Some(&data_uri)
+        } else if images.is_some() {
+            Some(&local)
+        } else {
+            None
+        };
+        // Links to other chapters point at their files.
+        let anchors = Anchors::new(document);
+        let attachments = Attachments::new(config, document, assets);
+        let link = |url: &Url| {
+            anchors
+                .link_in(url, |position| {
+                    chapter_file_name(&document.chapters[position], extension(format))
+                })
+                .or_else(|| attachments.link(url))
+        };
+        let mut files = document
+            .chapters
+            .iter()
+            .map(|chapter| {
+                let file_name = chapter_file_name(chapter, extension(format));
+                let html = html::render(
+                    config,
+                    &document.single(chapter),
+                    resource,
+                    Some(&link),
+                    None,
+                )?;
+                Ok((PathBuf::from(file_name), html.into_bytes()))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        files.extend(images.iter().flat_map(LocalImages::files));
+        files.extend(attachments.files());
+        files
+    } else if config.split_chapters {
+        let attachments = Attachments::new(config, document, assets);
+        let mut files = document
+            .chapters
+            .iter()
+            .map(|chapter| {
+                let file_name = chapter_file_name(chapter, extension(format));
+                let mut contents = render(
+                    config,
+                    format,
+                    &document.single(chapter),
+                    assets,
+                    &attachments,
+                )?;
+                if format == OutputFormat::Markdown {
+                    let fetched_at =
+                        (!config.reproducible).then_some(&chapter.page.fetch.fetched_at);
+                    let front_matter = markdown::front_matter(chapter, fetched_at);
+                    contents.splice(0..0, front_matter.into_bytes());
+                }
+                Ok((PathBuf::from(file_name), contents))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if Attachments::linked_from(format) {
+            files.extend(attachments.files());
+        }
+        files
+    } else if format == OutputFormat::Html && config.localize_images {
+        let images = LocalImages::new(config, document, assets, &images_directory(path));
+        let anchors = Anchors::new(document);
+        let attachments = Attachments::new(config, document, assets);
+        let html = html::render(
+            config,
+            document,
+            Some(&|url| images.resource(url)),
+            Some(&|url| anchors.link(url).or_else(|| attachments.link(url))),
+            None,
+        )?;
+        let mut file = OutputFile::create(path, compression(config, format))?;
+        file.write(html.as_bytes())?;
+        file.finish()?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        write_files(directory, images.files())?;
+        write_files(directory, attachments.files())?;
+        info!("Successfully saved content to {}", path.display());
+        return Ok(());
+    } else {
+        let attachments = Attachments::new(config, document, assets);
+        // Rendered before the file is created, so a failed render leaves no empty file.
+        let contents = render(config, format, document, assets, &attachments)?;
+        let mut file = OutputFile::create(path, compression(config, format))?;
+        file.write(&contents)?;
+        file.finish()?;
+        if Attachments::linked_from(format) {
+            write_files(path.parent().unwrap_or(Path::new("")), attachments.files())?;
+        }
+        info!("Successfully saved content to {}", path.display());
+        return Ok(());
+    };
+
+    write_files(path, files)?;
+    info!(
+        "Successfully saved {} chapters to {}",
+        document.chapters.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Writes `files`, given relative to `directory`, creating the directories they are in.
+fn write_files(directory: &Path, files: Vec<(PathBuf, Vec<u8>)>) -> Result<(), String> {
+    for (file, contents) in files {
+        let file = directory.join(file);
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        write_file(&file, &contents)?;
+    }
+    Ok(())
+}
+
+/// The directory next to the single HTML file at `path` that `--localize-images` saves
+/// its images in, e.g. `book_files` for `book.html` or `book.html.gz`.
+fn images_directory(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("book");
+    let name = name
+        .strip_suffix(".gz")
+        .or_else(|| name.strip_suffix(".zst"))
+        .unwrap_or(name);
+    let stem = Path::new(name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(name);
+    format!("{}_files", stem)
+}
+
+/// Whether the output in `format` is a directory of files rather than a single file.
+fn writes_directory(config: &Config, format: OutputFormat) -> bool {
+    config.split_chapters
+        || (format == OutputFormat::Html && splits_volumes(config))
+        || matches!(
+            format,
+            OutputFormat::Mdbook
+                | OutputFormat::Tantivy
+                | OutputFormat::Mirror
+                | OutputFormat::Latex
+                | OutputFormat::Asciidoc
+        )
+}
+
+/// The `--compress` setting for the output in `format`. Databases are updated in place and
+/// directories would be unusable with every file compressed, so they never are.
+fn compression(config: &Config, format: OutputFormat) -> Option<Compression> {
+    config
+        .compress
+        .filter(|_| format != OutputFormat::Sqlite && !writes_directory(config, format))
+}
+
+/// Whether `--split-size` or `--split-every` splits the HTML output into volumes.
+fn splits_volumes(config: &Config) -> bool {
+    config.split_size.is_some() || config.split_every.is_some()
+}
+
+/// When the output was generated, or `None` with `--reproducible`, which leaves it out.
+fn generated_at(config: &Config) -> Option<DateTime<Utc>> {
+    (!config.reproducible).then(Utc::now)
+}
+
+/// The text of a page without markup, as used for search and exports.
+pub fn plain_text(page: &Page) -> String {
+    markdown::convert(&page.html, &page.url, Style::Text)
+}
+
+/// The number of words in the text of a page, for its reading time. Chinese, Japanese
+/// and Korean are written without spaces, so each of their characters counts as a word.
+pub fn word_count(page: &Page) -> usize {
+    plain_text(page)
+        .split_whitespace()
+        .map(|word| {
+            let ideographs = word.chars().filter(|&c| is_ideograph(c)).count();
+            let rest = word
+                .chars()
+                .any(|c| c.is_alphanumeric() && !is_ideograph(c));
+            ideographs + usize::from(rest)
+        })
+        .sum()
+}
+
+/// Minutes it takes to read `words` words at a typical pace for technical prose, rounded
+/// up, so every chapter with text takes at least a minute.
+pub fn reading_minutes(words: usize) -> usize {
+    words.div_ceil(WORDS_PER_MINUTE)
+}
+
+/// Whether `c` is a Chinese, Japanese or Korean character.
+fn is_ideograph(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // Hiragana and Katakana
+        | '\u{3400}'..='\u{4dbf}' // CJK Unified Ideographs Extension A
+        | '\u{4e00}'..='\u{9fff}' // CJK Unified Ideographs
+        | '\u{ac00}'..='\u{d7af}' // Hangul Syllables
+        | '\u{f900}'..='\u{faff}' // CJK Compatibility Ideographs
+    )
+}
+
+/// File name for a chapter written on its own, e.g. `003-getting_started.md`.
+fn chapter_file_name(chapter: &Chapter, extension: &str) -> String {
+    let slug = chapter
+        .title
+        .as_deref()
+        .map(slugify)
+        .filter(|slug| !slug.is_empty())
+        .unwrap_or_else(|| "chapter".to_string());
+    format!("{:03}-{}.{}", chapter.position + 1, slug, extension)
+}
+
+/// Path for a chapter written on its own inside a directory per section of its
+/// breadcrumb trail, e.g. `user_guide/install/003-getting_started.md`.
+fn chapter_path(chapter: &Chapter, extension: &str) -> String {
+    chapter
+        .sections
+        .iter()
+        .map(|section| slugify(section))
+        .filter(|slug| !slug.is_empty())
+        .chain(std::iter::once(chapter_file_name(chapter, extension)))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Renders `document` as one file in `format`. Links to files downloaded for
+/// `--attachments` point at `attachments` in the formats that can link to them.
+fn render(
+    config: &Config,
+    format: OutputFormat,
+    document: &Document,
+    assets: &Assets,
+    attachments: &Attachments,
+) -> Result<Vec<u8>, String> {
+    let attachment = |url: &Url| attachments.link(url);
+    match format {
+        // The pages of a mirror are HTML; the tree itself is written by `write_output`.
+        OutputFormat::Html | OutputFormat::Mirror => {
+            let resource = |url: &Url| assets.data_uri(url);
+            let anchors = Anchors::new(document);
+            let link = |url: &Url| anchors.link(url).or_else(|| attachments.link(url));
+            Ok(html::render(
+                config,
+                document,
+                config.self_contained.then_some(&resource as _),
+                Some(&link),
+                None,
+            )?
+            .into_bytes())
+        }
+        OutputFormat::Markdown => {
+            Ok(markdown::render(document, Style::Markdown, &attachment).into_bytes())
+        }
+        OutputFormat::Pandoc => Ok(pandoc::render(config, document).into_bytes()),
+        // The source of a LaTeX book; the book with its images is written by `write_output`.
+        OutputFormat::Latex => {
+            Ok(latex::source(document, &|_| None, config.link_footnotes).into_bytes())
+        }
+        // One AsciiDoc document; the book split into chapter files is written by `write_output`.
+        OutputFormat::Asciidoc => Ok(asciidoc::source(document).into_bytes()),
+        OutputFormat::Org => Ok(org::render(document).into_bytes()),
+        OutputFormat::Text => Ok(markdown::render(document, Style::Text, &attachment).into_bytes()),
+        OutputFormat::Jsonl => jsonl::render(config, document),
+        OutputFormat::Chunks => chunks::render(config, document),
+        OutputFormat::Epub => epub::render(config, document, assets),
+        OutputFormat::Pdf => pdf::render(
+            document,
+            assets,
+            config.browser.as_deref(),
+            config.link_footnotes,
+            config.keep_scripts,
+            &config.css.join("\n"),
+        ),
+        // The chapters of an mdBook project are Markdown; the project itself is written by `write_output`.
+        OutputFormat::Mdbook => {
+            Ok(markdown::render(document, Style::Markdown, &|_| None).into_bytes())
+        }
+        OutputFormat::Warc => Ok(warc::render(document, &config.user_agent)),
+        OutputFormat::Zip => bundle::render(config, document, assets),
+        // Databases and indexes are written by `write_output`; this is what they hold per page.
+        OutputFormat::Sqlite | OutputFormat::Tantivy => jsonl::render(config, document),
+    }
+}
+
+fn add_to_zip(
+    zip: &mut ZipWriter<Cursor<Vec<u8>>>,
+    name: &str,
+    contents: &[u8],
+    options: SimpleFileOptions,
+) -> Result<(), String> {
+    zip.start_file(name, options)
+        .and_then(|_| zip.write_all(contents).map_err(Into::into))
+        .map_err(|e| format!("Failed to add {} to the archive: {}", name, e))
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// An output file being written, through a compressing encoder with `--compress`.
+struct OutputFile {
+    path: PathBuf,
+    writer: Encoder,
+}
+
+enum Encoder {
+    Plain(BufWriter<fs::File>),
+    Gzip(flate2::write::GzEncoder<BufWriter<fs::File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<fs::File>>),
+}
+
+impl OutputFile {
+    fn create(path: &Path, compression: Option<Compression>) -> Result<OutputFile, String> {
+        let file = fs::File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let writer = BufWriter::new(file);
+        let writer = match compression {
+            None => Encoder::Plain(writer),
+            Some(Compression::Gzip) => Encoder::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            )),
+            Some(Compression::Zstd) => Encoder::Zstd(
+                zstd::Encoder::new(writer, 0)
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?,
+            ),
+        };
+        Ok(OutputFile {
+            path: path.to_path_buf(),
+            writer,
+        })
+    }
+
+    fn write(&mut self, contents: &[u8]) -> Result<(), String> {
+        match &mut self.writer {
+            Encoder::Plain(writer) => writer.write_all(contents),
+            Encoder::Gzip(encoder) => encoder.write_all(contents),
+            Encoder::Zstd(encoder) => encoder.write_all(contents),
+        }
+        .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+
+    /// Ends the compressed stream and flushes everything to disk.
+    fn finish(self) -> Result<(), String> {
+        match self.writer {
+            Encoder::Plain(writer) => Ok(writer),
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Zstd(encoder) => encoder.finish(),
+        }
+        .and_then(|mut writer| writer.flush())
+        .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+}
+
+fn extension(format: OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Html => "html",
+        OutputFormat::Markdown => "md",
+        OutputFormat::Text => "txt",
+        OutputFormat::Jsonl => "jsonl",
+        OutputFormat::Epub => "epub",
+        OutputFormat::Pdf => "pdf",
+        OutputFormat::Mdbook => "md",
+        OutputFormat::Warc => "warc",
+        OutputFormat::Zip => "zip",
+        OutputFormat::Sqlite => "db",
+        OutputFormat::Tantivy => "index",
+        OutputFormat::Mirror => "html",
+        OutputFormat::Pandoc => "pandoc.md",
+        OutputFormat::Latex => "tex",
+        OutputFormat::Asciidoc => "adoc",
+        OutputFormat::Org => "org",
+        OutputFormat::Chunks => "chunks.jsonl",
+    }
+}
+
+/// Names the output after the site title, e.g. `the_rust_programming_language`; see
+/// [`output_path`] for the extension.
+fn derived_output_path(document: &Document) -> PathBuf {
+    let slug = document
+        .site_title
+        .as_deref()
+        .map(slugify)
+        .filter(|slug| !slug.is_empty());
+    match slug {
+        Some(slug) => PathBuf::from(slug),
+        None => PathBuf::from(DEFAULT_OUTPUT).with_extension(""),
+    }
+}
+
+/// Where the output in `format` goes, given the configured (`explicit`) or derived `path`.
+///
+/// A configured path is used as is when only one format is written. Otherwise files get
+/// the format's extension, and directory outputs, such as `--split-chapters`, get none, or
+/// a `-<format>` suffix when several formats are written.
+fn output_path(config: &Config, format: OutputFormat, path: &Path, explicit: bool) -> PathBuf {
+    let several = config.formats.len() > 1;
+    if explicit && !several {
+        return path.to_path_buf();
+    }
+    let stem = path.with_extension("");
+    if !writes_directory(config, format) {
+        stem.with_extension(extension(format))
+    } else if several {
+        let name = format.to_possible_value().unwrap();
+        suffixed_path(&stem, &format!("-{}", name.get_name()))
+    } else {
+        stem
+    }
+}
+
+/// Applies `--output-suffix` and refuses to replace an existing file unless `--force` is set.
+/// SQLite databases are updated in place, so they may already exist.
+fn final_output_path(
+    config: &Config,
+    format: OutputFormat,
+    path: PathBuf,
+) -> Result<PathBuf, String> {
+    let path = match config.output_suffix {
+        Some(OutputSuffix::Timestamp) => {
+            suffixed_path(&path, &format!("-{}", RUN_TIMESTAMP.as_str()))
+        }
+        None => path,
+    };
+    let compressed = compression(config, format).map(|compression| match compression {
+        Compression::Gzip => "gz",
+        Compression::Zstd => "zst",
+    });
+    // `-o book.html.gz` already names the compressed file.
+    let path = match compressed {
+        Some(extension)
+            if path
+                .extension()
+                .is_none_or(|existing| existing != extension) =>
+        {
+            let mut name = path.into_os_string();
+            name.push(format!(".{}", extension));
+            PathBuf::from(name)
+        }
+        _ => path,
+    };
+    // Search indexes check what they replace themselves.
+    if !matches!(format, OutputFormat::Sqlite | OutputFormat::Tantivy) {
+        check_unused(config, &path)?;
+    }
+    Ok(path)
+}
+
+/// Where `--manifest` writes the manifest of the outputs named after `path`, e.g.
+/// `book.manifest.json` for `book.html`.
+fn manifest_path(config: &Config, path: &Path) -> Result<PathBuf, String> {
+    sidecar_path(config, path, "manifest.json")
+}
+
+/// Where a file written beside the outputs named after `path` goes, with `extension`
+/// replacing theirs.
+fn sidecar_path(config: &Config, path: &Path, extension: &str) -> Result<PathBuf, String> {
+    let path = match config.output_suffix {
+        Some(OutputSuffix::Timestamp) => {
+            suffixed_path(path, &format!("-{}", RUN_TIMESTAMP.as_str()))
+        }
+        None => path.to_path_buf(),
+    }
+    .with_extension(extension);
+    check_unused(config, &path)?;
+    Ok(path)
+}
+
+/// Refuses to replace an existing file unless `--force` is set.
+fn check_unused(config: &Config, path: &Path) -> Result<(), String> {
+    if path.exists() && !config.force {
+        return Err(format!(
+            "Output file {} already exists. Pass --force to overwrite it or --output-suffix timestamp to write a new file.",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Inserts `suffix` before the extension, e.g. `book.html` becomes `book-2.html`.
+fn suffixed_path(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}{}.{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{}{}", stem, suffix),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Escapes text for use in HTML content and attribute values.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
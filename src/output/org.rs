@@ -0,0 +1,58 @@
+use super::document::{Chapter, Document};
+use super::markdown::{self, Style};
+use regex::Regex;
+use std::sync::LazyLock;
+use uuid::Uuid;
+
+/// A top-level heading line.
+static HEADING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^\* .*$").unwrap());
+
+/// Writes the chapters as one Org file, each chapter a top-level heading.
+///
+/// Chapter headings carry a `:PROPERTIES:` drawer with an `:ID:` derived from the page
+/// URL, so it stays the same across runs, and the URL in `:ROAM_REFS:`, which makes every
+/// chapter an org-roam node that can be linked to and found by its source.
+pub fn render(document: &Document) -> String {
+    let chapters = document.chapters.iter().map(convert).collect::<Vec<_>>();
+    format!(
+        "#+TITLE: {}\n#+LANGUAGE: {}\n\n{}\n",
+        document.title(),
+        document.lang,
+        chapters.join("\n\n")
+    )
+}
+
+/// A chapter under its first top-level heading, or its title when the page has no `<h1>`.
+fn convert(chapter: &Chapter) -> String {
+    let url = chapter.page.url.as_str();
+    let body = markdown::convert(&chapter.page.html, &chapter.page.url, Style::Org);
+    let drawer = format!(
+        ":PROPERTIES:\n:ID: {}\n:ROAM_REFS: {}\n:END:",
+        Uuid::new_v5(&Uuid::NAMESPACE_URL, url.as_bytes()),
+        url
+    );
+    match first_heading(&body) {
+        Some(end) => format!("{}\n{}{}", &body[..end], drawer, &body[end..]),
+        None => format!("* {}\n{}\n\n{}", chapter.title_or_url(), drawer, body),
+    }
+}
+
+/// Where the line of the first top-level heading of `body` ends.
+fn first_heading(body: &str) -> Option<usize> {
+    HEADING.find(body).map(|heading| heading.end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heading_after_a_mid_line_star() {
+        let body = "Run a * b first.\n\n* Usage\nText";
+        assert_eq!(
+            first_heading(body),
+            Some("Run a * b first.\n\n* Usage".len())
+        );
+        assert_eq!(first_heading("Only a * b here."), None);
+    }
+}
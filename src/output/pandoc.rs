@@ -0,0 +1,43 @@
+use super::document::Document;
+use super::generated_at;
+use super::markdown::{self, Style};
+use crate::config::Config;
+
+/// Markdown for Pandoc: the chapters as with `--format markdown`, preceded by a YAML
+/// metadata block with the title, language, date and sources, which Pandoc carries into
+/// whatever it converts to, e.g. `pandoc book.pandoc.md -o book.docx`. The date is left out
+/// with `--reproducible`.
+pub fn render(config: &Config, document: &Document) -> String {
+    // JSON strings are valid YAML scalars, so they need no further quoting.
+    let quote = |value: &str| serde_json::to_string(value).unwrap_or_default();
+    let mut metadata = vec![
+        "---".to_string(),
+        format!("title: {}", quote(document.title())),
+        format!("lang: {}", quote(document.lang)),
+    ];
+    if let Some(time) = generated_at(config) {
+        metadata.push(format!(
+            "date: {}",
+            quote(&time.format("%Y-%m-%d").to_string())
+        ));
+    }
+    metadata.extend([
+        format!(
+            "generator: {}",
+            quote(concat!("documentation_scraper/", env!("CARGO_PKG_VERSION")))
+        ),
+        "source:".to_string(),
+    ]);
+    metadata.extend(
+        config
+            .start_urls
+            .iter()
+            .map(|url| format!("  - {}", quote(url.as_str()))),
+    );
+    metadata.push("---".to_string());
+    format!(
+        "{}\n\n{}",
+        metadata.join("\n"),
+        markdown::render(document, Style::Markdown, &|_| None)
+    )
+}
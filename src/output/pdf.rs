@@ -0,0 +1,180 @@
+use super::anchors::Anchors;
+use super::document::Document;
+use super::search_box::chapter_id;
+use super::{escape_html, link_notes, xhtml};
+use crate::assets::Assets;
+use crate::log::debug;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use url::Url;
+
+/// Executable names of Chromium-based browsers, tried in order on PATH.
+const BROWSERS: &[&str] = &[
+    "chromium",
+    "chromium-browser",
+    "google-chrome",
+    "google-chrome-stable",
+    "chrome",
+    "microsoft-edge",
+    "msedge",
+];
+
+/// Install locations checked when no browser is found on PATH.
+const BROWSER_PATHS: &[&str] = &[
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+    "/Applications/Chromium.app/Contents/MacOS/Chromium",
+    r"C:\Program Files\Google\Chrome\Application\chrome.exe",
+    r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe",
+];
+
+/// Print styles: every chapter starts on a new page, code wraps instead of being cut off,
+/// and code listings, figures and table rows are kept on one page where they fit. The
+/// older `page-break-*` properties are given too for engines without `break-*`.
+const PRINT_STYLE: &str = "body { font-family: sans-serif; line-height: 1.5; }
+section.chapter { break-before: page; page-break-before: always; }
+section.chapter:first-child { break-before: auto; page-break-before: auto; }
+section.cover { text-align: center; padding-top: 30vh; }
+section.attribution { break-before: page; }
+section.term-index { break-before: page; }
+section.term-index ul { columns: 2; padding-left: 1em; }
+pre { white-space: pre-wrap; }
+img { max-width: 100%; }
+h1, h2, h3, h4, h5, h6 { break-after: avoid; page-break-after: avoid; }
+pre, figure, img, tr { break-inside: avoid; page-break-inside: avoid; }
+thead { display: table-header-group; }
+p { orphans: 3; widows: 3; }
+div.admonition { border-left: 4px solid #448aff; padding: 0 0.8em; margin: 1em 0; break-inside: avoid; }
+div.admonition.tip { border-color: #00bfa5; }
+div.admonition.important, div.admonition.caution { border-color: #ff9100; }
+div.admonition.warning, div.admonition.danger { border-color: #ff1744; }
+p.admonition-title { font-weight: bold; }
+div.embed { border: 1px dashed #999; padding: 0 0.8em; margin: 1em 0; break-inside: avoid; overflow-wrap: anywhere; }
+sup.link-note a { text-decoration: none; }
+ol.link-notes { font-size: 0.85em; border-top: 1px solid #ccc; padding-top: 0.5em; overflow-wrap: anywhere; }";
+
+/// Prints the combined chapters to PDF with a headless Chromium-based browser.
+///
+/// The browser's default header and footer carry the book title and page numbers, and
+/// the PDF outline (bookmarks) is built from the chapter headings.
+/// Files downloaded for `--self-contained` are embedded instead of loaded from the site,
+/// and with `link_footnotes` each chapter ends with the URLs of its links to other sites.
+/// Scripts are kept with `keep_scripts`. `css` comes after the print styles, for `--css`.
+pub fn render(
+    document: &Document,
+    assets: &Assets,
+    browser: Option<&Path>,
+    link_footnotes: bool,
+    keep_scripts: bool,
+    css: &str,
+) -> Result<Vec<u8>, String> {
+    let browser = match browser {
+        Some(browser) => browser.to_path_buf(),
+        None => find_browser().ok_or(
+            "PDF output needs Chrome, Chromium or Edge. Install one or pass its path with --browser.",
+        )?,
+    };
+
+    let anchors = Anchors::new(document);
+    let sections = document
+        .chapters
+        .iter()
+        .map(|chapter| {
+            let id = chapter_id(chapter.position);
+            let mut html = xhtml::serialize(
+                &chapter.page.html,
+                &chapter.page.url,
+                &|url| assets.data_uri(url),
+                &|url| anchors.link(url),
+                keep_scripts,
+            );
+            if link_footnotes {
+                html = link_notes::add(&html, &id);
+            }
+            format!(
+                "<section class=\"chapter\" id=\"{}\">{}</section>",
+                id, html
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let cover = document
+        .cover
+        .as_ref()
+        .map(|cover| cover.html())
+        .unwrap_or_default();
+    let attribution = document
+        .attribution
+        .as_ref()
+        .map(|attribution| attribution.html())
+        .unwrap_or_default();
+    let term_index = document
+        .term_index
+        .as_ref()
+        .map(|index| index.html(&|url| anchors.link(url)))
+        .unwrap_or_default();
+    let html = format!(
+        "<!DOCTYPE html><html lang=\"{}\"><head><meta charset=\"UTF-8\"><title>{}</title><style>{}{}{}</style></head><body>{}{}{}{}</body></html>",
+        escape_html(document.lang),
+        escape_html(document.title()),
+        PRINT_STYLE,
+        document
+            .site_css
+            .as_deref()
+            .unwrap_or_default()
+            .replace("</", "<\\/"),
+        css.replace("</", "<\\/"),
+        cover,
+        sections,
+        attribution,
+        term_index
+    );
+
+    let work_dir = env::temp_dir().join(format!("documentation_scraper-{}", std::process::id()));
+    fs::create_dir_all(&work_dir)
+        .map_err(|e| format!("Failed to create {}: {}", work_dir.display(), e))?;
+    let result = print(&browser, &work_dir, &html);
+    let _ = fs::remove_dir_all(&work_dir);
+    result
+}
+
+fn print(browser: &Path, work_dir: &Path, html: &str) -> Result<Vec<u8>, String> {
+    let input = work_dir.join("book.html");
+    let output = work_dir.join("book.pdf");
+    fs::write(&input, html).map_err(|e| format!("Failed to write {}: {}", input.display(), e))?;
+    let input_url = Url::from_file_path(&input)
+        .map_err(|_| format!("Cannot open {} in a browser", input.display()))?;
+
+    debug!("Printing PDF with {}", browser.display());
+    let status = Command::new(browser)
+        .arg("--headless")
+        .arg("--disable-gpu")
+        .arg("--generate-pdf-document-outline")
+        .arg(format!("--print-to-pdf={}", output.display()))
+        .arg(format!(
+            "--user-data-dir={}",
+            work_dir.join("profile").display()
+        ))
+        .arg(input_url.as_str())
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", browser.display(), e))?;
+    if !status.status.success() || !output.exists() {
+        return Err(format!(
+            "{} could not print the PDF ({}): {}",
+            browser.display(),
+            status.status,
+            String::from_utf8_lossy(&status.stderr).trim()
+        ));
+    }
+    fs::read(&output).map_err(|e| format!("Failed to read {}: {}", output.display(), e))
+}
+
+fn find_browser() -> Option<PathBuf> {
+    let path = env::var_os("PATH").unwrap_or_default();
+    BROWSERS
+        .iter()
+        .flat_map(|name| env::split_paths(&path).map(move |dir| dir.join(name)))
+        .chain(BROWSER_PATHS.iter().map(PathBuf::from))
+        .find(|candidate| candidate.is_file())
+}
@@ -0,0 +1,80 @@
+use super::document::Document;
+use super::plain_text;
+use serde::Serialize;
+
+/// Filters the embedded index as the reader types; every word must appear in a chapter.
+const SCRIPT: &str = r#"(function () {
+  var index = JSON.parse(document.getElementById("search-index").textContent);
+  index.forEach(function (entry) { entry.l = (entry.t + " " + entry.x).toLowerCase(); });
+  var input = document.getElementById("search-input");
+  var results = document.getElementById("search-results");
+  input.addEventListener("input", function () {
+    var terms = input.value.toLowerCase().split(/\s+/).filter(Boolean);
+    results.innerHTML = "";
+    if (!terms.length) return;
+    index.filter(function (entry) {
+      return terms.every(function (term) { return entry.l.indexOf(term) !== -1; });
+    }).slice(0, 20).forEach(function (entry) {
+      var item = document.createElement("li");
+      var link = document.createElement("a");
+      link.href = entry.u;
+      link.textContent = entry.t;
+      item.appendChild(link);
+      var at = entry.x.toLowerCase().indexOf(terms[0]);
+      if (at !== -1) {
+        var context = document.createElement("div");
+        context.textContent = "…" + entry.x.slice(Math.max(0, at - 40), at + 100) + "…";
+        item.appendChild(context);
+      }
+      results.appendChild(item);
+    });
+  });
+})();"#;
+
+const STYLE: &str = "#search { position: sticky; top: 0; background: #fff; padding: 0.5rem 0; }
+#search-input { width: 100%; padding: 0.4rem; font-size: 1rem; box-sizing: border-box; }
+#search-results { max-height: 50vh; overflow-y: auto; margin: 0; }
+#search-results div { font-size: 0.85em; color: #555; }";
+
+/// One chapter in the embedded index, with short keys to keep it compact.
+#[derive(Serialize)]
+struct Entry<'a> {
+    /// Chapter title.
+    t: &'a str,
+    /// Link to the chapter within the document.
+    u: String,
+    /// Chapter text with whitespace collapsed.
+    x: String,
+}
+
+/// The id of a chapter's `<section>` in the combined document, for linking to it.
+pub fn chapter_id(position: usize) -> String {
+    format!("chapter-{}", position + 1)
+}
+
+/// A search box, the JSON index of every chapter and the script tying them together.
+pub fn render(document: &Document) -> String {
+    let entries = document
+        .chapters
+        .iter()
+        .map(|chapter| Entry {
+            t: chapter.title_or_url(),
+            u: format!("#{}", chapter_id(chapter.position)),
+            x: plain_text(chapter.page)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+        })
+        .collect::<Vec<_>>();
+    // "</" would end the <script> element early.
+    let index = serde_json::to_string(&entries)
+        .unwrap_or_else(|_| "[]".to_string())
+        .replace("</", "<\\/");
+    format!(
+        r#"<style>{STYLE}</style>
+<div id="search"><input id="search-input" type="search" placeholder="Search" aria-label="Search"><ol id="search-results"></ol></div>
+<script type="application/json" id="search-index">{index}</script>
+<script>{SCRIPT}</script>
+"#
+    )
+}
@@ -0,0 +1,305 @@
+use super::document::Document;
+use crate::assets::Assets;
+use crate::css;
+use scraper::{Html, Selector};
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+/// How many levels of `@import` are followed.
+const MAX_IMPORT_DEPTH: usize = 4;
+
+/// What site stylesheet rules are scoped to: the element holding each chapter.
+const SCOPE: &str = "section.chapter";
+
+/// At-rules holding rules of their own, which are pruned like the stylesheet itself.
+const GROUPING_RULES: &[&str] = &["media", "supports", "layer", "container", "document"];
+
+/// The site's stylesheets, for `--site-css`, cut down to the rules that apply to the
+/// scraped content and scoped to the chapters so they leave the rest of the book alone.
+///
+/// A rule is kept when the element it styles occurs in some chapter. Parts of a selector
+/// naming containers outside the content, such as `.rst-content` in
+/// `.rst-content code`, are dropped, unless they depend on attributes or state, like a
+/// `[data-theme=dark]` switch, in which case the whole selector is. Fonts and images the
+/// rules use are embedded as data URIs when they were downloaded.
+pub fn render(document: &Document, assets: &Assets) -> String {
+    let mut pruner = Pruner {
+        assets,
+        fragments: document
+            .pages()
+            .iter()
+            .map(|page| Html::parse_fragment(&page.html))
+            .collect(),
+        matches: HashMap::new(),
+    };
+    let mut seen = HashSet::new();
+    let mut out = String::new();
+    for url in document.pages().iter().flat_map(|page| &page.stylesheets) {
+        if seen.insert(url) {
+            out.push_str(&pruner.stylesheet(url, 0));
+        }
+    }
+    out
+}
+
+struct Pruner<'a> {
+    assets: &'a Assets,
+    /// The chapters, parsed once to match selectors against.
+    fragments: Vec<Html>,
+    /// Whether a compound selector matches an element of some chapter, by its text.
+    matches: HashMap<String, bool>,
+}
+
+impl Pruner<'_> {
+    fn stylesheet(&mut self, url: &Url, depth: usize) -> String {
+        match self.assets.get(url) {
+            Some(asset) if asset.media_type == "text/css" => {
+                let source = css::minify(&String::from_utf8_lossy(&asset.bytes));
+                self.rules(&source, url, depth)
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// The rules of `source` worth keeping, with `@import`s replaced by what they import.
+    fn rules(&mut self, source: &str, base: &Url, depth: usize) -> String {
+        let mut out = String::new();
+        let mut rest = source.trim_start();
+        while !rest.is_empty() {
+            let end = top_level(rest, &['{', ';']).unwrap_or(rest.len());
+            let prelude = rest[..end].trim();
+            if rest[end..].starts_with(';') || end == rest.len() {
+                rest = rest.get(end + 1..).unwrap_or("").trim_start();
+                if let Some(import) = prelude.strip_prefix("@import")
+                    && depth < MAX_IMPORT_DEPTH
+                    && let Some(url) = css::url_references(import)
+                        .first()
+                        .and_then(|reference| base.join(reference).ok())
+                {
+                    out.push_str(&self.stylesheet(&url, depth + 1));
+                }
+                continue;
+            }
+            let close = matching_brace(rest, end);
+            let body = &rest[end + 1..close];
+            rest = rest.get(close + 1..).unwrap_or("").trim_start();
+
+            if let Some(at_rule) = prelude.strip_prefix('@') {
+                let name = at_rule
+                    .split(|c: char| c.is_whitespace() || c == '(')
+                    .next()
+                    .unwrap_or_default();
+                if GROUPING_RULES.contains(&name) {
+                    let inner = self.rules(body, base, depth);
+                    if !inner.is_empty() {
+                        out.push_str(&format!("{}{{{}}}", prelude, inner));
+                    }
+                } else {
+                    out.push_str(&format!("{}{{{}}}", prelude, self.urls(body, base)));
+                }
+            } else if let Some(selectors) = self.selectors(prelude) {
+                out.push_str(&format!("{}{{{}}}", selectors, self.urls(body, base)));
+            }
+        }
+        out
+    }
+
+    /// The selector list scoped to the chapters, without the selectors that match nothing.
+    fn selectors(&mut self, list: &str) -> Option<String> {
+        let kept = split_top_level(list, ',')
+            .into_iter()
+            .filter_map(|selector| self.selector(selector.trim()))
+            .collect::<Vec<_>>();
+        (!kept.is_empty()).then(|| kept.join(","))
+    }
+
+    fn selector(&mut self, selector: &str) -> Option<String> {
+        let parts = compounds(selector);
+        let (_, subject) = parts.last()?;
+        if is_root(subject) {
+            return Some(SCOPE.to_string());
+        }
+        if !self.matches(subject) {
+            return None;
+        }
+        let mut scoped = SCOPE.to_string();
+        // After the scope or a dropped container, the next part is only known to be inside.
+        let mut descendant = true;
+        for (index, (combinator, compound)) in parts.iter().enumerate() {
+            let ancestor = index + 1 < parts.len();
+            if ancestor && (is_root(compound) || !self.matches(compound)) {
+                if !is_root(compound)
+                    && (compound.contains(['[', ':'])
+                        || ["html", "body", ":root"]
+                            .iter()
+                            .any(|root| compound.starts_with(root)))
+                {
+                    return None;
+                }
+                descendant = true;
+                continue;
+            }
+            scoped.push_str(if descendant { " " } else { combinator });
+            scoped.push_str(compound);
+            descendant = false;
+        }
+        Some(scoped)
+    }
+
+    /// Whether the element `compound` describes occurs in some chapter, ignoring
+    /// pseudo-classes and pseudo-elements. Selectors that cannot be checked are kept.
+    fn matches(&mut self, compound: &str) -> bool {
+        if let Some(&matched) = self.matches.get(compound) {
+            return matched;
+        }
+        let element = without_pseudo(compound);
+        let matched = match Selector::parse(if element.is_empty() { "*" } else { &element }) {
+            Ok(selector) => self
+                .fragments
+                .iter()
+                .any(|fragment| fragment.select(&selector).next().is_some()),
+            Err(_) => true,
+        };
+        self.matches.insert(compound.to_string(), matched);
+        matched
+    }
+
+    /// The declarations with their `url()`s embedded, or made absolute when the file was
+    /// not downloaded.
+    fn urls(&self, declarations: &str, base: &Url) -> String {
+        css::rewrite_urls(declarations, |reference| {
+            let url = base.join(reference).ok()?;
+            Some(
+                self.assets
+                    .data_uri(&url)
+                    .unwrap_or_else(|| url.to_string()),
+            )
+        })
+    }
+}
+
+/// Whether the compound selector stands for the whole document, which becomes the scope.
+fn is_root(compound: &str) -> bool {
+    matches!(compound, "html" | "body" | ":root")
+}
+
+/// A selector split into its compound selectors, each with the combinator before it.
+fn compounds(selector: &str) -> Vec<(String, String)> {
+    let mut parts = Vec::new();
+    let mut combinator = String::new();
+    let mut compound = String::new();
+    let mut depth = 0;
+    for c in selector.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 && (c.is_whitespace() || matches!(c, '>' | '+' | '~')) {
+            if !compound.is_empty() {
+                parts.push((
+                    std::mem::take(&mut combinator),
+                    std::mem::take(&mut compound),
+                ));
+            }
+            if !c.is_whitespace() {
+                combinator = c.to_string();
+            } else if combinator.is_empty() {
+                combinator = " ".to_string();
+            }
+            continue;
+        }
+        compound.push(c);
+    }
+    if !compound.is_empty() {
+        parts.push((combinator, compound));
+    }
+    parts
+}
+
+/// The compound selector without its pseudo-classes and pseudo-elements, e.g. `a.x` for
+/// `a.x:hover::after`.
+fn without_pseudo(compound: &str) -> String {
+    let mut out = String::new();
+    let mut chars = compound.chars().peekable();
+    let mut in_attribute = false;
+    while let Some(c) = chars.next() {
+        if in_attribute {
+            in_attribute = c != ']';
+            out.push(c);
+            continue;
+        }
+        match c {
+            ':' => {
+                // Skip to the next simple selector, past any `(...)` argument.
+                let mut depth = 0;
+                while let Some(&next) = chars.peek() {
+                    match next {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        '.' | '#' | '[' if depth == 0 => break,
+                        _ => {}
+                    }
+                    chars.next();
+                }
+            }
+            '[' => {
+                in_attribute = true;
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// The position of the first of `targets` outside strings, parentheses and brackets.
+fn top_level(text: &str, targets: &[char]) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(open), _) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth -= 1,
+            (None, _) if depth == 0 && targets.contains(&c) => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(index) = top_level(rest, &[separator]) {
+        parts.push(&rest[..index]);
+        rest = &rest[index + 1..];
+    }
+    parts.push(rest);
+    parts
+}
+
+/// Where the block opened by the `{` at `open` ends, or the end of the text.
+fn matching_brace(text: &str, open: usize) -> usize {
+    let mut depth = 0;
+    let mut quote = None;
+    for (index, c) in text[open..].char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '{') => depth += 1,
+            (None, '}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return open + index;
+                }
+            }
+            _ => {}
+        }
+    }
+    text.len()
+}
@@ -0,0 +1,105 @@
+use super::document::Document;
+use super::plain_text;
+use chrono::Utc;
+use rusqlite::{Connection, params};
+use std::path::Path;
+use url::Url;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS crawls (
+    id INTEGER PRIMARY KEY,
+    finished_at TEXT NOT NULL,
+    start_urls TEXT NOT NULL,
+    pages INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS pages (
+    url TEXT PRIMARY KEY,
+    crawl_id INTEGER NOT NULL REFERENCES crawls(id),
+    seed INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    order_key TEXT NOT NULL,
+    title TEXT,
+    lang TEXT,
+    html TEXT NOT NULL,
+    text TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    content_type TEXT,
+    bytes INTEGER NOT NULL,
+    elapsed_ms INTEGER NOT NULL,
+    fetched_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS pages_by_order ON pages (seed, order_key);
+";
+
+/// Stores the pages in a SQLite database, creating it if needed.
+///
+/// Pages are keyed by URL, so running the same crawl again updates them in place and
+/// keeps pages that were not reached this time. Each run is recorded in `crawls`, and
+/// every page points at the run that last fetched it.
+pub fn write(document: &Document, start_urls: &[Url], path: &Path) -> Result<(), String> {
+    let error = |e: rusqlite::Error| format!("Failed to write {}: {}", path.display(), e);
+    let mut connection = Connection::open(path).map_err(error)?;
+    connection.execute_batch(SCHEMA).map_err(error)?;
+
+    let transaction = connection.transaction().map_err(error)?;
+    let start_urls = start_urls
+        .iter()
+        .map(Url::as_str)
+        .collect::<Vec<_>>()
+        .join("\n");
+    transaction
+        .execute(
+            "INSERT INTO crawls (finished_at, start_urls, pages) VALUES (?1, ?2, ?3)",
+            params![Utc::now().to_rfc3339(), start_urls, document.chapters.len()],
+        )
+        .map_err(error)?;
+    let crawl_id = transaction.last_insert_rowid();
+
+    {
+        let mut insert = transaction
+            .prepare(
+                "INSERT INTO pages (url, crawl_id, seed, position, order_key, title, lang, html,
+                     text, status, content_type, bytes, elapsed_ms, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+                 ON CONFLICT (url) DO UPDATE SET
+                     crawl_id = excluded.crawl_id, seed = excluded.seed,
+                     position = excluded.position, order_key = excluded.order_key,
+                     title = excluded.title, lang = excluded.lang, html = excluded.html,
+                     text = excluded.text, status = excluded.status,
+                     content_type = excluded.content_type, bytes = excluded.bytes,
+                     elapsed_ms = excluded.elapsed_ms, fetched_at = excluded.fetched_at",
+            )
+            .map_err(error)?;
+        for chapter in &document.chapters {
+            let page = chapter.page;
+            insert
+                .execute(params![
+                    page.url.as_str(),
+                    crawl_id,
+                    page.seed,
+                    chapter.position,
+                    order_key(&page.order),
+                    page.title,
+                    page.lang,
+                    page.html,
+                    plain_text(page),
+                    page.fetch.status,
+                    page.fetch.content_type,
+                    page.fetch.bytes,
+                    page.fetch.elapsed.as_millis() as i64,
+                    page.fetch.fetched_at.to_rfc3339(),
+                ])
+                .map_err(error)?;
+        }
+    }
+    transaction.commit().map_err(error)
+}
+
+/// `Page::order` as text that sorts the same way, e.g. `000002.000001`.
+fn order_key(order: &[usize]) -> String {
+    order
+        .iter()
+        .map(|position| format!("{:06}", position))
+        .collect::<Vec<_>>()
+        .join(".")
+}
@@ -0,0 +1,216 @@
+use super::document::Document;
+use super::markdown::{self, Style};
+use super::{
+    OutputFile, base_path, chunks, compression, final_output_path, jsonl, manifest, manifest_path,
+    output_path,
+};
+use crate::config::{Config, OutputFormat};
+use crate::crawl::{Failure, Page, SortKey, duplicate_keys};
+use crate::log::info;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use url::Url;
+
+/// Writes chapters to disk while the crawl is still running, for `--stream`.
+///
+/// Pages arrive in whatever order they finish downloading. Each one waits only until
+/// every page that sorts before it has been written, so memory holds the pages that
+/// overtook a slower one rather than the whole site.
+pub struct StreamWriter<'a> {
+    config: &'a Config,
+    /// Pages received ahead of one that sorts before them, in reading order.
+    waiting: BTreeMap<SortKey, Vec<Page>>,
+    /// The outputs being written, opened with their first chapter.
+    outputs: Option<Outputs>,
+    /// Outputs already closed, whose `--manifest` waits for the crawl's failures.
+    closed: Vec<Outputs>,
+    used_paths: HashSet<PathBuf>,
+    /// Chapters written to all outputs so far.
+    written: usize,
+    /// The URL and chapter number of the first page with each of [`duplicate_keys`].
+    seen: HashMap<(usize, String), (Url, usize)>,
+}
+
+/// The open output files of one start URL, or of the whole crawl.
+struct Outputs {
+    seed: usize,
+    /// The name the output files are derived from.
+    path: PathBuf,
+    /// Worked out from the first chapter, since the others are not known yet.
+    site_title: Option<String>,
+    files: Vec<(OutputFormat, OutputFile)>,
+    /// The files above once they are closed.
+    written: Vec<PathBuf>,
+    /// Chapters written to these outputs so far.
+    chapters: usize,
+    /// What `--manifest` records about each chapter written.
+    entries: Vec<manifest::Entry>,
+}
+
+impl<'a> StreamWriter<'a> {
+    pub fn new(config: &'a Config) -> StreamWriter<'a> {
+        StreamWriter {
+            config,
+            waiting: BTreeMap::new(),
+            outputs: None,
+            closed: Vec::new(),
+            used_paths: HashSet::new(),
+            written: 0,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Takes a scraped page and writes every waiting page that sorts before
+    /// `first_in_flight`, the first page that may still arrive.
+    pub fn push(&mut self, page: Page, first_in_flight: Option<&SortKey>) -> Result<(), String> {
+        self.waiting
+            .entry((page.seed, page.order.clone()))
+            .or_default()
+            .push(page);
+        while let Some(entry) = self.waiting.first_entry() {
+            if first_in_flight.is_some_and(|first| entry.key() >= first) {
+                break;
+            }
+            for page in entry.remove() {
+                self.write(page)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the pages still waiting, closes the outputs and writes their manifests with
+    /// the crawl's `failures`. Returns the number of chapters written.
+    pub fn finish(mut self, failures: &[Failure]) -> Result<usize, String> {
+        while let Some((_, pages)) = self.waiting.pop_first() {
+            for page in pages {
+                self.write(page)?;
+            }
+        }
+        if let Some(outputs) = self.outputs.take() {
+            self.closed.push(outputs.close()?);
+        }
+        if self.config.manifest {
+            for outputs in &self.closed {
+                let failures = failures
+                    .iter()
+                    .filter(|failure| !self.config.separate_outputs || failure.seed == outputs.seed)
+                    .collect::<Vec<_>>();
+                manifest::write(
+                    self.config,
+                    &manifest_path(self.config, &outputs.path)?,
+                    &outputs.written,
+                    &outputs.entries,
+                    &failures,
+                )?;
+            }
+        }
+        Ok(self.written)
+    }
+
+    fn write(&mut self, page: Page) -> Result<(), String> {
+        let config = self.config;
+        if self
+            .outputs
+            .as_ref()
+            .is_none_or(|outputs| config.separate_outputs && outputs.seed != page.seed)
+        {
+            if let Some(outputs) = self.outputs.take() {
+                self.closed.push(outputs.close()?);
+            }
+            self.outputs = Some(Outputs::open(config, &page, &mut self.used_paths)?);
+        }
+        let outputs = self.outputs.as_mut().unwrap();
+        if !config.keep_duplicates {
+            let keys = duplicate_keys(config, &page);
+            if let Some((original, chapter)) = keys.iter().find_map(|key| self.seen.get(key)) {
+                info!("Skipping {}: duplicate of {}", page.url, original);
+                if let Some(entry) = outputs.entries.get_mut(*chapter) {
+                    entry.add_alias(&page.url);
+                }
+                return Ok(());
+            }
+            for key in keys {
+                self.seen.insert(key, (page.url.clone(), outputs.chapters));
+            }
+        }
+
+        let document = Document::streamed(outputs.site_title.clone(), &page, outputs.chapters);
+        for (format, file) in &mut outputs.files {
+            let contents = match format {
+                OutputFormat::Jsonl => jsonl::render(config, &document)?,
+                OutputFormat::Chunks => chunks::render(config, &document)?,
+                OutputFormat::Markdown | OutputFormat::Text => {
+                    let style = match format {
+                        OutputFormat::Text => Style::Text,
+                        _ => Style::Markdown,
+                    };
+                    let mut text = String::new();
+                    if outputs.chapters > 0 {
+                        text.push_str(&markdown::separator(style));
+                    }
+                    text.push_str(&markdown::convert(&page.html, &page.url, style));
+                    text.into_bytes()
+                }
+                _ => unreachable!("--stream is limited to formats written per chapter"),
+            };
+            file.write(&contents)?;
+        }
+        outputs.chapters += 1;
+        if config.manifest {
+            outputs.entries.push(manifest::entry(config, &page));
+        }
+        self.written += 1;
+        Ok(())
+    }
+}
+
+impl Outputs {
+    /// Opens an output per format, named as [`super::write_all`] would name them.
+    fn open(
+        config: &Config,
+        first: &Page,
+        used_paths: &mut HashSet<PathBuf>,
+    ) -> Result<Outputs, String> {
+        let document = Document::new(&[first]);
+        let path = base_path(config, &document, first.seed, used_paths);
+        let mut files = Vec::new();
+        for &format in &config.formats {
+            let path = final_output_path(
+                config,
+                format,
+                output_path(config, format, &path, config.output.is_some()),
+            )?;
+            files.push((
+                format,
+                OutputFile::create(&path, compression(config, format))?,
+            ));
+        }
+        Ok(Outputs {
+            seed: first.seed,
+            path,
+            site_title: document.site_title,
+            files,
+            written: Vec::new(),
+            chapters: 0,
+            entries: Vec::new(),
+        })
+    }
+
+    /// Finishes every file, keeping the rest for the manifest.
+    fn close(mut self) -> Result<Outputs, String> {
+        for (format, mut file) in std::mem::take(&mut self.files) {
+            if matches!(format, OutputFormat::Markdown | OutputFormat::Text) {
+                file.write(b"\n")?;
+            }
+            let path = file.path.clone();
+            file.finish()?;
+            info!(
+                "Successfully saved {} chapters to {}",
+                self.chapters,
+                path.display()
+            );
+            self.written.push(path);
+        }
+        Ok(self)
+    }
+}
@@ -0,0 +1,252 @@
+use scraper::ElementRef;
+
+/// Elements that make a cell hold more than a line of text.
+const BLOCKS: &[&str] = &[
+    "blockquote",
+    "dl",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ol",
+    "pre",
+    "table",
+    "ul",
+];
+
+/// Most rows or columns a cell is taken to span, so a bogus `colspan` cannot blow the
+/// grid up.
+const MAX_SPAN: usize = 100;
+
+/// A cell, placed on the grid of its table's rows and columns.
+pub struct Cell<'a> {
+    pub element: ElementRef<'a>,
+    pub row: usize,
+    pub column: usize,
+    pub rows: usize,
+    pub columns: usize,
+}
+
+/// The cells of each row of a table, leaving out the rows of tables nested in its cells.
+pub fn rows(table: ElementRef) -> Vec<Vec<ElementRef>> {
+    table
+        .children()
+        .filter_map(ElementRef::wrap)
+        .flat_map(|child| match child.value().name() {
+            "tr" => vec![child],
+            "thead" | "tbody" | "tfoot" => child
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|row| row.value().name() == "tr")
+                .collect(),
+            _ => Vec::new(),
+        })
+        .map(|row| {
+            row.children()
+                .filter_map(ElementRef::wrap)
+                .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+                .collect()
+        })
+        .collect()
+}
+
+/// Whether the table needs more than a GFM table can express: cells spanning several
+/// rows or columns, or holding code blocks, lists, other tables or several paragraphs.
+pub fn is_complex(rows: &[Vec<ElementRef>]) -> bool {
+    rows.iter().flatten().any(|cell| {
+        span(*cell, "rowspan") > 1
+            || span(*cell, "colspan") > 1
+            || cell
+                .descendants()
+                .filter_map(ElementRef::wrap)
+                .any(|element| BLOCKS.contains(&element.value().name()))
+            || cell
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|child| child.value().name() == "p")
+                .count()
+                > 1
+    })
+}
+
+/// Places the cells on the grid as browsers do: each takes the first column of its row
+/// that no cell from a row above spans down into.
+pub fn place<'a>(rows: &[Vec<ElementRef<'a>>]) -> Vec<Cell<'a>> {
+    let mut taken: Vec<Vec<bool>> = vec![Vec::new(); rows.len()];
+    let mut cells = Vec::new();
+    for (row, elements) in rows.iter().enumerate() {
+        let mut column = 0;
+        for element in elements {
+            while taken[row].get(column).copied().unwrap_or(false) {
+                column += 1;
+            }
+            let spanned_rows = span(*element, "rowspan").min(rows.len() - row);
+            let spanned_columns = span(*element, "colspan");
+            for line in &mut taken[row..row + spanned_rows] {
+                if line.len() < column + spanned_columns {
+                    line.resize(column + spanned_columns, false);
+                }
+                line[column..column + spanned_columns].fill(true);
+            }
+            cells.push(Cell {
+                element: *element,
+                row,
+                column,
+                rows: spanned_rows,
+                columns: spanned_columns,
+            });
+            column += spanned_columns;
+        }
+    }
+    cells
+}
+
+/// Draws the cells as a grid table of `+`, `-` and `|`, given the text of each, which
+/// lines up cells spanning several rows or columns and keeps the lines of their text:
+///
+/// ```text
+/// +------+-----+
+/// | Name | Age |
+/// +------+-----+
+/// | Both cells |
+/// +------------+
+/// ```
+pub fn grid(cells: &[Cell], texts: &[String]) -> String {
+    let rows = cells
+        .iter()
+        .map(|cell| cell.row + cell.rows)
+        .max()
+        .unwrap_or(0);
+    let columns = cells
+        .iter()
+        .map(|cell| cell.column + cell.columns)
+        .max()
+        .unwrap_or(0);
+    if rows == 0 || columns == 0 {
+        return String::new();
+    }
+    let texts = texts
+        .iter()
+        .map(|text| text.replace('\t', "    "))
+        .collect::<Vec<_>>();
+    let lines = texts
+        .iter()
+        .map(|text| text.lines().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    // Every slot of the grid gets a cell, so rows with too few cells still have borders.
+    let mut boxes = cells
+        .iter()
+        .map(|cell| (cell.row, cell.column, cell.rows, cell.columns))
+        .collect::<Vec<_>>();
+    let mut owned = vec![vec![false; columns]; rows];
+    for &(row, column, spanned_rows, spanned_columns) in &boxes {
+        for line in &mut owned[row..row + spanned_rows] {
+            line[column..column + spanned_columns].fill(true);
+        }
+    }
+    for (row, line) in owned.iter().enumerate() {
+        for (column, owned) in line.iter().enumerate() {
+            if !owned {
+                boxes.push((row, column, 1, 1));
+            }
+        }
+    }
+
+    // Cells spanning one column or row size it, then spanning cells widen the last
+    // column or row they span if they need more room.
+    let mut widths = vec![1; columns];
+    let mut heights = vec![1; rows];
+    let size = |index: usize| {
+        let text = lines.get(index).map(Vec::as_slice).unwrap_or_default();
+        let width = text.iter().map(|line| line.chars().count()).max();
+        (width.unwrap_or(0), text.len())
+    };
+    for (index, cell) in cells.iter().enumerate() {
+        let (width, height) = size(index);
+        if cell.columns == 1 {
+            widths[cell.column] = widths[cell.column].max(width);
+        }
+        if cell.rows == 1 {
+            heights[cell.row] = heights[cell.row].max(height);
+        }
+    }
+    for (index, cell) in cells.iter().enumerate() {
+        let (width, height) = size(index);
+        let spanned = &widths[cell.column..cell.column + cell.columns];
+        let available = spanned.iter().sum::<usize>() + 3 * (cell.columns - 1);
+        if width > available {
+            widths[cell.column + cell.columns - 1] += width - available;
+        }
+        let spanned = &heights[cell.row..cell.row + cell.rows];
+        let available = spanned.iter().sum::<usize>() + cell.rows - 1;
+        if height > available {
+            heights[cell.row + cell.rows - 1] += height - available;
+        }
+    }
+
+    // The canvas line or character each border of the grid is drawn at.
+    let ys = offsets(&heights, 1);
+    let xs = offsets(&widths, 3);
+    let mut canvas = vec![vec![' '; xs[columns] + 1]; ys[rows] + 1];
+    let edges = |(row, column, spanned_rows, spanned_columns): (usize, usize, usize, usize)| {
+        (
+            ys[row],
+            ys[row + spanned_rows],
+            xs[column],
+            xs[column + spanned_columns],
+        )
+    };
+    for &cell in &boxes {
+        let (top, bottom, left, right) = edges(cell);
+        canvas[top][left..=right].fill('-');
+        canvas[bottom][left..=right].fill('-');
+        for line in &mut canvas[top..=bottom] {
+            line[left] = '|';
+            line[right] = '|';
+        }
+    }
+    // Corners go last, so no edge of a neighbouring cell draws over them.
+    for &cell in &boxes {
+        let (top, bottom, left, right) = edges(cell);
+        for y in [top, bottom] {
+            canvas[y][left] = '+';
+            canvas[y][right] = '+';
+        }
+    }
+    for (index, cell) in cells.iter().enumerate() {
+        for (offset, line) in lines[index].iter().enumerate() {
+            let y = ys[cell.row] + 1 + offset;
+            for (offset, ch) in line.chars().enumerate() {
+                canvas[y][xs[cell.column] + 2 + offset] = ch;
+            }
+        }
+    }
+    canvas
+        .iter()
+        .map(|line| line.iter().collect::<String>().trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// How many rows or columns the cell spans, from its `rowspan` or `colspan`.
+fn span(cell: ElementRef, attribute: &str) -> usize {
+    cell.value()
+        .attr(attribute)
+        .and_then(|span| span.trim().parse::<usize>().ok())
+        .filter(|span| *span > 0)
+        .unwrap_or(1)
+        .min(MAX_SPAN)
+}
+
+/// Where the border before each of `sizes` and after the last falls, with `gap` between
+/// a border and the next size.
+fn offsets(sizes: &[usize], gap: usize) -> Vec<usize> {
+    let mut offsets = vec![0];
+    for size in sizes {
+        offsets.push(offsets.last().unwrap() + size + gap);
+    }
+    offsets
+}
@@ -0,0 +1,198 @@
+use super::document::Document;
+use super::escape_html;
+use super::xhtml::Resource;
+use regex::Regex;
+use scraper::{ElementRef, Html};
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+use url::Url;
+
+/// Inline code that names something, such as `Vec::push`, `serde_json` or `--release`,
+/// rather than showing an expression.
+static IDENTIFIER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^-{0,2}[A-Za-z_][\w:.!<>\[\]&*/-]*(\(\))?$").unwrap());
+
+/// Elements whose text is taken as a term.
+const TERMS: &[&str] = &["dfn", "strong", "em", "code", "h2", "h3", "h4"];
+
+/// Most words a term may have, so emphasized sentences are left out.
+const MAX_WORDS: usize = 6;
+
+/// Longest term kept, in characters.
+const MAX_LENGTH: usize = 60;
+
+/// The back-of-book index `--term-index` ends the book with.
+#[derive(Clone)]
+pub struct TermIndex {
+    /// The terms in alphabetical order.
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone)]
+struct Entry {
+    /// The term as first written.
+    term: String,
+    /// Whether the term is a code identifier, shown in `<code>`.
+    code: bool,
+    /// Where the term appears: the first place in each chapter, as the URL of the page
+    /// and the nearest heading above it, with the title of the chapter.
+    places: Vec<(Url, String)>,
+}
+
+impl TermIndex {
+    /// Collects the terms the chapters define (`<dfn>`) or emphasize, their inline code
+    /// identifiers and their section headings. Terms differing only in case are one entry,
+    /// except code, whose case matters.
+    pub fn new(document: &Document) -> TermIndex {
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut positions: HashMap<(bool, String), usize> = HashMap::new();
+        for chapter in &document.chapters {
+            let page = chapter.page;
+            let title = chapter.title_or_url();
+            let fragment = Html::parse_fragment(&page.html);
+            let mut anchor = page.url.clone();
+            let mut seen = HashSet::new();
+            for element in fragment
+                .root_element()
+                .descendants()
+                .filter_map(ElementRef::wrap)
+            {
+                let name = element.value().name();
+                if matches!(name, "h1" | "h2" | "h3" | "h4" | "h5" | "h6")
+                    && let Some(id) = element.value().attr("id").filter(|id| !id.is_empty())
+                {
+                    anchor.set_fragment(Some(id));
+                }
+                let Some((term, code)) = term(element) else {
+                    continue;
+                };
+                let key = (
+                    code,
+                    if code {
+                        term.clone()
+                    } else {
+                        term.to_lowercase()
+                    },
+                );
+                if !seen.insert(key.clone()) {
+                    continue;
+                }
+                let place = (anchor.clone(), title.to_string());
+                match positions.get(&key) {
+                    Some(&position) => entries[position].places.push(place),
+                    None => {
+                        positions.insert(key, entries.len());
+                        entries.push(Entry {
+                            term,
+                            code,
+                            places: vec![place],
+                        });
+                    }
+                }
+            }
+        }
+        entries.sort_by_cached_key(|entry| (sort_key(&entry.term), entry.term.clone()));
+        TermIndex { entries }
+    }
+
+    /// The index as a `<section>` grouped by initial letter, valid as both HTML and
+    /// XHTML. `link` gives the target of each place, which is left out of the index when
+    /// it returns `None`.
+    pub fn html(&self, link: Resource) -> String {
+        let mut html =
+            String::from("<section class=\"term-index\" id=\"term-index\"><h1>Index</h1>");
+        let mut group = None;
+        for entry in &self.entries {
+            let places = entry
+                .places
+                .iter()
+                .filter_map(|(url, title)| {
+                    Some(format!(
+                        "<a href=\"{}\">{}</a>",
+                        escape_html(&link(url)?),
+                        escape_html(title)
+                    ))
+                })
+                .collect::<Vec<_>>();
+            if places.is_empty() {
+                continue;
+            }
+            let initial = initial(&entry.term);
+            if group != Some(initial) {
+                if group.is_some() {
+                    html.push_str("</ul>");
+                }
+                html.push_str(&format!("<h2>{}</h2><ul>", initial));
+                group = Some(initial);
+            }
+            let term = if entry.code {
+                format!("<code>{}</code>", escape_html(&entry.term))
+            } else {
+                escape_html(&entry.term)
+            };
+            html.push_str(&format!("<li>{}: {}</li>", term, places.join(", ")));
+        }
+        if group.is_some() {
+            html.push_str("</ul>");
+        }
+        html.push_str("</section>");
+        html
+    }
+}
+
+/// The term an element gives, with whether it is code, or `None` if its text is not one:
+/// too long, a label such as "Note:", or code that is not an identifier.
+fn term(element: ElementRef) -> Option<(String, bool)> {
+    let name = element.value().name();
+    if !TERMS.contains(&name) || in_excluded(element) {
+        return None;
+    }
+    let text = element
+        .text()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    // Sphinx and others end headings with a `¶` permalink.
+    let text = text.trim_end_matches(['¶', '#']).trim();
+    if text.chars().count() > MAX_LENGTH || !text.chars().any(char::is_alphabetic) {
+        return None;
+    }
+    if name == "code" {
+        return IDENTIFIER.is_match(text).then(|| (text.to_string(), true));
+    }
+    let label = text.ends_with([':', '.', '!', '?', ',', ';']);
+    (!label && text.split(' ').count() <= MAX_WORDS).then(|| (text.to_string(), false))
+}
+
+/// Whether the element is inside a code block, a heading (for anything other than the
+/// heading itself) or the title of a callout or embed, which say nothing about terms.
+fn in_excluded(element: ElementRef) -> bool {
+    element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(|ancestor| {
+            let value = ancestor.value();
+            matches!(
+                value.name(),
+                "pre" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "nav"
+            ) || value
+                .classes()
+                .any(|class| matches!(class, "admonition-title" | "embed-title"))
+        })
+}
+
+/// What a term is sorted by: its letters and digits in lowercase, so `--release`,
+/// `Release` and `release()` sit together.
+fn sort_key(term: &str) -> String {
+    term.trim_start_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+/// The letter a term is listed under, or `#` for one starting with a digit.
+fn initial(term: &str) -> char {
+    match sort_key(term).chars().next() {
+        Some(c) if c.is_alphabetic() => c.to_uppercase().next().unwrap_or(c),
+        _ => '#',
+    }
+}
@@ -0,0 +1,165 @@
+use super::anchors::Anchors;
+use super::attachments::Attachments;
+use super::document::{Chapter, Document};
+use super::escape_html;
+use super::html::{self, Volume};
+use super::images::LocalImages;
+use super::search_box::chapter_id;
+use super::xhtml::Resource;
+use crate::assets::Assets;
+use crate::config::Config;
+use std::path::PathBuf;
+use url::Url;
+
+/// Splits the combined HTML into volumes of at most `--split-size` bytes of chapter HTML
+/// or `--split-every` chapters, each linked to its neighbours, plus an `index.html` listing
+/// every volume and chapter. A chapter larger than the size limit gets a volume of its own.
+///
+/// Links between scraped pages point into the volume holding the target chapter. With
+/// `--localize-images` the volumes share an `images` directory, and with `--attachments`
+/// an `attachments` one.
+/// Returns the files to write, relative to the output directory.
+pub fn render(
+    config: &Config,
+    document: &Document,
+    assets: &Assets,
+) -> Result<Vec<(PathBuf, Vec<u8>)>, String> {
+    let volumes = split(config, &document.chapters);
+    let file_name = |number: usize| format!("volume-{:02}.html", number);
+
+    let anchors = Anchors::new(document);
+    let attachments = Attachments::new(config, document, assets);
+    // Volumes hold consecutive chapters, so the first one to end at or after a position
+    // holds that chapter.
+    let volume_of = |position: usize| {
+        volumes
+            .iter()
+            .position(|chapters| {
+                chapters
+                    .last()
+                    .is_some_and(|last| last.position >= position)
+            })
+            .map_or(1, |index| index + 1)
+    };
+    let link = |url: &Url| {
+        let whole_chapter = url.fragment().is_none_or(str::is_empty);
+        anchors
+            .link_in(url, |position| {
+                let file = file_name(volume_of(position));
+                // A volume holds several chapters, so a link to a whole one needs its section.
+                if whole_chapter {
+                    format!("{}#{}", file, chapter_id(position))
+                } else {
+                    file
+                }
+            })
+            .or_else(|| attachments.link(url))
+    };
+    let images = LocalImages::new(config, document, assets, "images");
+    let data_uri = |url: &Url| assets.data_uri(url);
+    let local = |url: &Url| images.resource(url);
+    let resource: Option<Resource> = if config.self_contained {
+        Some(&data_uri)
+    } else if config.localize_images {
+        Some(&local)
+    } else {
+        None
+    };
+
+    let mut files = Vec::new();
+    for (index, chapters) in volumes.iter().enumerate() {
+        let number = index + 1;
+        let mut part = document.part(chapters);
+        if number == 1 {
+            part.cover = document.cover.clone();
+        }
+        if number == volumes.len() {
+            part.attribution = document.attribution.clone();
+            part.term_index = document.term_index.clone();
+        }
+        let volume = Volume {
+            number,
+            count: volumes.len(),
+            previous: (number > 1).then(|| file_name(number - 1)),
+            next: (number < volumes.len()).then(|| file_name(number + 1)),
+            index: "index.html".to_string(),
+        };
+        let html = html::render(config, &part, resource, Some(&link), Some(&volume))?;
+        files.push((PathBuf::from(file_name(number)), html.into_bytes()));
+    }
+    files.push((
+        PathBuf::from("index.html"),
+        index_page(document, &volumes, file_name).into_bytes(),
+    ));
+    if config.localize_images {
+        files.extend(images.files());
+    }
+    files.extend(attachments.files());
+    Ok(files)
+}
+
+/// Groups consecutive chapters into volumes, starting a new one whenever the next chapter
+/// would go over either limit.
+fn split<'c, 'a>(config: &Config, chapters: &'c [Chapter<'a>]) -> Vec<&'c [Chapter<'a>]> {
+    let mut volumes = Vec::new();
+    let mut start = 0;
+    let mut size = 0;
+    for (index, chapter) in chapters.iter().enumerate() {
+        let chapter_size = chapter.page.html.len();
+        let full = config
+            .split_every
+            .is_some_and(|every| index - start >= every)
+            || config
+                .split_size
+                .is_some_and(|limit| size + chapter_size > limit);
+        if full && index > start {
+            volumes.push(&chapters[start..index]);
+            start = index;
+            size = 0;
+        }
+        size += chapter_size;
+    }
+    if start < chapters.len() {
+        volumes.push(&chapters[start..]);
+    }
+    volumes
+}
+
+/// The master page: every volume with the chapters it holds.
+fn index_page(
+    document: &Document,
+    volumes: &[&[Chapter]],
+    file_name: impl Fn(usize) -> String,
+) -> String {
+    let sections = volumes
+        .iter()
+        .enumerate()
+        .map(|(index, chapters)| {
+            let entries = chapters
+                .iter()
+                .map(|chapter| {
+                    format!(
+                        "<li><a href=\"{}#{}\">{}</a></li>",
+                        file_name(index + 1),
+                        chapter_id(chapter.position),
+                        escape_html(chapter.title_or_url())
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!(
+                "<h2><a href=\"{}\">Volume {}</a></h2>\n<ol start=\"{}\">\n{}\n</ol>",
+                file_name(index + 1),
+                index + 1,
+                chapters.first().map_or(1, |chapter| chapter.position + 1),
+                entries
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"{lang}\">\n<head>\n<meta charset=\"UTF-8\">\n<title>{title}</title>\n<style>body {{ font-family: sans-serif; line-height: 1.6; max-width: 800px; margin: 2rem auto; padding: 0 1rem; }}</style>\n</head>\n<body>\n<h1>{title}</h1>\n{sections}\n</body>\n</html>\n",
+        lang = escape_html(document.lang),
+        title = escape_html(document.title()),
+    )
+}
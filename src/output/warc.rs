@@ -0,0 +1,126 @@
+use super::document::Document;
+use chrono::{DateTime, Utc};
+use reqwest::header::{CONTENT_LENGTH, TRANSFER_ENCODING};
+use uuid::Uuid;
+
+/// Records the HTTP exchange of every scraped page as a WARC 1.1 file, starting with a
+/// `warcinfo` record, so the crawl can be replayed with standard web-archive tools.
+///
+/// Each page gets a `response` record with the headers and body as received, and a
+/// `request` record reconstructed from what the crawler sends.
+pub fn render(document: &Document, user_agent: &str) -> Vec<u8> {
+    let mut output = Vec::new();
+    let info = format!(
+        "software: documentation_scraper/{}\r\nformat: WARC File Format 1.1\r\nconformsTo: https://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/\r\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    write_record(
+        &mut output,
+        &[
+            ("WARC-Type", "warcinfo".to_string()),
+            ("WARC-Date", warc_date(&Utc::now())),
+            ("WARC-Record-ID", record_id()),
+            ("Content-Type", "application/warc-fields".to_string()),
+        ],
+        info.as_bytes(),
+    );
+
+    for page in document.pages() {
+        let Some(exchange) = &page.fetch.exchange else {
+            continue;
+        };
+        let date = warc_date(&page.fetch.fetched_at);
+        let response_id = record_id();
+
+        let mut response = format!(
+            "{:?} {}\r\n",
+            exchange.version,
+            status_line(page.fetch.status)
+        )
+        .into_bytes();
+        for (name, value) in &exchange.headers {
+            // The body is stored decoded from chunks, with its real length.
+            if name == TRANSFER_ENCODING || name == CONTENT_LENGTH {
+                continue;
+            }
+            response.extend_from_slice(name.as_str().as_bytes());
+            response.extend_from_slice(b": ");
+            response.extend_from_slice(value.as_bytes());
+            response.extend_from_slice(b"\r\n");
+        }
+        response.extend_from_slice(
+            format!("content-length: {}\r\n\r\n", exchange.body.len()).as_bytes(),
+        );
+        response.extend_from_slice(&exchange.body);
+        write_record(
+            &mut output,
+            &[
+                ("WARC-Type", "response".to_string()),
+                ("WARC-Record-ID", response_id.clone()),
+                ("WARC-Date", date.clone()),
+                ("WARC-Target-URI", page.url.to_string()),
+                (
+                    "Content-Type",
+                    "application/http; msgtype=response".to_string(),
+                ),
+            ],
+            &response,
+        );
+
+        let mut target = page.url.path().to_string();
+        if let Some(query) = page.url.query() {
+            target.push('?');
+            target.push_str(query);
+        }
+        let host = match page.url.port() {
+            Some(port) => format!("{}:{}", page.url.host_str().unwrap_or_default(), port),
+            None => page.url.host_str().unwrap_or_default().to_string(),
+        };
+        let request = format!(
+            "GET {} HTTP/1.1\r\nhost: {}\r\nuser-agent: {}\r\naccept: */*\r\n\r\n",
+            target, host, user_agent
+        );
+        write_record(
+            &mut output,
+            &[
+                ("WARC-Type", "request".to_string()),
+                ("WARC-Record-ID", record_id()),
+                ("WARC-Date", date),
+                ("WARC-Target-URI", page.url.to_string()),
+                ("WARC-Concurrent-To", response_id),
+                (
+                    "Content-Type",
+                    "application/http; msgtype=request".to_string(),
+                ),
+            ],
+            request.as_bytes(),
+        );
+    }
+    output
+}
+
+fn write_record(output: &mut Vec<u8>, headers: &[(&str, String)], block: &[u8]) {
+    output.extend_from_slice(b"WARC/1.1\r\n");
+    for (name, value) in headers {
+        output.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+    }
+    output.extend_from_slice(format!("Content-Length: {}\r\n\r\n", block.len()).as_bytes());
+    output.extend_from_slice(block);
+    output.extend_from_slice(b"\r\n\r\n");
+}
+
+fn status_line(status: u16) -> String {
+    let reason = reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or_default();
+    format!("{} {}", status, reason)
+}
+
+fn record_id() -> String {
+    format!("<urn:uuid:{}>", Uuid::new_v4())
+}
+
+fn warc_date(date: &DateTime<Utc>) -> String {
+    date.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
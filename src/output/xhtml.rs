@@ -0,0 +1,172 @@
+use super::escape_html;
+use crate::css;
+use scraper::node::Node;
+use scraper::{ElementRef, Html};
+use url::Url;
+
+/// Elements serialized as `<name/>` because they can have no content.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Attributes that load a resource into the page, by element.
+const RESOURCE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("img", "src"),
+    ("link", "href"),
+    ("source", "src"),
+    ("video", "src"),
+    ("video", "poster"),
+    ("audio", "src"),
+    ("input", "src"),
+];
+
+/// Picks the URL to load an embedded file from, given its absolute URL, or `None` to
+/// keep loading it from the site.
+pub type Resource<'a> = &'a dyn Fn(&Url) -> Option<String>;
+
+/// Re-serializes an HTML fragment as well-formed XHTML, which is also valid HTML.
+///
+/// Links are made absolute so they keep working outside the site, and `resource` picks
+/// the URL to use for each image, stylesheet or other embedded file, given its absolute
+/// URL. `link` likewise picks the target of each hyperlink, or `None` to link to the
+/// site. Scripts are dropped unless `keep_scripts` is set, as with `--keep-scripts`, apart
+/// from MathJax's `math/tex` ones, which hold formulas the crawl keeps.
+pub fn serialize(
+    html: &str,
+    base: &Url,
+    resource: Resource,
+    link: Resource,
+    keep_scripts: bool,
+) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut out = String::new();
+    for child in fragment.root_element().children() {
+        write_node(child, base, resource, link, keep_scripts, &mut out);
+    }
+    out
+}
+
+fn write_node(
+    node: ego_tree::NodeRef<Node>,
+    base: &Url,
+    resource: Resource,
+    link: Resource,
+    keep_scripts: bool,
+    out: &mut String,
+) {
+    let rewrite_css = |text: &str| {
+        css::rewrite_urls(text, |reference| {
+            base.join(reference).ok().and_then(|url| resource(&url))
+        })
+    };
+    match node.value() {
+        Node::Text(text) => {
+            let parent = node.parent().and_then(ElementRef::wrap);
+            let in_style = parent.is_some_and(|parent| parent.value().name() == "style");
+            if let Some(script) = parent.filter(|parent| parent.value().name() == "script") {
+                out.push_str(&script_text(script.value().attr("type"), text));
+            } else if in_style {
+                // `>` is common in CSS and fine unescaped in both HTML and XML, and HTML
+                // would not decode `&gt;` inside a <style> element.
+                out.push_str(&rewrite_css(text).replace('&', "&amp;").replace('<', "&lt;"));
+            } else {
+                out.push_str(&escape_text(text));
+            }
+        }
+        Node::Element(_) => {
+            let Some(element) = ElementRef::wrap(node) else {
+                return;
+            };
+            let name = element.value().name();
+            let formula = element
+                .value()
+                .attr("type")
+                .is_some_and(|kind| kind.starts_with("math/"));
+            if name == "script" && !keep_scripts && !formula {
+                return;
+            }
+
+            out.push('<');
+            out.push_str(name);
+            match name {
+                "svg" => out.push_str(r#" xmlns="http://www.w3.org/2000/svg""#),
+                "math" => out.push_str(r#" xmlns="http://www.w3.org/1998/Math/MathML""#),
+                _ => {}
+            }
+            for (attr, value) in element.value().attrs() {
+                if !is_xml_name(attr) || attr == "xmlns" || attr == "srcset" {
+                    continue;
+                }
+                let value = if RESOURCE_ATTRIBUTES.contains(&(name, attr)) {
+                    match base.join(value) {
+                        Ok(url) => resource(&url).unwrap_or_else(|| url.to_string()),
+                        Err(_) => value.to_string(),
+                    }
+                } else if name == "a" && attr == "href" && !value.starts_with('#') {
+                    match base.join(value) {
+                        Ok(url) => link(&url).unwrap_or_else(|| url.to_string()),
+                        Err(_) => value.to_string(),
+                    }
+                } else if attr == "style" {
+                    rewrite_css(value)
+                } else {
+                    value.to_string()
+                };
+                out.push_str(&format!(" {}=\"{}\"", attr, escape_html(&value)));
+            }
+
+            if VOID_ELEMENTS.contains(&name) {
+                out.push_str("/>");
+                return;
+            }
+            out.push('>');
+            for child in node.children() {
+                write_node(child, base, resource, link, keep_scripts, out);
+            }
+            out.push_str(&format!("</{}>", name));
+        }
+        _ => {}
+    }
+}
+
+/// The text of a script, which HTML reads as is but XML decodes. Text with `<` or `&` is
+/// put in a CDATA section hidden behind the comments of the script's language, so both
+/// read the same; JavaScript gets `//`, and the TeX of MathJax's `math/tex` scripts `%`.
+/// Scripts in other languages, such as JSON, are left as they are.
+fn script_text(kind: Option<&str>, text: &str) -> String {
+    if !text.contains(['<', '&']) || text.contains("]]>") {
+        return text.to_string();
+    }
+    let kind = kind.unwrap_or_default().to_ascii_lowercase();
+    let comment = if kind.is_empty()
+        || kind == "module"
+        || kind.contains("javascript")
+        || kind.contains("ecmascript")
+    {
+        "//"
+    } else if kind.starts_with("math/tex") {
+        "%"
+    } else {
+        return text.to_string();
+    };
+    format!(
+        "{comment}<![CDATA[\n{}\n{comment}]]>",
+        text.trim_matches('\n')
+    )
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Attribute names HTML accepts but XML does not (e.g. `@click`) are dropped.
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
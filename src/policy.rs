@@ -0,0 +1,69 @@
+use std::str::FromStr;
+
+/// When a crawl with failed pages should make the process exit with an error.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FailOn {
+    /// Always exit successfully, as long as an output could be written.
+    #[default]
+    None,
+    /// Fail if any page could not be scraped.
+    Any,
+    /// Fail if more than this fraction (0.0 to 1.0) of the attempted pages failed.
+    Threshold(f64),
+}
+
+impl FailOn {
+    /// Checks the outcome of a crawl, returning an error message when the policy is violated.
+    pub fn check(self, succeeded: usize, failed: usize) -> Result<(), String> {
+        let attempted = succeeded + failed;
+        let violated = match self {
+            FailOn::None => false,
+            FailOn::Any => failed > 0,
+            FailOn::Threshold(limit) => attempted > 0 && failed as f64 / attempted as f64 > limit,
+        };
+        if violated {
+            Err(format!(
+                "{} of {} pages failed to scrape (--fail-on {})",
+                failed, attempted, self
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl FromStr for FailOn {
+    type Err = String;
+
+    /// Parses `any`, `none`, or `threshold:<percent>` such as `threshold:5%`.
+    fn from_str(value: &str) -> Result<FailOn, String> {
+        match value {
+            "any" => Ok(FailOn::Any),
+            "none" => Ok(FailOn::None),
+            _ => {
+                let percent = value
+                    .strip_prefix("threshold:")
+                    .map(|percent| percent.trim_end_matches('%'))
+                    .and_then(|percent| percent.parse::<f64>().ok())
+                    .filter(|percent| (0.0..=100.0).contains(percent))
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid failure policy '{}', expected any, none or threshold:<percent>",
+                            value
+                        )
+                    })?;
+                Ok(FailOn::Threshold(percent / 100.0))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for FailOn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FailOn::None => write!(f, "none"),
+            FailOn::Any => write!(f, "any"),
+            FailOn::Threshold(limit) => write!(f, "threshold:{}%", limit * 100.0),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// Presets for popular documentation generators.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    /// mdBook, as used by The Rust Programming Language.
+    Mdbook,
+    /// Sphinx, including the Read the Docs theme.
+    Sphinx,
+    /// Docusaurus v2 and later.
+    Docusaurus,
+    /// MkDocs with the Material theme.
+    MkdocsMaterial,
+    /// GitBook, both legacy static exports and hosted spaces.
+    Gitbook,
+    /// rustdoc, the API documentation of Rust crates, as hosted on docs.rs.
+    Rustdoc,
+    /// Javadoc, the API documentation of Java libraries, with or without frames.
+    Javadoc,
+    /// Doxygen, with or without its tree view.
+    Doxygen,
+    /// Common selectors that work on many sites; also used when detection fails.
+    Generic,
+}
+
+/// Which pages of a crawl led by a table of contents it is read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TocScope {
+    /// The start page, whose table lists the whole book, as mdBook's sidebar does.
+    StartPage,
+    /// Every page, whose table lists just the pages below it, as a Sphinx `toctree` does.
+    Nested,
+    /// Every page, whose table lists the whole book with only the part around the page
+    /// expanded, as the sidebars of Docusaurus and hosted GitBook spaces do.
+    EveryPage,
+}
+
+/// Selectors for boilerplate found inside the content element of many sites: navigation,
+/// edit buttons, cookie banners and footers.
+const BOILERPLATE: &[&str] = &[
+    "nav",
+    "[role='navigation']",
+    "[role='banner']",
+    "[role='contentinfo']",
+    "footer",
+    ".sidebar",
+    "a.edit-this-page",
+    ".edit-this-page",
+    "a.edit-page-link",
+    "a[title='Edit this page']",
+    ".cookie-banner",
+    ".cookie-consent",
+    "#cookie-banner",
+    "#cookie-consent",
+    "#onetrust-consent-sdk",
+];
+
+impl Profile {
+    /// CSS selectors for the element holding each page's content, tried in order.
+    pub fn content_selectors(self) -> &'static [&'static str] {
+        match self {
+            Profile::Mdbook => &["main"],
+            // The Read the Docs and Alabaster themes, then PyData and Furo.
+            Profile::Sphinx => &[
+                "div[itemprop='articleBody']",
+                "div.body",
+                "article.bd-article",
+                "article[role='main']",
+            ],
+            Profile::Docusaurus => &[
+                ".theme-doc-markdown",
+                "div[itemprop='articleBody']",
+                "article",
+            ],
+            Profile::MkdocsMaterial => &["article.md-content__inner", ".md-content"],
+            Profile::Gitbook => &["section.markdown-section", "main", "article"],
+            Profile::Rustdoc => &["#main-content", "#main"],
+            // Javadoc 8 has no `main`, only the container after the page's header.
+            Profile::Javadoc => &["main", ".contentContainer"],
+            // Without the tree view the title sits outside the contents; every chapter
+            // gets it as its heading anyway.
+            Profile::Doxygen => &["#doc-content", "div.contents"],
+            Profile::Generic => &["main", "article", "[role='main']", "#content", ".content"],
+        }
+    }
+
+    /// CSS selectors for boilerplate to remove from the content: the common ones, plus those
+    /// of the generator's own theme.
+    pub fn boilerplate_selectors(self) -> Vec<&'static str> {
+        let own: &[&str] = match self {
+            Profile::Mdbook => &[".nav-wrapper", ".nav-chapters"],
+            Profile::Sphinx => &[
+                ".sphinxsidebar",
+                "div.related",
+                ".rst-footer-buttons",
+                "div[role='navigation']",
+                "a.headerlink",
+                "span.linenos",
+                "button.copybtn",
+                ".related-pages",
+                ".prev-next-area",
+                ".bd-sidebar-primary",
+                ".bd-sidebar-secondary",
+                ".toc-drawer",
+            ],
+            Profile::Docusaurus => &[
+                ".theme-doc-breadcrumbs",
+                ".theme-doc-toc-mobile",
+                ".theme-doc-footer",
+                ".theme-doc-version-badge",
+                ".theme-doc-version-banner",
+                ".theme-announcement-bar",
+                ".theme-edit-this-page",
+                ".theme-last-updated",
+                ".theme-doc-sidebar-container",
+                ".pagination-nav",
+                "a.hash-link",
+            ],
+            Profile::MkdocsMaterial => &[
+                ".md-content__button",
+                ".md-source-file",
+                "a.headerlink",
+                ".md-feedback",
+                ".md-top",
+                ".md-code__nav",
+                "button.md-clipboard",
+                "[data-md-component='announce']",
+                "[data-md-component='outdated']",
+                "[data-md-component='skip']",
+            ],
+            Profile::Gitbook => &[
+                ".page-footer",
+                ".navigation",
+                ".book-header",
+                ".book-summary",
+                ".gitbook-link",
+                "[data-testid='table-of-contents']",
+            ],
+            // The version and source links beside headings, the anchors and copy buttons,
+            // and the "Expand description" toggles.
+            Profile::Rustdoc => &[
+                ".out-of-band",
+                ".rightside",
+                "a.src",
+                "a.anchor",
+                "a.tooltip",
+                "#copy-path",
+                "rustdoc-toolbar",
+                "summary.hideme",
+            ],
+            // The navigation bars of Javadoc 8 and of later releases, and the copyright.
+            Profile::Javadoc => &[
+                ".topNav",
+                ".subNav",
+                ".bottomNav",
+                ".skipNav",
+                ".top-nav",
+                ".sub-nav",
+                ".skip-nav",
+                ".legalCopy",
+                ".legal-copy",
+                "noscript",
+            ],
+            // The title area, menus, tree view, search box and footer, the title that every
+            // chapter gets as its heading anyway, the permalinks of the members, and the
+            // icons and toggles of the index lists.
+            Profile::Doxygen => &[
+                "#top",
+                "#titlearea",
+                "#main-nav",
+                "div.tabs",
+                "div.tabs2",
+                "div.tabs3",
+                "#side-nav",
+                "#nav-tree",
+                "#nav-path",
+                ".navpath",
+                "#MSearchBox",
+                "#MSearchSelectWindow",
+                "#MSearchResultsWindow",
+                "div.headertitle",
+                "div.header > div.summary",
+                "span.permalink",
+                "span.icona",
+                "span.arrow",
+                "hr.footer",
+                "address.footer",
+            ],
+            Profile::Generic => &[],
+        };
+        BOILERPLATE.iter().chain(own).copied().collect()
+    }
+
+    /// CSS selectors for the table of contents that gives the order and nesting of the
+    /// pages, tried in order; every element the first one matches is part of it. Crawls of
+    /// the other generators follow next links.
+    pub fn toc_selectors(self) -> &'static [&'static str] {
+        match self {
+            Profile::Mdbook => &["#sidebar ol.chapter", ".sidebar ol.chapter", "ol.chapter"],
+            Profile::Sphinx => &["div.toctree-wrapper"],
+            Profile::Docusaurus => &[
+                "ul.theme-doc-sidebar-menu",
+                ".theme-doc-sidebar-container nav.menu",
+            ],
+            // The navigation holds every page, collapsed sections included; the tabs along
+            // the top repeat its first level.
+            Profile::MkdocsMaterial => &["nav.md-nav--primary"],
+            // Static exports list the pages in the book summary, hosted spaces in their
+            // sidebar.
+            Profile::Gitbook => &[
+                ".book-summary ul.summary",
+                "[data-testid='table-of-contents']",
+            ],
+            // The names in the lists of a crate's or module's items, without the links in
+            // their descriptions, in the markup of recent and older releases.
+            Profile::Rustdoc => &[
+                "dl.item-table > dt",
+                ".item-table .item-name",
+                ".item-table .item-left",
+            ],
+            // The names in the lists of the modules, packages and classes of the overview,
+            // module and package pages, in the markup of Javadoc 16 and later, then of
+            // older releases. Class pages have only their members' tables.
+            Profile::Javadoc => &[
+                "#all-modules-table .col-first, #all-packages-table .col-first, \
+                 .packages-summary .col-first, #class-summary .col-first",
+                ".overviewSummary .colFirst, .packagesSummary .colFirst, .typeSummary .colFirst",
+            ],
+            // The lists of the index pages: the related pages, namespaces, classes and files.
+            // The start page's menu, which leads to them, is filled in by a script.
+            Profile::Doxygen => &["table.directory td.entry"],
+            Profile::Generic => &[],
+        }
+    }
+
+    /// Which pages the table of contents is read from.
+    pub fn toc_scope(self) -> TocScope {
+        match self {
+            Profile::Mdbook => TocScope::StartPage,
+            Profile::Sphinx => TocScope::Nested,
+            Profile::Docusaurus => TocScope::EveryPage,
+            Profile::MkdocsMaterial => TocScope::StartPage,
+            Profile::Gitbook => TocScope::EveryPage,
+            Profile::Rustdoc => TocScope::Nested,
+            Profile::Javadoc => TocScope::Nested,
+            Profile::Doxygen => TocScope::Nested,
+            Profile::Generic => TocScope::StartPage,
+        }
+    }
+
+    /// CSS selectors for the link pointing to the next page, tried in order.
+    pub fn next_selectors(self) -> &'static [&'static str] {
+        match self {
+            Profile::Mdbook => &["a[rel~='next']", "a[title='Next chapter']"],
+            Profile::Sphinx => &["link[rel='next']", "a[rel='next']"],
+            Profile::Docusaurus => &[
+                "a.pagination-nav__link--next",
+                ".pagination-nav__item--next a",
+            ],
+            Profile::MkdocsMaterial => &["a.md-footer__link--next", "link[rel='next']"],
+            Profile::Gitbook => &["a.navigation-next", "a[rel~='next']"],
+            Profile::Rustdoc => &[],
+            Profile::Javadoc => &[],
+            Profile::Doxygen => &[],
+            Profile::Generic => &[
+                "link[rel='next']",
+                "a[rel~='next']",
+                "a[title='Next chapter']",
+            ],
+        }
+    }
+}
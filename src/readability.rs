@@ -0,0 +1,104 @@
+use ego_tree::NodeId;
+use scraper::{ElementRef, Html, Selector};
+
+/// Elements whose text counts towards the score of the block holding them.
+const TEXT_BLOCKS: &str = "p, pre, td, li, blockquote, dd";
+
+/// Class and id words suggesting an element holds the main content.
+const POSITIVE: &[&str] = &[
+    "article", "body", "content", "doc", "entry", "main", "markdown", "page", "post", "prose",
+    "text",
+];
+
+/// Class and id words suggesting an element is chrome around the content.
+const NEGATIVE: &[&str] = &[
+    "ad", "banner", "comment", "cookie", "footer", "header", "menu", "nav", "related", "share",
+    "sidebar", "social", "toc",
+];
+
+/// Finds the element most likely to hold the main content of a page no content selector
+/// matched, in the way of Readability: every paragraph adds to the score of its parent,
+/// and half as much to its grandparent, by the amount of text it has. Blocks whose class
+/// or id sounds like content or chrome are weighted up or down, and the score shrinks
+/// with the share of text that sits in links, so menus and link lists lose.
+///
+/// Returns `None` if the page has no text to go by.
+pub fn main_content(document: &Html) -> Option<NodeId> {
+    // Kept in the order the blocks are met, so that of two equal scores the first wins
+    // on every run.
+    let mut scores: Vec<(NodeId, f64)> = Vec::new();
+    let mut add = |element: ElementRef, score: f64| match scores
+        .iter_mut()
+        .find(|(id, _)| *id == element.id())
+    {
+        Some((_, total)) => *total += score,
+        None => scores.push((element.id(), weight(element) + score)),
+    };
+    for block in document.select(&Selector::parse(TEXT_BLOCKS).unwrap()) {
+        let text = block.text().collect::<String>();
+        let text = text.trim();
+        if text.chars().count() < 25 {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (text.len() as f64 / 100.0).min(3.0);
+        let mut ancestors = block.ancestors().filter_map(ElementRef::wrap);
+        if let Some(parent) = ancestors.next() {
+            add(parent, score);
+        }
+        if let Some(grandparent) = ancestors.next() {
+            add(grandparent, score / 2.0);
+        }
+    }
+
+    scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = ElementRef::wrap(document.tree.get(id)?)?;
+            Some((id, score * (1.0 - link_density(element))))
+        })
+        .reduce(|best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        })
+        .map(|(id, _)| id)
+}
+
+/// The starting score of a block, from what its class and id suggest.
+fn weight(element: ElementRef) -> f64 {
+    let value = element.value();
+    let names = value
+        .classes()
+        .chain(value.id())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>();
+    let has = |words: &[&str]| {
+        names
+            .iter()
+            .any(|name| name.split(['-', '_']).any(|part| words.contains(&part)))
+    };
+    let mut weight = 0.0;
+    if has(POSITIVE) || matches!(value.name(), "main" | "article") {
+        weight += 25.0;
+    }
+    if has(NEGATIVE) || matches!(value.name(), "nav" | "aside" | "footer" | "header") {
+        weight -= 25.0;
+    }
+    weight
+}
+
+/// The share of the element's text that is link text.
+fn link_density(element: ElementRef) -> f64 {
+    let length = element.text().map(str::len).sum::<usize>();
+    if length == 0 {
+        return 0.0;
+    }
+    let links = element
+        .select(&Selector::parse("a").unwrap())
+        .flat_map(|link| link.text())
+        .map(str::len)
+        .sum::<usize>();
+    links as f64 / length as f64
+}
@@ -0,0 +1,59 @@
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use std::fmt;
+
+/// Which build of a Read the Docs project a page belongs to, as the site tells the script
+/// behind its flyout menu of versions and languages.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Build {
+    pub project: String,
+    pub version: String,
+    /// Only declared by the older themes.
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+impl fmt::Display for Build {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} '{}'", self.project, self.version)?;
+        if let Some(language) = &self.language {
+            write!(f, " ({})", language)?;
+        }
+        Ok(())
+    }
+}
+
+/// The Read the Docs build of the page, from the `readthedocs-project-slug` and
+/// `readthedocs-version-slug` tags hosted pages carry, or from the `READTHEDOCS_DATA`
+/// object of older themes.
+pub fn build(document: &Html) -> Option<Build> {
+    let meta = |name: &str| {
+        let selector = Selector::parse(&format!("meta[name='{}'][content]", name)).unwrap();
+        document
+            .select(&selector)
+            .next()
+            .and_then(|meta| meta.value().attr("content"))
+            .map(str::to_string)
+            .filter(|content| !content.is_empty())
+    };
+    if let (Some(project), Some(version)) = (
+        meta("readthedocs-project-slug"),
+        meta("readthedocs-version-slug"),
+    ) {
+        return Some(Build {
+            project,
+            version,
+            language: None,
+        });
+    }
+
+    let scripts = Selector::parse("script:not([src])").unwrap();
+    document.select(&scripts).find_map(|script| {
+        let text = script.text().collect::<String>();
+        let data = &text[text.find("READTHEDOCS_DATA")?..];
+        // The object is JSON, followed by more script in some themes.
+        let mut objects =
+            serde_json::Deserializer::from_str(&data[data.find('{')?..]).into_iter::<Build>();
+        objects.next()?.ok()
+    })
+}
@@ -0,0 +1,42 @@
+use ammonia::Builder;
+use std::sync::LazyLock;
+
+/// Tags allowed besides ammonia's defaults, which cover ordinary text markup.
+const TAGS: &[&str] = &[
+    "audio", "main", "picture", "section", "source", "tfoot", "track", "video",
+];
+
+/// Attributes allowed on every tag besides `lang` and `title`. Ids and classes carry the
+/// anchors and code languages the outputs rely on.
+const GENERIC_ATTRIBUTES: &[&str] = &["class", "dir", "id", "role"];
+
+/// Attribute prefixes allowed on every tag.
+const GENERIC_ATTRIBUTE_PREFIXES: &[&str] = &["aria-", "data-"];
+
+/// Attributes allowed on particular tags besides ammonia's defaults.
+const TAG_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("a", &["name"]),
+    ("audio", &["controls", "src"]),
+    ("source", &["src", "type"]),
+    ("track", &["kind", "label", "src", "srclang"]),
+    ("video", &["controls", "height", "poster", "src", "width"]),
+];
+
+static SANITIZER: LazyLock<Builder<'static>> = LazyLock::new(|| {
+    let mut builder = Builder::default();
+    builder
+        .add_tags(TAGS)
+        .add_generic_attributes(GENERIC_ATTRIBUTES)
+        .add_generic_attribute_prefixes(GENERIC_ATTRIBUTE_PREFIXES);
+    for (tag, attributes) in TAG_ATTRIBUTES {
+        builder.add_tag_attributes(tag, *attributes);
+    }
+    builder
+});
+
+/// Cleans a page's content for `--sanitize`, keeping only allowlisted tags and
+/// attributes: scripts, styles, forms, frames, event handlers and `style` attributes are
+/// removed, as are links and images with `javascript:`, `data:` or other unsafe URLs.
+pub fn clean(html: &str) -> String {
+    SANITIZER.clean(html).to_string()
+}
@@ -0,0 +1,134 @@
+use crate::output::{Document, plain_text};
+use std::fs;
+use std::path::Path;
+use tantivy::collector::TopDocs;
+use tantivy::query::QueryParser;
+use tantivy::schema::{FAST, Field, INDEXED, STORED, STRING, Schema, TEXT, Value};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::{Index, IndexWriter, TantivyDocument, doc};
+
+/// Memory the index writer may use while adding documents.
+const WRITER_MEMORY: usize = 50_000_000;
+
+/// Fields of the search index.
+struct Fields {
+    title: Field,
+    body: Field,
+    url: Field,
+    order: Field,
+}
+
+fn schema() -> (Schema, Fields) {
+    let mut builder = Schema::builder();
+    let fields = Fields {
+        title: builder.add_text_field("title", TEXT | STORED),
+        body: builder.add_text_field("body", TEXT | STORED),
+        url: builder.add_text_field("url", STRING | STORED),
+        order: builder.add_u64_field("order", INDEXED | STORED | FAST),
+    };
+    (builder.build(), fields)
+}
+
+fn fields(schema: &Schema) -> Result<Fields, String> {
+    let field = |name| {
+        schema
+            .get_field(name)
+            .map_err(|_| format!("Not a documentation_scraper index: no '{}' field", name))
+    };
+    Ok(Fields {
+        title: field("title")?,
+        body: field("body")?,
+        url: field("url")?,
+        order: field("order")?,
+    })
+}
+
+/// Writes a tantivy full-text index of the pages into the directory `path`.
+///
+/// An existing index there is only replaced with `force`; any other existing directory
+/// is left alone.
+pub fn build_index(document: &Document, path: &Path, force: bool) -> Result<(), String> {
+    let error =
+        |e: tantivy::TantivyError| format!("Failed to write index {}: {}", path.display(), e);
+    if path.exists() {
+        if !force || !path.join("meta.json").exists() {
+            return Err(format!(
+                "{} already exists and is not an index that --force may replace",
+                path.display()
+            ));
+        }
+        fs::remove_dir_all(path)
+            .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+    }
+    fs::create_dir_all(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+
+    let (schema, fields) = schema();
+    let index = Index::create_in_dir(path, schema).map_err(error)?;
+    let mut writer: IndexWriter = index.writer(WRITER_MEMORY).map_err(error)?;
+    for chapter in &document.chapters {
+        let page = chapter.page;
+        writer
+            .add_document(doc!(
+                fields.title => page.title.clone().unwrap_or_default(),
+                fields.body => plain_text(page),
+                fields.url => page.url.as_str(),
+                fields.order => chapter.position as u64,
+            ))
+            .map_err(error)?;
+    }
+    writer.commit().map_err(error)?;
+    Ok(())
+}
+
+/// Prints the pages of the index at `path` that best match `query`, best first.
+///
+/// The query uses tantivy's syntax: words, "phrases", `title:word`, `+required -excluded`.
+pub fn search(path: &Path, query: &str, limit: usize) -> Result<(), String> {
+    let error = |e: tantivy::TantivyError| format!("Failed to search {}: {}", path.display(), e);
+    let index = Index::open_in_dir(path).map_err(error)?;
+    let fields = fields(&index.schema())?;
+    let searcher = index.reader().map_err(error)?.searcher();
+
+    let parser = QueryParser::for_index(&index, vec![fields.title, fields.body]);
+    let query = parser
+        .parse_query(query)
+        .map_err(|e| format!("Invalid query: {}", e))?;
+    let hits = searcher
+        .search(&query, &TopDocs::with_limit(limit).order_by_score())
+        .map_err(error)?;
+    if hits.is_empty() {
+        println!("No matches.");
+        return Ok(());
+    }
+
+    let mut snippets = SnippetGenerator::create(&searcher, &*query, fields.body).map_err(error)?;
+    snippets.set_max_num_chars(160);
+    for (rank, (score, address)) in hits.into_iter().enumerate() {
+        let document: TantivyDocument = searcher.doc(address).map_err(error)?;
+        let text = |field| {
+            document
+                .get_first(field)
+                .and_then(|value| value.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let order = document
+            .get_first(fields.order)
+            .and_then(|value| value.as_u64())
+            .unwrap_or_default();
+        println!(
+            "{:>3}. {} (chapter {}, score {:.2})",
+            rank + 1,
+            text(fields.title),
+            order + 1,
+            score
+        );
+        println!("     {}", text(fields.url));
+        let snippet = snippets.snippet_from_doc(&document);
+        let fragment = snippet.fragment().split_whitespace().collect::<Vec<_>>();
+        if !fragment.is_empty() {
+            println!("     {}", fragment.join(" "));
+        }
+    }
+    Ok(())
+}
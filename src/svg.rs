@@ -0,0 +1,162 @@
+use crate::assets::Assets;
+use crate::crawl::Page;
+use crate::output::escape_html;
+use regex::{Captures, Regex};
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+/// An `<img>` tag, up to the `>` outside its quoted attribute values.
+static IMG_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)<img\b(?:[^>"']|"[^"]*"|'[^']*')*>"#).unwrap());
+
+/// A CSS `url(#id)` reference to another element of the same SVG.
+static URL_REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"url\(\s*['"]?#([^)'"\s]+)['"]?\s*\)"#).unwrap());
+
+/// An id selector in a `<style>` element.
+static ID_SELECTOR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"#([A-Za-z_][\w-]*)").unwrap());
+
+/// Attributes of an `<img>` carried over to the SVG replacing it.
+const KEPT_ATTRIBUTES: &[&str] = &["width", "height", "class", "style"];
+
+/// Replaces every `<img>` showing a downloaded SVG with the SVG markup itself, for
+/// `--inline-svg`, so diagrams scale with the text and need no separate file.
+///
+/// Each SVG's ids get a prefix of their own, with the references to them rewritten, so
+/// two diagrams using the same ids cannot clash once they share a document. Scripts and
+/// event handlers are dropped.
+pub fn inline(pages: &mut [Page], assets: &Assets) {
+    let mut count = 0;
+    for page in pages {
+        let html = IMG_TAG.replace_all(&page.html, |tag: &Captures| {
+            let fragment = Html::parse_fragment(&tag[0]);
+            let img = fragment.select(&Selector::parse("img").unwrap()).next();
+            let svg = img.and_then(|img| {
+                let url = page.url.join(img.value().attr("src")?).ok()?;
+                let asset = assets
+                    .get(&url)
+                    .filter(|asset| asset.media_type == "image/svg+xml")?;
+                count += 1;
+                sanitize(
+                    &String::from_utf8_lossy(&asset.bytes),
+                    &format!("svg{}-", count),
+                    img,
+                )
+            });
+            svg.unwrap_or_else(|| tag[0].to_string())
+        });
+        page.html = html.into_owned();
+    }
+}
+
+/// The `<svg>` element of an SVG file, ready to embed in HTML in place of `img`.
+fn sanitize(source: &str, prefix: &str, img: ElementRef) -> Option<String> {
+    let fragment = Html::parse_fragment(source);
+    let svg = fragment.select(&Selector::parse("svg").unwrap()).next()?;
+    let ids = svg
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter_map(|element| element.value().attr("id"))
+        .collect::<HashSet<_>>();
+
+    let mut out = String::new();
+    write_element(svg, prefix, &ids, Some(img), &mut out);
+    Some(out)
+}
+
+fn write_element(
+    element: ElementRef,
+    prefix: &str,
+    ids: &HashSet<&str>,
+    img: Option<ElementRef>,
+    out: &mut String,
+) {
+    let name = element.value().name();
+    out.push('<');
+    out.push_str(name);
+    if img.is_some() {
+        out.push_str(r#" xmlns="http://www.w3.org/2000/svg""#);
+    }
+    // A size given on the `<img>` replaces both of the SVG's, keeping its aspect ratio.
+    let replaced = |attr: &str| {
+        img.is_some_and(|img| match attr {
+            "width" | "height" => {
+                img.value().attr("width").is_some() || img.value().attr("height").is_some()
+            }
+            _ => KEPT_ATTRIBUTES.contains(&attr) && img.value().attr(attr).is_some(),
+        })
+    };
+    for (attr, value) in element.value().attrs.iter() {
+        let local = &*attr.local;
+        // Namespace declarations are implied by the HTML parser; `xlink:href` is `href`.
+        if attr.prefix.as_deref() == Some("xmlns")
+            || local == "xmlns"
+            || local.starts_with("on")
+            || replaced(local)
+        {
+            continue;
+        }
+        let value = if local == "id" {
+            format!("{}{}", prefix, value)
+        } else if local == "href" && value.starts_with('#') {
+            format!("#{}{}", prefix, &value[1..])
+        } else {
+            rewrite_references(value, prefix)
+        };
+        out.push_str(&format!(" {}=\"{}\"", local, escape_html(&value)));
+    }
+    if let Some(img) = img {
+        for attr in KEPT_ATTRIBUTES {
+            if let Some(value) = img.value().attr(attr) {
+                out.push_str(&format!(" {}=\"{}\"", attr, escape_html(value)));
+            }
+        }
+        // An empty alt marks a decorative image, which screen readers skip.
+        match img.value().attr("alt").map(str::trim) {
+            Some("") => out.push_str(" aria-hidden=\"true\""),
+            Some(alt) => out.push_str(&format!(
+                " role=\"img\" aria-label=\"{}\"",
+                escape_html(alt)
+            )),
+            None => out.push_str(" role=\"img\""),
+        }
+    }
+    out.push('>');
+
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) if name == "style" => {
+                let css = rewrite_references(text, prefix);
+                let css = ID_SELECTOR.replace_all(&css, |id: &Captures| {
+                    if ids.contains(&id[1]) {
+                        format!("#{}{}", prefix, &id[1])
+                    } else {
+                        id[0].to_string()
+                    }
+                });
+                out.push_str(&css.replace('&', "&amp;").replace('<', "&lt;"));
+            }
+            Node::Text(text) => out.push_str(
+                &text
+                    .replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;"),
+            ),
+            Node::Element(child_element) if child_element.name() != "script" => {
+                write_element(ElementRef::wrap(child).unwrap(), prefix, ids, None, out);
+            }
+            _ => {}
+        }
+    }
+    out.push_str(&format!("</{}>", name));
+}
+
+/// The value with every `url(#id)` pointing at the prefixed id.
+fn rewrite_references(value: &str, prefix: &str) -> String {
+    URL_REFERENCE
+        .replace_all(value, |reference: &Captures| {
+            format!("url(#{}{})", prefix, &reference[1])
+        })
+        .into_owned()
+}
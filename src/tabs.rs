@@ -0,0 +1,98 @@
+use crate::crawl::Page;
+use crate::dom;
+use crate::output::escape_html;
+use scraper::{ElementRef, Html, Selector};
+use std::sync::LazyLock;
+
+/// Elements holding a set of tabs: Docusaurus, MkDocs Material (both markups),
+/// sphinx-tabs and sphinx-design.
+static TAB_SETS: LazyLock<Selector> = LazyLock::new(|| {
+    Selector::parse(".tabs-container, .tabbed-set, .sphinx-tabs, .sd-tab-set").unwrap()
+});
+
+/// The tab buttons of a set, when they are marked up as ARIA tabs.
+static ARIA_TABS: LazyLock<Selector> = LazyLock::new(|| Selector::parse("[role='tab']").unwrap());
+static ARIA_PANELS: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("[role='tabpanel']").unwrap());
+
+/// The labels and panels of a set built on radio buttons, as MkDocs and sphinx-design do.
+static LABELS: LazyLock<Selector> = LazyLock::new(|| Selector::parse("label").unwrap());
+static PANELS: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse(".tabbed-block, .tabbed-content, .sd-tab-content").unwrap());
+
+/// How deeply tab sets inside tabs are flattened.
+const MAX_NESTING: usize = 4;
+
+/// Flattens every set of tabs, such as a code sample in several languages, into its
+/// panels one after another, each headed by its tab's label in bold, so that every
+/// variant shows without the script that switches between them:
+///
+/// ```html
+/// <div class="tabs"><div class="tab"><p class="tab-label"><strong>Python</strong></p>...</div>...</div>
+/// ```
+pub fn flatten(pages: &mut [Page]) {
+    for page in pages {
+        if !["tabs", "tabbed", "tab-set"]
+            .iter()
+            .any(|marker| page.html.contains(marker))
+        {
+            continue;
+        }
+        // The innermost sets go first, so panels holding tabs are flattened already.
+        for _ in 0..MAX_NESTING {
+            let mut fragment = Html::parse_fragment(&page.html);
+            let flattened = fragment
+                .select(&TAB_SETS)
+                .filter(|set| set.select(&TAB_SETS).next().is_none())
+                .filter_map(|set| Some((set.id(), flatten_set(set)?)))
+                .collect::<Vec<_>>();
+            if flattened.is_empty() {
+                break;
+            }
+            for (id, html) in flattened {
+                dom::replace(&mut fragment.tree, id, &html);
+            }
+            page.html = fragment.root_element().inner_html();
+        }
+    }
+}
+
+/// The set's panels as labelled sections, or `None` if its tabs cannot be made out.
+fn flatten_set(set: ElementRef) -> Option<String> {
+    let (labels, panels) = if set.select(&ARIA_TABS).next().is_some() {
+        (
+            set.select(&ARIA_TABS).collect::<Vec<_>>(),
+            set.select(&ARIA_PANELS).collect::<Vec<_>>(),
+        )
+    } else {
+        // MkDocs' older markup nests each block in its content element.
+        let panels = set
+            .select(&PANELS)
+            .filter(|panel| {
+                !panel
+                    .value()
+                    .classes()
+                    .any(|class| class == "tabbed-content")
+                    || panel.select(&PANELS).next().is_none()
+            })
+            .collect::<Vec<_>>();
+        (set.select(&LABELS).collect::<Vec<_>>(), panels)
+    };
+    if labels.is_empty() || panels.is_empty() {
+        return None;
+    }
+
+    let tabs = labels
+        .iter()
+        .zip(&panels)
+        .map(|(label, panel)| {
+            let label = label.text().collect::<String>();
+            format!(
+                "<div class=\"tab\"><p class=\"tab-label\"><strong>{}</strong></p>{}</div>",
+                escape_html(label.trim()),
+                panel.inner_html()
+            )
+        })
+        .collect::<String>();
+    Some(format!("<div class=\"tabs\">{}</div>", tabs))
+}
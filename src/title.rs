@@ -0,0 +1,215 @@
+use crate::breadcrumbs::hierarchy;
+use crate::crawl::Page;
+use crate::dom;
+use crate::output::escape_html;
+use scraper::{ElementRef, Html, Selector};
+
+/// Separators sites commonly put between the page title and the site name.
+const TITLE_SEPARATORS: &[&str] = &[" - ", " | ", " — ", " – ", " · ", " :: "];
+
+/// What Doxygen puts between the project name and the page title.
+const PROJECT_SEPARATOR: &str = ": ";
+
+/// The text of the page's `<title>`, with whitespace collapsed. The " - Rust" rustdoc ends
+/// every title with is left out.
+pub fn page_title(document: &Html) -> Option<String> {
+    let selector = Selector::parse("head > title, title").unwrap();
+    let title = document
+        .select(&selector)
+        .next()
+        .map(|title| collapse_whitespace(&title.text().collect::<String>()))?;
+    let title = match rustdoc_crate(document) {
+        Some(_) => title.strip_suffix(" - Rust").unwrap_or(&title).to_string(),
+        None => title,
+    };
+    Some(title).filter(|title| !title.is_empty())
+}
+
+/// The text of the first `<h1>` in the content, for pages without a `<title>`.
+pub fn first_heading(content: ElementRef) -> Option<String> {
+    let selector = Selector::parse("h1").unwrap();
+    content
+        .select(&selector)
+        .map(|heading| collapse_whitespace(&heading.text().collect::<String>()))
+        .find(|heading| !heading.is_empty())
+}
+
+/// Starts every page whose content has no `<h1>` with one holding its chapter title, so
+/// each chapter of the book opens with its name in every output rather than running on
+/// from the one before.
+pub fn add_headings(pages: &mut [Page]) {
+    let site_title = site_title(&pages.iter().collect::<Vec<_>>());
+    for page in pages {
+        let Some(title) = &page.title else {
+            continue;
+        };
+        let fragment = Html::parse_fragment(&page.html);
+        if fragment
+            .select(&Selector::parse("h1").unwrap())
+            .next()
+            .is_some()
+        {
+            continue;
+        }
+        let heading = format!(
+            "<h1>{}</h1>\n",
+            escape_html(chapter_title(title, site_title.as_deref()))
+        );
+        page.html.insert_str(0, &heading);
+    }
+}
+
+/// Hierarchical numbers for the pages in reading order, from how deep each sits in the
+/// [`hierarchy`]: `1`, `1.1`, `1.2`, `2` and so on. A page is never numbered more than
+/// one level below the page before it, so a parent page that failed leaves no gap.
+pub fn chapter_numbers(pages: &[&Page]) -> Vec<String> {
+    let mut counters: Vec<usize> = Vec::new();
+    hierarchy(pages)
+        .into_iter()
+        .map(|(depth, _)| {
+            let depth = depth.min(counters.len());
+            counters.truncate(depth + 1);
+            if counters.len() == depth {
+                counters.push(0);
+            }
+            counters[depth] += 1;
+            counters
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(".")
+        })
+        .collect()
+}
+
+/// Puts each page's number from [`chapter_numbers`] at the start of its first `<h1>`, for
+/// `--number-chapters`, as `<span class="chapter-number">1.2</span>`.
+pub fn number_headings(pages: &mut [Page]) {
+    let numbers = chapter_numbers(&pages.iter().collect::<Vec<_>>());
+    let selector = Selector::parse("h1").unwrap();
+    for (page, number) in pages.iter_mut().zip(numbers) {
+        let mut fragment = Html::parse_fragment(&page.html);
+        let Some(heading) = fragment
+            .select(&selector)
+            .next()
+            .map(|heading| heading.id())
+        else {
+            continue;
+        };
+        let html = format!("<span class=\"chapter-number\">{}</span> ", number);
+        dom::prepend(&mut fragment.tree, heading, &html);
+        page.html = fragment.root_element().inner_html();
+    }
+}
+
+/// The site name declared with `<meta property="og:site_name">`, or for rustdoc pages the
+/// name of the crate, if any.
+pub fn site_name(document: &Html) -> Option<String> {
+    let selector = Selector::parse("meta[property='og:site_name']").unwrap();
+    document
+        .select(&selector)
+        .filter_map(|meta| meta.value().attr("content"))
+        .map(collapse_whitespace)
+        .find(|name| !name.is_empty())
+        .or_else(|| rustdoc_crate(document))
+}
+
+/// The crate a rustdoc page documents, from the settings rustdoc leaves for its script.
+fn rustdoc_crate(document: &Html) -> Option<String> {
+    let selector = Selector::parse("meta[name='rustdoc-vars'], #rustdoc-vars").unwrap();
+    document
+        .select(&selector)
+        .find_map(|vars| vars.value().attr("data-current-crate"))
+        .map(str::to_string)
+        .filter(|name| !name.is_empty())
+}
+
+/// Works out the title of the whole site or book from its pages.
+///
+/// Prefers a declared site name. Otherwise, when every page title ends with the same
+/// segment after a separator ("Ownership - The Rust Programming Language") or in
+/// parentheses ("String (Java SE 17)"), or every one starts with the same segment before a
+/// colon ("Acme: Widget Class Reference"), that segment is the site title. Failing both,
+/// the first page's title is used.
+pub fn site_title(pages: &[&Page]) -> Option<String> {
+    if let Some(name) = pages.iter().find_map(|page| page.site_name.clone()) {
+        return Some(name);
+    }
+
+    let titles = pages
+        .iter()
+        .filter_map(|page| page.title.as_deref())
+        .collect::<Vec<_>>();
+    let first = *titles.first()?;
+
+    if titles.len() > 1 {
+        for separator in TITLE_SEPARATORS {
+            if let Some((_, suffix)) = first.rsplit_once(separator)
+                && titles.iter().all(|title| {
+                    title.ends_with(&format!("{}{}", separator, suffix)) || *title == suffix
+                })
+            {
+                return Some(suffix.trim().to_string());
+            }
+        }
+        // Javadoc puts the title of the docs in parentheses instead.
+        if let Some((_, suffix)) = parenthesized(first)
+            && titles
+                .iter()
+                .all(|title| parenthesized(title).is_some_and(|(_, other)| other == suffix))
+        {
+            return Some(suffix.to_string());
+        }
+        // Doxygen puts the project name first, before a colon.
+        if let Some((prefix, _)) = first.split_once(PROJECT_SEPARATOR)
+            && titles
+                .iter()
+                .all(|title| title.starts_with(&format!("{}{}", prefix, PROJECT_SEPARATOR)))
+        {
+            return Some(prefix.trim().to_string());
+        }
+    }
+
+    Some(first.to_string())
+}
+
+/// A page title without the site title it ends or, for Doxygen, starts with, e.g.
+/// "Ownership" for "Ownership - The Rust Programming Language", "String" for
+/// "String (Java SE 17)" or "Widget Class Reference" for "Acme: Widget Class Reference".
+pub fn chapter_title<'a>(title: &'a str, site_title: Option<&str>) -> &'a str {
+    let Some(site_title) = site_title else {
+        return title;
+    };
+    TITLE_SEPARATORS
+        .iter()
+        .find_map(|separator| title.strip_suffix(&format!("{}{}", separator, site_title)))
+        .or_else(|| {
+            parenthesized(title)
+                .filter(|(_, suffix)| *suffix == site_title)
+                .map(|(chapter, _)| chapter)
+        })
+        .or_else(|| title.strip_prefix(&format!("{}{}", site_title, PROJECT_SEPARATOR)))
+        .map(str::trim)
+        .filter(|chapter| !chapter.is_empty())
+        .unwrap_or(title)
+}
+
+/// A title split before the parenthesized part it ends with, both trimmed, e.g. "String"
+/// and "Java Platform SE 8" for "String (Java Platform SE 8 )".
+fn parenthesized(title: &str) -> Option<(&str, &str)> {
+    let (chapter, suffix) = title.strip_suffix(')')?.rsplit_once(" (")?;
+    Some((chapter.trim(), suffix.trim()))
+}
+
+/// Lowercase ASCII letters and digits joined by underscores, e.g. `the_rust_programming_language`.
+pub fn slugify(text: &str) -> String {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,57 @@
+use crate::locale::is_language_segment;
+use regex::Regex;
+use std::sync::LazyLock;
+use url::Url;
+
+/// Path segments that name a release of versioned documentation, e.g. `/stable/`,
+/// `/nightly/`, `/1.70.0/`, `/v2.1/` or `/3.x/`.
+static VERSION_SEGMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(stable|beta|nightly|latest|dev|current|master|main|v?\d+(\.\d+){0,2}(\.x)?)$")
+        .unwrap()
+});
+
+/// Hosts of Read the Docs, whose projects are served at `/<language>/<version>/`.
+const READ_THE_DOCS_HOSTS: &[&str] = &[".readthedocs.io", ".readthedocs-hosted.com", ".rtfd.io"];
+
+/// Index of the first path segment that looks like a documentation version. On Read the
+/// Docs that is the segment after the language, whatever the version is called, such as a
+/// branch name.
+fn version_segment(url: &Url) -> Option<(usize, &str)> {
+    let mut segments = url.path_segments()?;
+    let read_the_docs = url.host_str().is_some_and(|host| {
+        READ_THE_DOCS_HOSTS
+            .iter()
+            .any(|suffix| host.ends_with(suffix))
+    });
+    if read_the_docs
+        && segments.next().is_some_and(is_language_segment)
+        && let Some(version) = segments.next().filter(|segment| !segment.is_empty())
+    {
+        return Some((1, version));
+    }
+    url.path_segments()?
+        .enumerate()
+        .find(|(_, segment)| VERSION_SEGMENT.is_match(segment))
+}
+
+/// The documentation version named in the URL's path, if any.
+pub fn doc_version(url: &Url) -> Option<&str> {
+    version_segment(url).map(|(_, segment)| segment)
+}
+
+/// Replaces the version segment of `url` with `version`.
+pub fn with_doc_version(url: &Url, version: &str) -> Result<Url, String> {
+    let (index, _) = version_segment(url)
+        .ok_or_else(|| format!("Could not find a version segment to replace in {}", url))?;
+    let segments = url
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, segment)| if i == index { version } else { segment })
+        .collect::<Vec<_>>();
+
+    let mut rewritten = url.clone();
+    rewritten.set_path(&segments.join("/"));
+    Ok(rewritten)
+}